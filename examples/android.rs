@@ -17,8 +17,15 @@ fn main() {
 
     match config.sample_format() {
         cpal::SampleFormat::F32 => run::<f32>(&device, &config.into()).unwrap(),
+        cpal::SampleFormat::F64 => run::<f64>(&device, &config.into()).unwrap(),
         cpal::SampleFormat::I16 => run::<i16>(&device, &config.into()).unwrap(),
         cpal::SampleFormat::U16 => run::<u16>(&device, &config.into()).unwrap(),
+        cpal::SampleFormat::I32 => run::<i32>(&device, &config.into()).unwrap(),
+        cpal::SampleFormat::U32 => run::<u32>(&device, &config.into()).unwrap(),
+        cpal::SampleFormat::I24 => run::<cpal::I24>(&device, &config.into()).unwrap(),
+        cpal::SampleFormat::I24In32 => run::<cpal::I24In32>(&device, &config.into()).unwrap(),
+        cpal::SampleFormat::I8 => run::<i8>(&device, &config.into()).unwrap(),
+        cpal::SampleFormat::U8 => run::<u8>(&device, &config.into()).unwrap(),
     }
 }
 
@@ -85,8 +85,15 @@ fn main() -> anyhow::Result<()> {
 
     match config.sample_format() {
         cpal::SampleFormat::F32 => run::<f32>(&device, &config.into()),
+        cpal::SampleFormat::F64 => run::<f64>(&device, &config.into()),
         cpal::SampleFormat::I16 => run::<i16>(&device, &config.into()),
         cpal::SampleFormat::U16 => run::<u16>(&device, &config.into()),
+        cpal::SampleFormat::I32 => run::<i32>(&device, &config.into()),
+        cpal::SampleFormat::U32 => run::<u32>(&device, &config.into()),
+        cpal::SampleFormat::I24 => run::<cpal::I24>(&device, &config.into()),
+        cpal::SampleFormat::I24In32 => run::<cpal::I24In32>(&device, &config.into()),
+        cpal::SampleFormat::I8 => run::<i8>(&device, &config.into()),
+        cpal::SampleFormat::U8 => run::<u8>(&device, &config.into()),
     }
 }
 
@@ -0,0 +1,130 @@
+//! Feeds the input of a device back into its output through a single full-duplex stream.
+//!
+//! Unlike the `feedback` example, no ring buffer is needed: each callback receives the input and
+//! writes the output for the same frames. The first input channel is copied to every output
+//! channel.
+
+extern crate anyhow;
+extern crate clap;
+extern crate cpal;
+
+use clap::arg;
+use cpal::traits::{DeviceTrait, HostTrait, StreamTrait};
+
+#[derive(Debug)]
+struct Opt {
+    #[cfg(all(
+        any(target_os = "linux", target_os = "dragonfly", target_os = "freebsd"),
+        feature = "jack"
+    ))]
+    jack: bool,
+
+    device: String,
+}
+
+impl Opt {
+    fn from_args() -> Self {
+        let app = clap::App::new("duplex").arg(arg!([DEVICE] "The audio device to use"));
+        #[cfg(all(
+            any(target_os = "linux", target_os = "dragonfly", target_os = "freebsd"),
+            feature = "jack"
+        ))]
+        let app = app.arg(arg!(-j --jack "Use the JACK host"));
+        let matches = app.get_matches();
+        let device = matches.value_of("DEVICE").unwrap_or("default").to_string();
+
+        #[cfg(all(
+            any(target_os = "linux", target_os = "dragonfly", target_os = "freebsd"),
+            feature = "jack"
+        ))]
+        return Opt {
+            jack: matches.is_present("jack"),
+            device,
+        };
+
+        #[cfg(any(
+            not(any(target_os = "linux", target_os = "dragonfly", target_os = "freebsd")),
+            not(feature = "jack")
+        ))]
+        Opt { device }
+    }
+}
+
+fn main() -> anyhow::Result<()> {
+    let opt = Opt::from_args();
+
+    // Conditionally compile with jack if the feature is specified.
+    #[cfg(all(
+        any(target_os = "linux", target_os = "dragonfly", target_os = "freebsd"),
+        feature = "jack"
+    ))]
+    // Manually check for flags. Can be passed through cargo with -- e.g.
+    // cargo run --release --example duplex --features jack -- --jack
+    let host = if opt.jack {
+        cpal::host_from_id(cpal::available_hosts()
+            .into_iter()
+            .find(|id| *id == cpal::HostId::Jack)
+            .expect(
+                "make sure --features jack is specified. only works on OSes where jack is available",
+            )).expect("jack host unavailable")
+    } else {
+        cpal::default_host()
+    };
+
+    #[cfg(any(
+        not(any(target_os = "linux", target_os = "dragonfly", target_os = "freebsd")),
+        not(feature = "jack")
+    ))]
+    let host = cpal::default_host();
+
+    let device = if opt.device == "default" {
+        host.default_output_device()
+    } else {
+        host.output_devices()?
+            .find(|x| x.name().map(|y| y == opt.device).unwrap_or(false))
+    }
+    .expect("failed to find device");
+    println!("Using device: \"{}\"", device.name()?);
+
+    let input_config = device.default_input_config()?;
+    let output_config = device.default_output_config()?;
+    let config = cpal::DuplexStreamConfig {
+        input_channels: input_config.channels(),
+        output_channels: output_config.channels(),
+        sample_rate: output_config.sample_rate(),
+        buffer_size: cpal::BufferSize::Default,
+        strict: false,
+    };
+    let input_channels = config.input_channels as usize;
+    let output_channels = config.output_channels as usize;
+
+    println!(
+        "Attempting to build a duplex stream with f32 samples and `{:?}`.",
+        config
+    );
+    let stream = device.build_duplex_stream(
+        &config,
+        move |input: &[f32], output: &mut [f32], _: &cpal::DuplexCallbackInfo| {
+            let frames = input.chunks(input_channels);
+            for (in_frame, out_frame) in frames.zip(output.chunks_mut(output_channels)) {
+                for sample in out_frame {
+                    *sample = in_frame[0];
+                }
+            }
+        },
+        err_fn,
+    )?;
+    println!("Successfully built stream.");
+    stream.play()?;
+
+    // Run for 3 seconds before closing.
+    println!("Playing for 3 seconds... ");
+    std::thread::sleep(std::time::Duration::from_secs(3));
+    drop(stream);
+    println!("Done!");
+    Ok(())
+}
+
+fn err_fn(err: cpal::StreamError) {
+    eprintln!("an error occurred on stream: {}", err);
+}
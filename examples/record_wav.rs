@@ -117,6 +117,11 @@ fn main() -> Result<(), anyhow::Error> {
             move |data, _: &_| write_input_data::<f32, f32>(data, &writer_2),
             err_fn,
         )?,
+        cpal::SampleFormat::F64 => device.build_input_stream(
+            &config.into(),
+            move |data, _: &_| write_input_data::<f64, f32>(data, &writer_2),
+            err_fn,
+        )?,
         cpal::SampleFormat::I16 => device.build_input_stream(
             &config.into(),
             move |data, _: &_| write_input_data::<i16, i16>(data, &writer_2),
@@ -127,6 +132,36 @@ fn main() -> Result<(), anyhow::Error> {
             move |data, _: &_| write_input_data::<u16, i16>(data, &writer_2),
             err_fn,
         )?,
+        cpal::SampleFormat::I32 => device.build_input_stream(
+            &config.into(),
+            move |data, _: &_| write_input_data::<i32, i32>(data, &writer_2),
+            err_fn,
+        )?,
+        cpal::SampleFormat::U32 => device.build_input_stream(
+            &config.into(),
+            move |data, _: &_| write_input_data::<u32, i32>(data, &writer_2),
+            err_fn,
+        )?,
+        cpal::SampleFormat::I24 => device.build_input_stream(
+            &config.into(),
+            move |data, _: &_| write_input_data::<cpal::I24, i32>(data, &writer_2),
+            err_fn,
+        )?,
+        cpal::SampleFormat::I24In32 => device.build_input_stream(
+            &config.into(),
+            move |data, _: &_| write_input_data::<cpal::I24In32, i32>(data, &writer_2),
+            err_fn,
+        )?,
+        cpal::SampleFormat::I8 => device.build_input_stream(
+            &config.into(),
+            move |data, _: &_| write_input_data::<i8, i8>(data, &writer_2),
+            err_fn,
+        )?,
+        cpal::SampleFormat::U8 => device.build_input_stream(
+            &config.into(),
+            move |data, _: &_| write_input_data::<u8, i8>(data, &writer_2),
+            err_fn,
+        )?,
     };
 
     stream.play()?;
@@ -141,9 +176,12 @@ fn main() -> Result<(), anyhow::Error> {
 
 fn sample_format(format: cpal::SampleFormat) -> hound::SampleFormat {
     match format {
+        cpal::SampleFormat::I8 | cpal::SampleFormat::U8 => hound::SampleFormat::Int,
         cpal::SampleFormat::U16 => hound::SampleFormat::Int,
+        cpal::SampleFormat::I32 | cpal::SampleFormat::U32 => hound::SampleFormat::Int,
+        cpal::SampleFormat::I24 | cpal::SampleFormat::I24In32 => hound::SampleFormat::Int,
         cpal::SampleFormat::I16 => hound::SampleFormat::Int,
-        cpal::SampleFormat::F32 => hound::SampleFormat::Float,
+        cpal::SampleFormat::F32 | cpal::SampleFormat::F64 => hound::SampleFormat::Float,
     }
 }
 
@@ -151,7 +189,13 @@ fn wav_spec_from_config(config: &cpal::SupportedStreamConfig) -> hound::WavSpec
     hound::WavSpec {
         channels: config.channels() as _,
         sample_rate: config.sample_rate().0 as _,
-        bits_per_sample: (config.sample_format().sample_size() * 8) as _,
+        bits_per_sample: match config.sample_format() {
+            // 24-bit samples are written as 32-bit integers by `write_input_data`.
+            cpal::SampleFormat::I24 | cpal::SampleFormat::I24In32 => 32,
+            // `hound` does not support 64-bit floats, so they are written as 32-bit floats.
+            cpal::SampleFormat::F64 => 32,
+            sample_format => (sample_format.sample_size() * 8) as _,
+        },
         sample_format: sample_format(config.sample_format()),
     }
 }
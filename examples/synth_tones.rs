@@ -44,8 +44,17 @@ where
 
     match config.sample_format() {
         cpal::SampleFormat::F32 => stream_make::<f32, _>(&device, &config.into(), on_sample),
+        cpal::SampleFormat::F64 => stream_make::<f64, _>(&device, &config.into(), on_sample),
         cpal::SampleFormat::I16 => stream_make::<i16, _>(&device, &config.into(), on_sample),
         cpal::SampleFormat::U16 => stream_make::<u16, _>(&device, &config.into(), on_sample),
+        cpal::SampleFormat::I32 => stream_make::<i32, _>(&device, &config.into(), on_sample),
+        cpal::SampleFormat::U32 => stream_make::<u32, _>(&device, &config.into(), on_sample),
+        cpal::SampleFormat::I24 => stream_make::<cpal::I24, _>(&device, &config.into(), on_sample),
+        cpal::SampleFormat::I24In32 => {
+            stream_make::<cpal::I24In32, _>(&device, &config.into(), on_sample)
+        }
+        cpal::SampleFormat::I8 => stream_make::<i8, _>(&device, &config.into(), on_sample),
+        cpal::SampleFormat::U8 => stream_make::<u8, _>(&device, &config.into(), on_sample),
     }
 }
 
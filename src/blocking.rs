@@ -0,0 +1,571 @@
+//! Streams that are read from and written to by blocking calls, rather than through a callback.
+//!
+//! These suit code written against a blocking model, such as OSS or PortAudio's
+//! `Pa_WriteStream`. Each one wraps a regular callback stream built through `DeviceTrait`, and so
+//! works with every host. The callback moves samples in and out of a queue, which `write` fills
+//! and `read` drains, waiting for space or data as necessary.
+//!
+//! When the callback finds the queue of an output stream empty, or that of an input stream full,
+//! the stream counts an xrun and plays silence or drops the input respectively. The queue is a
+//! lock-free ring buffer, so the callback never waits for a read or write in progress. Only whole
+//! frames are moved between the queue and the callback, so reads and writes should consist of
+//! whole frames too.
+
+use crate::traits::{DeviceTrait, StreamTrait};
+use crate::{
+    BuildStreamError, ChannelCount, FrameCount, InputCallbackInfo, OutputCallbackInfo,
+    PlayStreamError, Sample, StreamConfig, StreamError,
+};
+use std::cell::UnsafeCell;
+use std::sync::atomic::{fence, AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Arc, Condvar, Mutex, MutexGuard};
+use std::task::Waker;
+use std::time::{Duration, Instant};
+
+/// An output stream that plays the samples passed to `write`.
+pub struct OutputStream<T> {
+    stream: Box<dyn StreamTrait>,
+    shared: Arc<Shared<T>>,
+}
+
+/// An input stream whose captured samples are retrieved with `read`.
+pub struct InputStream<T> {
+    stream: Box<dyn StreamTrait>,
+    shared: Arc<Shared<T>>,
+}
+
+impl<T> OutputStream<T>
+where
+    T: Sample + Send + 'static,
+{
+    /// Build and start an output stream on `device`, queueing up to `capacity` frames.
+    ///
+    /// A larger queue tolerates longer gaps between writes, at the cost of latency.
+    pub fn new<D>(
+        device: &D,
+        config: &StreamConfig,
+        capacity: FrameCount,
+    ) -> Result<Self, BuildStreamError>
+    where
+        D: DeviceTrait,
+        D::Stream: 'static,
+    {
+        let shared = Arc::new(Shared::new(config.channels, capacity));
+        let stream = build_output_stream(device, config, &shared)?;
+        Ok(OutputStream { stream, shared })
+    }
+
+    /// Queue all of `samples`, waiting for the stream to make room as necessary.
+    pub fn write(&self, samples: &[T]) -> Result<(), StreamError> {
+        self.shared.write(samples, None).map(|_| ())
+    }
+
+    /// Like `write`, but gives up once `timeout` has elapsed. Returns the number of samples that
+    /// were queued.
+    pub fn write_timeout(&self, samples: &[T], timeout: Duration) -> Result<usize, StreamError> {
+        self.shared.write(samples, Some(Instant::now() + timeout))
+    }
+
+    /// The number of samples that can be written without blocking.
+    pub fn available(&self) -> usize {
+        self.shared.space()
+    }
+
+    /// The number of times the queue ran dry since the first write, each heard as a gap.
+    pub fn xruns(&self) -> usize {
+        self.shared.xruns()
+    }
+
+    /// The underlying callback stream, e.g. for pausing it or querying its latency.
+    pub fn stream(&self) -> &dyn StreamTrait {
+        &*self.stream
+    }
+}
+
+impl<T> InputStream<T>
+where
+    T: Sample + Send + 'static,
+{
+    /// Build and start an input stream on `device`, queueing up to `capacity` frames.
+    ///
+    /// A larger queue tolerates longer gaps between reads, at the cost of memory.
+    pub fn new<D>(
+        device: &D,
+        config: &StreamConfig,
+        capacity: FrameCount,
+    ) -> Result<Self, BuildStreamError>
+    where
+        D: DeviceTrait,
+        D::Stream: 'static,
+    {
+        let shared = Arc::new(Shared::new(config.channels, capacity));
+        let stream = build_input_stream(device, config, &shared)?;
+        Ok(InputStream { stream, shared })
+    }
+
+    /// Fill all of `samples`, waiting for the stream to capture more as necessary.
+    pub fn read(&self, samples: &mut [T]) -> Result<(), StreamError> {
+        self.shared.read(samples, None).map(|_| ())
+    }
+
+    /// Like `read`, but gives up once `timeout` has elapsed. Returns the number of samples that
+    /// were read.
+    pub fn read_timeout(&self, samples: &mut [T], timeout: Duration) -> Result<usize, StreamError> {
+        self.shared.read(samples, Some(Instant::now() + timeout))
+    }
+
+    /// The number of samples that can be read without blocking.
+    pub fn available(&self) -> usize {
+        self.shared.queued()
+    }
+
+    /// The number of times captured frames were dropped because the queue was full.
+    pub fn xruns(&self) -> usize {
+        self.shared.xruns()
+    }
+
+    /// The underlying callback stream, e.g. for pausing it or querying its latency.
+    pub fn stream(&self) -> &dyn StreamTrait {
+        &*self.stream
+    }
+}
+
+// Builds and starts an output stream that plays from the queue of `shared`.
+pub(crate) fn build_output_stream<D, T>(
+    device: &D,
+    config: &StreamConfig,
+    shared: &Arc<Shared<T>>,
+) -> Result<Box<dyn StreamTrait>, BuildStreamError>
+where
+    D: DeviceTrait,
+    D::Stream: 'static,
+    T: Sample + Send + 'static,
+{
+    let data_shared = shared.clone();
+    let error_shared = shared.clone();
+    let stream = device.build_output_stream(
+        config,
+        move |data: &mut [T], _: &OutputCallbackInfo| data_shared.play(data),
+        move |err| error_shared.report(err),
+    )?;
+    play(&stream)?;
+    Ok(Box::new(stream))
+}
+
+// Builds and starts an input stream that captures into the queue of `shared`.
+pub(crate) fn build_input_stream<D, T>(
+    device: &D,
+    config: &StreamConfig,
+    shared: &Arc<Shared<T>>,
+) -> Result<Box<dyn StreamTrait>, BuildStreamError>
+where
+    D: DeviceTrait,
+    D::Stream: 'static,
+    T: Sample + Send + 'static,
+{
+    let data_shared = shared.clone();
+    let error_shared = shared.clone();
+    let stream = device.build_input_stream(
+        config,
+        move |data: &[T], _: &InputCallbackInfo| data_shared.capture(data),
+        move |err| error_shared.report(err),
+    )?;
+    play(&stream)?;
+    Ok(Box::new(stream))
+}
+
+fn play<S: StreamTrait>(stream: &S) -> Result<(), BuildStreamError> {
+    stream.play().map_err(|err| match err {
+        PlayStreamError::DeviceNotAvailable => BuildStreamError::DeviceNotAvailable,
+        PlayStreamError::BackendSpecific { err } => err.into(),
+    })
+}
+
+// The state shared between a blocking or asynchronous stream and its callback.
+//
+// Samples pass through a ring that the callback never waits for. The stream's owner and its
+// callback are each a single thread at a time, so an output stream's owner is the ring's only
+// producer and its callback the only consumer, and the other way around for an input stream.
+pub(crate) struct Shared<T> {
+    ring: Ring<T>,
+    channels: usize,
+    xruns: AtomicUsize,
+    // Set by the first write, so that the silence an output stream plays before it is no xrun.
+    written: AtomicBool,
+    // Set by the owner before it waits for the callback, so that the callback only locks `state`
+    // when there is someone to wake.
+    waiting: AtomicBool,
+    state: Mutex<State>,
+    // Notified whenever the callback moves samples in or out of the ring, or reports an error.
+    changed: Condvar,
+}
+
+pub(crate) struct State {
+    error: Option<StreamError>,
+    // Woken along with `changed`, for streams polled by an executor.
+    waker: Option<Waker>,
+}
+
+impl<T: Sample> Shared<T> {
+    pub(crate) fn new(channels: ChannelCount, capacity: FrameCount) -> Self {
+        Shared {
+            ring: Ring::new(capacity as usize * channels as usize),
+            channels: channels as usize,
+            xruns: AtomicUsize::new(0),
+            written: AtomicBool::new(false),
+            waiting: AtomicBool::new(false),
+            state: Mutex::new(State {
+                error: None,
+                waker: None,
+            }),
+            changed: Condvar::new(),
+        }
+    }
+
+    fn lock(&self) -> MutexGuard<'_, State> {
+        self.state.lock().expect("blocking stream state poisoned")
+    }
+
+    pub(crate) fn xruns(&self) -> usize {
+        self.xruns.load(Ordering::Relaxed)
+    }
+
+    pub(crate) fn mark_written(&self) {
+        self.written.store(true, Ordering::Relaxed);
+    }
+
+    #[cfg(feature = "futures")]
+    pub(crate) fn error(&self) -> Option<StreamError> {
+        self.lock().error.clone()
+    }
+
+    // The number of samples in the ring.
+    pub(crate) fn queued(&self) -> usize {
+        self.ring.len()
+    }
+
+    // The number of samples that fit in the ring.
+    pub(crate) fn space(&self) -> usize {
+        self.ring.capacity() - self.ring.len()
+    }
+
+    // Queues as many of `samples` as fit, as the owner of an output stream.
+    pub(crate) fn enqueue(&self, samples: &[T]) -> usize {
+        unsafe { self.ring.push(samples) }
+    }
+
+    // Fills as much of `samples` as is queued, as the owner of an input stream.
+    pub(crate) fn dequeue(&self, samples: &mut [T]) -> usize {
+        unsafe { self.ring.pop(samples) }
+    }
+
+    // Has the callback wake `waker` once it makes progress, unless `ready` already holds or an
+    // error was reported, in which case returns `true` for the caller to look again.
+    #[cfg(feature = "futures")]
+    pub(crate) fn register<F>(&self, waker: &Waker, ready: F) -> bool
+    where
+        F: Fn() -> bool,
+    {
+        let mut state = self.lock();
+        state.waker = Some(waker.clone());
+        self.waiting.store(true, Ordering::SeqCst);
+        fence(Ordering::SeqCst);
+        ready() || state.error.is_some()
+    }
+
+    // Waits for `ready` to hold, returning `false` once `deadline` has passed. `ready` is checked
+    // after setting `waiting`, so that the callback cannot make it hold unnoticed.
+    fn wait<F>(&self, ready: F, deadline: Option<Instant>) -> Result<bool, StreamError>
+    where
+        F: Fn() -> bool,
+    {
+        let mut state = self.lock();
+        loop {
+            if let Some(ref err) = state.error {
+                return Err(err.clone());
+            }
+            self.waiting.store(true, Ordering::SeqCst);
+            fence(Ordering::SeqCst);
+            if ready() {
+                return Ok(true);
+            }
+            state = match deadline {
+                None => self.changed.wait(state).ok(),
+                Some(deadline) => {
+                    let now = Instant::now();
+                    if now >= deadline {
+                        return Ok(false);
+                    }
+                    self.changed
+                        .wait_timeout(state, deadline - now)
+                        .ok()
+                        .map(|(state, _)| state)
+                }
+            }
+            .expect("blocking stream state poisoned");
+        }
+    }
+
+    // Wakes the owner if it waits for the callback. The owner holds the lock only while checking
+    // whether to wait, so the callback is never kept waiting long.
+    fn notify(&self) {
+        fence(Ordering::SeqCst);
+        if self.waiting.swap(false, Ordering::SeqCst) {
+            let waker = self.lock().waker.take();
+            self.changed.notify_all();
+            if let Some(waker) = waker {
+                waker.wake();
+            }
+        }
+    }
+
+    fn write(&self, samples: &[T], deadline: Option<Instant>) -> Result<usize, StreamError> {
+        self.mark_written();
+        let mut written = 0;
+        loop {
+            let ready = || written == samples.len() || self.space() > 0;
+            if !self.wait(ready, deadline)? {
+                return Ok(written);
+            }
+            written += self.enqueue(&samples[written..]);
+            if written == samples.len() {
+                return Ok(written);
+            }
+        }
+    }
+
+    fn read(&self, samples: &mut [T], deadline: Option<Instant>) -> Result<usize, StreamError> {
+        let len = samples.len();
+        let mut read = 0;
+        loop {
+            let ready = || read == len || self.queued() > 0;
+            if !self.wait(ready, deadline)? {
+                return Ok(read);
+            }
+            read += self.dequeue(&mut samples[read..]);
+            if read == len {
+                return Ok(read);
+            }
+        }
+    }
+
+    // The output callback, playing whole frames from the ring and silence once it runs dry.
+    pub(crate) fn play(&self, data: &mut [T]) {
+        let queued = self.queued();
+        let n = data.len().min(queued - queued % self.channels);
+        unsafe { self.ring.pop(&mut data[..n]) };
+        for sample in &mut data[n..] {
+            *sample = T::EQUILIBRIUM;
+        }
+        if n < data.len() && self.written.load(Ordering::Relaxed) {
+            self.xruns.fetch_add(1, Ordering::Relaxed);
+        }
+        self.notify();
+    }
+
+    // The input callback, queueing whole frames and dropping those that do not fit.
+    pub(crate) fn capture(&self, data: &[T]) {
+        let space = self.space();
+        let n = data.len().min(space - space % self.channels);
+        unsafe { self.ring.push(&data[..n]) };
+        if n < data.len() {
+            self.xruns.fetch_add(1, Ordering::Relaxed);
+        }
+        self.notify();
+    }
+
+    pub(crate) fn report(&self, err: StreamError) {
+        let waker = {
+            let mut state = self.lock();
+            state.error = Some(err);
+            state.waker.take()
+        };
+        self.changed.notify_all();
+        if let Some(waker) = waker {
+            waker.wake();
+        }
+    }
+}
+
+// A single-producer, single-consumer queue over a preallocated buffer, with one slot always left
+// empty to tell a full ring from an empty one. The producer advances `tail` and the consumer
+// `head`, each releasing the slots it is done with to the other.
+struct Ring<T> {
+    slots: Box<[UnsafeCell<T>]>,
+    head: AtomicUsize,
+    tail: AtomicUsize,
+}
+
+// The producer and consumer never access the same slot at once.
+unsafe impl<T: Send> Sync for Ring<T> {}
+
+impl<T: Sample> Ring<T> {
+    fn new(capacity: usize) -> Self {
+        Ring {
+            slots: (0..capacity + 1)
+                .map(|_| UnsafeCell::new(T::EQUILIBRIUM))
+                .collect(),
+            head: AtomicUsize::new(0),
+            tail: AtomicUsize::new(0),
+        }
+    }
+
+    fn capacity(&self) -> usize {
+        self.slots.len() - 1
+    }
+
+    fn len(&self) -> usize {
+        let head = self.head.load(Ordering::Acquire);
+        let tail = self.tail.load(Ordering::Acquire);
+        (tail + self.slots.len() - head) % self.slots.len()
+    }
+
+    // Queues as many of `samples` as fit, returning how many did.
+    //
+    // Safety: must not be called by more than one thread at once.
+    unsafe fn push(&self, samples: &[T]) -> usize {
+        let head = self.head.load(Ordering::Acquire);
+        let tail = self.tail.load(Ordering::Relaxed);
+        let len = (tail + self.slots.len() - head) % self.slots.len();
+        let n = samples.len().min(self.capacity() - len);
+        for (i, &sample) in samples[..n].iter().enumerate() {
+            *self.slots[(tail + i) % self.slots.len()].get() = sample;
+        }
+        self.tail
+            .store((tail + n) % self.slots.len(), Ordering::Release);
+        n
+    }
+
+    // Fills as much of `samples` as is queued, returning how many samples were.
+    //
+    // Safety: must not be called by more than one thread at once.
+    unsafe fn pop(&self, samples: &mut [T]) -> usize {
+        let head = self.head.load(Ordering::Relaxed);
+        let tail = self.tail.load(Ordering::Acquire);
+        let len = (tail + self.slots.len() - head) % self.slots.len();
+        let n = samples.len().min(len);
+        for (i, sample) in samples[..n].iter_mut().enumerate() {
+            *sample = *self.slots[(head + i) % self.slots.len()].get();
+        }
+        self.head
+            .store((head + n) % self.slots.len(), Ordering::Release);
+        n
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::Shared;
+    use crate::StreamError;
+    use std::sync::Arc;
+    use std::thread;
+    use std::time::{Duration, Instant};
+
+    #[test]
+    fn output_underflow() {
+        let shared = Shared::<i16>::new(2, 4);
+        let mut data = [7; 4];
+        // Silence before the first write is not an xrun.
+        shared.play(&mut data);
+        assert_eq!(data, [0; 4]);
+        assert_eq!(shared.xruns(), 0);
+
+        let samples = [1, 2, 3, 4, 5, 6, 7, 8, 9, 10];
+        let deadline = Some(Instant::now() + Duration::from_millis(10));
+        assert_eq!(shared.write(&samples, deadline).unwrap(), 8);
+        shared.play(&mut data);
+        assert_eq!(data, [1, 2, 3, 4]);
+        assert_eq!(shared.write(&samples[8..9], None).unwrap(), 1);
+        let mut data = [7; 8];
+        shared.play(&mut data);
+        assert_eq!(data, [5, 6, 7, 8, 0, 0, 0, 0]);
+        assert_eq!(shared.xruns(), 1);
+        // The partial frame stays queued until it is completed.
+        assert_eq!(shared.queued(), 1);
+    }
+
+    #[test]
+    fn input_overflow() {
+        let shared = Shared::<f32>::new(2, 2);
+        shared.capture(&[1.0, 2.0, 3.0, 4.0, 5.0, 6.0]);
+        assert_eq!(shared.xruns(), 1);
+        let mut samples = [0.0; 6];
+        let deadline = Some(Instant::now() + Duration::from_millis(10));
+        assert_eq!(shared.read(&mut samples, deadline).unwrap(), 4);
+        assert_eq!(samples, [1.0, 2.0, 3.0, 4.0, 0.0, 0.0]);
+    }
+
+    #[test]
+    fn locked() {
+        // The callbacks move samples while the owner holds the lock, e.g. to wait.
+        let output = Shared::<i16>::new(1, 4);
+        output.mark_written();
+        assert_eq!(output.enqueue(&[1, 2]), 2);
+        let input = Shared::<i16>::new(1, 4);
+        let output_state = output.lock();
+        let input_state = input.lock();
+        let mut data = [7; 2];
+        output.play(&mut data);
+        input.capture(&[3, 4]);
+        drop((output_state, input_state));
+        assert_eq!(data, [1, 2]);
+        assert_eq!(output.xruns() + input.xruns(), 0);
+        let mut samples = [0; 2];
+        assert_eq!(input.read(&mut samples, None).unwrap(), 2);
+        assert_eq!(samples, [3, 4]);
+    }
+
+    #[test]
+    fn threads() {
+        let shared = Arc::new(Shared::<i32>::new(2, 8));
+        let callback_shared = shared.clone();
+        let callback = thread::spawn(move || {
+            let samples: Vec<i32> = (0..10_000).collect();
+            for chunk in samples.chunks(6) {
+                while callback_shared.space() < chunk.len() {
+                    thread::yield_now();
+                }
+                callback_shared.capture(chunk);
+            }
+        });
+        let mut samples = vec![0; 10_000];
+        shared.read(&mut samples, None).unwrap();
+        callback.join().unwrap();
+        assert!(samples.iter().copied().eq(0..10_000));
+        assert_eq!(shared.xruns(), 0);
+    }
+
+    #[test]
+    fn stream_error() {
+        let shared = Shared::<f32>::new(1, 16);
+        shared.report(StreamError::DeviceNotAvailable);
+        assert!(matches!(
+            shared.write(&[0.0], None),
+            Err(StreamError::DeviceNotAvailable)
+        ));
+        assert!(matches!(
+            shared.read(&mut [0.0], None),
+            Err(StreamError::DeviceNotAvailable)
+        ));
+    }
+
+    #[cfg(feature = "dummy")]
+    #[test]
+    fn dummy_input() {
+        use super::InputStream;
+        use crate::host::dummy::Device;
+        use crate::{BufferSize, SampleRate, StreamConfig};
+
+        let config = StreamConfig {
+            channels: 2,
+            sample_rate: SampleRate(48_000),
+            buffer_size: BufferSize::Fixed(64),
+            channel_layout: None,
+            strict: false,
+        };
+        let stream = InputStream::<u16>::new(&Device, &config, 256).unwrap();
+        let mut samples = [7; 256];
+        stream.read(&mut samples).unwrap();
+        assert!(samples.iter().all(|&sample| sample == 32768));
+    }
+}
@@ -0,0 +1,249 @@
+use crate::ChannelCount;
+use std::fmt;
+use std::str::FromStr;
+use ParseChannelPositionError;
+
+/// The speaker position that a single channel is intended for.
+///
+/// The `Display` and `FromStr` implementations use the abbreviations common to ALSA and PipeWire,
+/// such as `"FL"` for `FrontLeft` and `"LFE"` for `LowFrequency`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum ChannelPosition {
+    /// The only channel of a mono stream.
+    Mono,
+    FrontLeft,
+    FrontRight,
+    FrontCenter,
+    /// The low frequency effects channel, also known as LFE or subwoofer.
+    LowFrequency,
+    BackLeft,
+    BackRight,
+    FrontLeftOfCenter,
+    FrontRightOfCenter,
+    BackCenter,
+    SideLeft,
+    SideRight,
+    TopCenter,
+    TopFrontLeft,
+    TopFrontCenter,
+    TopFrontRight,
+    TopBackLeft,
+    TopBackCenter,
+    TopBackRight,
+    /// A channel that has no position, or one that the host could not identify.
+    Unknown,
+}
+
+impl ChannelPosition {
+    /// The abbreviation used by `Display` and `FromStr`.
+    pub fn abbreviation(&self) -> &'static str {
+        match self {
+            ChannelPosition::Mono => "MONO",
+            ChannelPosition::FrontLeft => "FL",
+            ChannelPosition::FrontRight => "FR",
+            ChannelPosition::FrontCenter => "FC",
+            ChannelPosition::LowFrequency => "LFE",
+            ChannelPosition::BackLeft => "RL",
+            ChannelPosition::BackRight => "RR",
+            ChannelPosition::FrontLeftOfCenter => "FLC",
+            ChannelPosition::FrontRightOfCenter => "FRC",
+            ChannelPosition::BackCenter => "RC",
+            ChannelPosition::SideLeft => "SL",
+            ChannelPosition::SideRight => "SR",
+            ChannelPosition::TopCenter => "TC",
+            ChannelPosition::TopFrontLeft => "TFL",
+            ChannelPosition::TopFrontCenter => "TFC",
+            ChannelPosition::TopFrontRight => "TFR",
+            ChannelPosition::TopBackLeft => "TRL",
+            ChannelPosition::TopBackCenter => "TRC",
+            ChannelPosition::TopBackRight => "TRR",
+            ChannelPosition::Unknown => "UNKNOWN",
+        }
+    }
+}
+
+const ALL_POSITIONS: [ChannelPosition; 20] = [
+    ChannelPosition::Mono,
+    ChannelPosition::FrontLeft,
+    ChannelPosition::FrontRight,
+    ChannelPosition::FrontCenter,
+    ChannelPosition::LowFrequency,
+    ChannelPosition::BackLeft,
+    ChannelPosition::BackRight,
+    ChannelPosition::FrontLeftOfCenter,
+    ChannelPosition::FrontRightOfCenter,
+    ChannelPosition::BackCenter,
+    ChannelPosition::SideLeft,
+    ChannelPosition::SideRight,
+    ChannelPosition::TopCenter,
+    ChannelPosition::TopFrontLeft,
+    ChannelPosition::TopFrontCenter,
+    ChannelPosition::TopFrontRight,
+    ChannelPosition::TopBackLeft,
+    ChannelPosition::TopBackCenter,
+    ChannelPosition::TopBackRight,
+    ChannelPosition::Unknown,
+];
+
+impl fmt::Display for ChannelPosition {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.abbreviation())
+    }
+}
+
+impl FromStr for ChannelPosition {
+    type Err = ParseChannelPositionError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        ALL_POSITIONS
+            .iter()
+            .find(|position| position.abbreviation().eq_ignore_ascii_case(s))
+            .copied()
+            .ok_or_else(|| ParseChannelPositionError {
+                input: s.to_string(),
+            })
+    }
+}
+
+/// The speaker position of each channel of a stream, in channel order.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ChannelLayout {
+    positions: Vec<ChannelPosition>,
+}
+
+impl ChannelLayout {
+    /// A layout with the given position for each channel.
+    pub fn new(positions: Vec<ChannelPosition>) -> Self {
+        ChannelLayout { positions }
+    }
+
+    /// A single `Mono` channel.
+    pub fn mono() -> Self {
+        Self::new(vec![ChannelPosition::Mono])
+    }
+
+    /// `FrontLeft`, `FrontRight`.
+    pub fn stereo() -> Self {
+        Self::new(vec![
+            ChannelPosition::FrontLeft,
+            ChannelPosition::FrontRight,
+        ])
+    }
+
+    /// `FrontLeft`, `FrontRight`, `BackLeft`, `BackRight`.
+    pub fn quad() -> Self {
+        Self::new(vec![
+            ChannelPosition::FrontLeft,
+            ChannelPosition::FrontRight,
+            ChannelPosition::BackLeft,
+            ChannelPosition::BackRight,
+        ])
+    }
+
+    /// `FrontLeft`, `FrontRight`, `FrontCenter`, `LowFrequency`, `BackLeft`, `BackRight`.
+    ///
+    /// This is the order used by WAV files and most APIs. ALSA devices commonly place the back
+    /// channels before the centre instead.
+    pub fn surround_5_1() -> Self {
+        Self::new(vec![
+            ChannelPosition::FrontLeft,
+            ChannelPosition::FrontRight,
+            ChannelPosition::FrontCenter,
+            ChannelPosition::LowFrequency,
+            ChannelPosition::BackLeft,
+            ChannelPosition::BackRight,
+        ])
+    }
+
+    /// `surround_5_1` followed by `SideLeft`, `SideRight`.
+    pub fn surround_7_1() -> Self {
+        let mut layout = Self::surround_5_1();
+        layout
+            .positions
+            .extend_from_slice(&[ChannelPosition::SideLeft, ChannelPosition::SideRight]);
+        layout
+    }
+
+    /// The number of channels described by the layout.
+    pub fn channels(&self) -> ChannelCount {
+        self.positions.len() as ChannelCount
+    }
+
+    /// The position of each channel, in channel order.
+    pub fn positions(&self) -> &[ChannelPosition] {
+        &self.positions
+    }
+
+    /// The position of the given channel, or `None` if it is out of range.
+    pub fn position(&self, channel: usize) -> Option<ChannelPosition> {
+        self.positions.get(channel).copied()
+    }
+
+    /// The index of the first channel with the given position.
+    pub fn channel_of(&self, position: ChannelPosition) -> Option<usize> {
+        self.positions.iter().position(|&p| p == position)
+    }
+
+    /// Whether every channel has a position other than `Unknown`.
+    pub fn is_known(&self) -> bool {
+        !self.positions.contains(&ChannelPosition::Unknown)
+    }
+}
+
+impl From<Vec<ChannelPosition>> for ChannelLayout {
+    fn from(positions: Vec<ChannelPosition>) -> Self {
+        Self::new(positions)
+    }
+}
+
+impl fmt::Display for ChannelLayout {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for (i, position) in self.positions.iter().enumerate() {
+            if i > 0 {
+                f.write_str(" ")?;
+            }
+            fmt::Display::fmt(position, f)?;
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for ChannelLayout {
+    type Err = ParseChannelPositionError;
+
+    /// Parses whitespace separated position abbreviations, such as `"FL FR FC LFE RL RR"`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        s.split_whitespace()
+            .map(str::parse)
+            .collect::<Result<_, _>>()
+            .map(Self::new)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{ChannelLayout, ChannelPosition, ALL_POSITIONS};
+
+    #[test]
+    fn position_display_from_str() {
+        for position in ALL_POSITIONS.iter() {
+            assert_eq!(position.to_string().parse(), Ok(*position));
+            assert_eq!(position.to_string().to_lowercase().parse(), Ok(*position));
+        }
+        assert!("XYZ".parse::<ChannelPosition>().is_err());
+    }
+
+    #[test]
+    fn layout_display_from_str() {
+        let layout = ChannelLayout::surround_5_1();
+        assert_eq!(layout.to_string(), "FL FR FC LFE RL RR");
+        assert_eq!(layout.to_string().parse(), Ok(layout.clone()));
+        assert_eq!(layout.channels(), 6);
+        assert_eq!(layout.channel_of(ChannelPosition::LowFrequency), Some(3));
+        assert_eq!(layout.position(6), None);
+        assert!(layout.is_known());
+        assert!(!"FL UNKNOWN".parse::<ChannelLayout>().unwrap().is_known());
+    }
+}
@@ -0,0 +1,603 @@
+use crate::{
+    duration_to_frames, BufferSize, ChannelCount, CheckStreamConfigError, QueryStreamConfigError,
+    RejectedParameter, SampleFormat, SampleRate, StreamConfig, SupportedBufferSize,
+    SupportedSampleRates, SupportedStreamConfig, SupportedStreamConfigRange, UnmetConstraint,
+};
+use std::cmp::Ordering;
+use std::time::Duration;
+
+/// How `ConfigConstraints` restrict the sample rate.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SampleRateConstraint {
+    /// Only configs supporting exactly this rate are acceptable.
+    Exact(SampleRate),
+    /// Configs supporting this rate are preferred. Others are acceptable at the supported rate
+    /// closest to it.
+    Preferred(SampleRate),
+}
+
+/// How configs satisfying all `ConfigConstraints` are ranked against each other.
+///
+/// A preferred sample rate and the order of the acceptable sample formats take precedence over
+/// the policy, which only breaks ties between otherwise equally suitable configs.
+#[derive(Clone, Copy, Debug, Default)]
+pub enum ScoringPolicy {
+    /// Rank configs by `SupportedStreamConfigRange::cmp_default_heuristics`.
+    #[default]
+    Default,
+    /// Prefer the most precise sample format, then the highest sample rate.
+    HighestQuality,
+    /// Prefer the smallest supported buffer size.
+    LowestLatency,
+    /// Rank configs by a custom comparison, preferring the greater one.
+    Custom(fn(&SupportedStreamConfig, &SupportedStreamConfig) -> Ordering),
+}
+
+/// Constraints for picking a stream config through `DeviceTrait::query_input_config` or
+/// `DeviceTrait::query_output_config`.
+///
+/// The default value accepts every supported config.
+#[derive(Clone, Debug, Default)]
+pub struct ConfigConstraints {
+    /// The required or preferred sample rate. Without one, configs use their maximum rate.
+    pub sample_rate: Option<SampleRateConstraint>,
+    /// The minimum acceptable number of channels.
+    pub min_channels: Option<ChannelCount>,
+    /// The maximum acceptable number of channels.
+    pub max_channels: Option<ChannelCount>,
+    /// The acceptable sample formats, most preferred first. Empty accepts any format.
+    pub sample_formats: Vec<SampleFormat>,
+    /// The buffer duration that the device must be able to provide, at the chosen sample rate.
+    pub latency: Option<Duration>,
+    /// How to choose between configs that satisfy all of the constraints above.
+    pub scoring: ScoringPolicy,
+}
+
+struct Candidate {
+    range: SupportedStreamConfigRange,
+    config: SupportedStreamConfig,
+}
+
+// The best of `configs` according to `constraints`.
+//
+// Constraints are checked in the order of `UnmetConstraint`, and the first one that rules out all
+// remaining configs is reported.
+pub(crate) fn best_config<I>(
+    configs: I,
+    constraints: &ConfigConstraints,
+) -> Result<SupportedStreamConfig, QueryStreamConfigError>
+where
+    I: IntoIterator<Item = SupportedStreamConfigRange>,
+{
+    let mut ranges: Vec<_> = configs.into_iter().collect();
+    if ranges.is_empty() {
+        return Err(QueryStreamConfigError::StreamTypeNotSupported);
+    }
+
+    let min_channels = constraints.min_channels.unwrap_or(0);
+    let max_channels = constraints.max_channels.unwrap_or(ChannelCount::MAX);
+    ranges.retain(|range| min_channels <= range.channels() && range.channels() <= max_channels);
+    check(&ranges, UnmetConstraint::Channels)?;
+
+    if !constraints.sample_formats.is_empty() {
+        ranges.retain(|range| constraints.sample_formats.contains(&range.sample_format()));
+    }
+    check(&ranges, UnmetConstraint::SampleFormat)?;
+
+    let mut candidates: Vec<_> = ranges
+        .into_iter()
+        .filter_map(|range| {
+            let config = match constraints.sample_rate {
+                Some(SampleRateConstraint::Exact(rate)) => range.clone().try_with_sample_rate(rate),
+                Some(SampleRateConstraint::Preferred(rate)) => {
+                    let rate = range.nearest_sample_rate(rate);
+                    Some(range.clone().with_sample_rate(rate))
+                }
+                None => Some(range.clone().with_max_sample_rate()),
+            };
+            config.map(|config| Candidate { range, config })
+        })
+        .collect();
+    check(&candidates, UnmetConstraint::SampleRate)?;
+
+    if let Some(latency) = constraints.latency {
+        candidates.retain(|candidate| {
+            let frames = latency.as_secs_f64() * candidate.config.sample_rate().0 as f64;
+            match *candidate.config.buffer_size() {
+                SupportedBufferSize::Range { min, max, .. } => {
+                    min as f64 <= frames.round() && frames.round() <= max as f64
+                }
+                SupportedBufferSize::Unknown => true,
+            }
+        });
+    }
+    check(&candidates, UnmetConstraint::Latency)?;
+
+    Ok(candidates
+        .into_iter()
+        .max_by(|a, b| compare(constraints, a, b))
+        .expect("candidates were checked to be non-empty")
+        .config)
+}
+
+// Whether one of `configs` can open a stream with `config` and `sample_format`.
+//
+// Parameters are checked in the order in which hosts apply them when building a stream: sample
+// format, sample rate, channels, channel layout and buffer size. The first one that rules out all
+// remaining configs is reported along with the values that those configs support.
+pub(crate) fn check_config<I>(
+    configs: I,
+    config: &StreamConfig,
+    sample_format: SampleFormat,
+) -> Result<(), CheckStreamConfigError>
+where
+    I: IntoIterator<Item = SupportedStreamConfigRange>,
+{
+    let mut ranges: Vec<_> = configs.into_iter().collect();
+    if ranges.is_empty() {
+        return Err(CheckStreamConfigError::StreamTypeNotSupported);
+    }
+
+    narrow(
+        &mut ranges,
+        |range| range.sample_format() == sample_format,
+        |ranges| {
+            let mut supported = Vec::new();
+            for range in ranges {
+                if !supported.contains(&range.sample_format()) {
+                    supported.push(range.sample_format());
+                }
+            }
+            RejectedParameter::SampleFormat {
+                requested: sample_format,
+                supported,
+            }
+        },
+    )?;
+    narrow(
+        &mut ranges,
+        |range| range.supports_sample_rate(config.sample_rate),
+        |ranges| {
+            let discrete = |range: &SupportedStreamConfigRange| match *range.sample_rates() {
+                SupportedSampleRates::Discrete(ref rates) => Some(rates.clone()),
+                SupportedSampleRates::Continuous => None,
+            };
+            let rates = match ranges.iter().map(discrete).collect::<Option<Vec<_>>>() {
+                Some(rates) => {
+                    let mut rates: Vec<_> = rates.into_iter().flatten().collect();
+                    rates.sort();
+                    rates.dedup();
+                    SupportedSampleRates::Discrete(rates)
+                }
+                None => SupportedSampleRates::Continuous,
+            };
+            RejectedParameter::SampleRate {
+                requested: config.sample_rate,
+                min: ranges
+                    .iter()
+                    .map(|range| range.min_sample_rate())
+                    .min()
+                    .unwrap(),
+                max: ranges
+                    .iter()
+                    .map(|range| range.max_sample_rate())
+                    .max()
+                    .unwrap(),
+                rates,
+            }
+        },
+    )?;
+    narrow(
+        &mut ranges,
+        |range| range.channels() == config.channels,
+        |ranges| RejectedParameter::Channels {
+            requested: config.channels,
+            min: ranges.iter().map(|range| range.channels()).min().unwrap(),
+            max: ranges.iter().map(|range| range.channels()).max().unwrap(),
+        },
+    )?;
+    if let Some(ref layout) = config.channel_layout {
+        // Configs without a layout belong to hosts that ignore the requested one.
+        narrow(
+            &mut ranges,
+            |range| {
+                layout.channels() == config.channels
+                    && range.channel_layout().into_iter().all(|l| l == layout)
+            },
+            |ranges| {
+                let mut supported = Vec::new();
+                for range_layout in ranges.iter().filter_map(|range| range.channel_layout()) {
+                    if !supported.contains(range_layout) {
+                        supported.push(range_layout.clone());
+                    }
+                }
+                RejectedParameter::ChannelLayout {
+                    requested: layout.clone(),
+                    supported,
+                }
+            },
+        )?;
+    }
+    narrow(
+        &mut ranges,
+        |range| supports_buffer_size(range.buffer_size(), config),
+        |ranges| RejectedParameter::BufferSize {
+            requested: config.buffer_size.clone(),
+            supported: widest_buffer_size(ranges),
+        },
+    )
+}
+
+// Keeps the ranges accepted by `supports`, or reports the parameter built by `rejected` from all
+// of them if none is.
+fn narrow<F, R>(
+    ranges: &mut Vec<SupportedStreamConfigRange>,
+    mut supports: F,
+    rejected: R,
+) -> Result<(), CheckStreamConfigError>
+where
+    F: FnMut(&SupportedStreamConfigRange) -> bool,
+    R: FnOnce(&[SupportedStreamConfigRange]) -> RejectedParameter,
+{
+    if !ranges.iter().any(&mut supports) {
+        return Err(CheckStreamConfigError::NotSupported(rejected(ranges)));
+    }
+    ranges.retain(supports);
+    Ok(())
+}
+
+// Whether a host reporting `supported` can honour the buffer size requested by `config`. Hosts
+// translate latencies into whatever buffer size comes closest, so those are supported unless the
+// config is strict.
+fn supports_buffer_size(supported: &SupportedBufferSize, config: &StreamConfig) -> bool {
+    match (&config.buffer_size, supported) {
+        (BufferSize::Default, _) => true,
+        (BufferSize::Latency(latency), SupportedBufferSize::Range { min, max, .. })
+            if config.strict =>
+        {
+            let frames = duration_to_frames(*latency, config.sample_rate);
+            *min <= frames && frames <= *max
+        }
+        (BufferSize::Latency(_), _) => true,
+        (BufferSize::Fixed(_), SupportedBufferSize::Unknown) => true,
+        (BufferSize::Fixed(frames), SupportedBufferSize::Range { min, max, .. }) => {
+            min <= frames && frames <= max
+        }
+        (
+            BufferSize::Periods {
+                period_size,
+                periods,
+            },
+            SupportedBufferSize::Range {
+                min,
+                max,
+                periods: Some(supported),
+            },
+        ) => {
+            let frames = period_size.saturating_mul(*periods);
+            supported.min_period_size <= *period_size
+                && *period_size <= supported.max_period_size
+                && supported.min_periods <= *periods
+                && *periods <= supported.max_periods
+                && *min <= frames
+                && frames <= *max
+        }
+        // Only hosts that report their periods can honour requests for them.
+        (BufferSize::Periods { .. }, _) => false,
+    }
+}
+
+// A buffer size range covering those of all `ranges`.
+fn widest_buffer_size(ranges: &[SupportedStreamConfigRange]) -> SupportedBufferSize {
+    let mut widest: Option<SupportedBufferSize> = None;
+    for range in ranges {
+        widest = Some(match (widest, range.buffer_size()) {
+            (None, buffer_size) => buffer_size.clone(),
+            (
+                Some(SupportedBufferSize::Range { min, max, periods }),
+                &SupportedBufferSize::Range {
+                    min: range_min,
+                    max: range_max,
+                    periods: ref range_periods,
+                },
+            ) => SupportedBufferSize::Range {
+                min: min.min(range_min),
+                max: max.max(range_max),
+                periods: periods.filter(|periods| Some(periods) == range_periods.as_ref()),
+            },
+            _ => SupportedBufferSize::Unknown,
+        });
+    }
+    widest.unwrap_or(SupportedBufferSize::Unknown)
+}
+
+fn check<T>(remaining: &[T], constraint: UnmetConstraint) -> Result<(), QueryStreamConfigError> {
+    if remaining.is_empty() {
+        Err(QueryStreamConfigError::ConstraintNotMet(constraint))
+    } else {
+        Ok(())
+    }
+}
+
+// Orders candidates so that the preferred one is the greatest.
+fn compare(constraints: &ConfigConstraints, a: &Candidate, b: &Candidate) -> Ordering {
+    let rate_distance = |candidate: &Candidate| match constraints.sample_rate {
+        Some(SampleRateConstraint::Preferred(rate)) => {
+            (candidate.config.sample_rate().0 as i64 - rate.0 as i64).abs()
+        }
+        _ => 0,
+    };
+    let format_rank = |candidate: &Candidate| {
+        constraints
+            .sample_formats
+            .iter()
+            .position(|&format| format == candidate.config.sample_format())
+    };
+    let min_buffer_size = |candidate: &Candidate| match *candidate.config.buffer_size() {
+        SupportedBufferSize::Range { min, .. } => min,
+        SupportedBufferSize::Unknown => u32::MAX,
+    };
+    let heuristics = a.range.cmp_default_heuristics(&b.range);
+
+    rate_distance(b)
+        .cmp(&rate_distance(a))
+        .then_with(|| format_rank(b).cmp(&format_rank(a)))
+        .then_with(|| match constraints.scoring {
+            ScoringPolicy::Default => heuristics,
+            ScoringPolicy::HighestQuality => precision(a.config.sample_format())
+                .cmp(&precision(b.config.sample_format()))
+                .then(a.config.sample_rate().cmp(&b.config.sample_rate()))
+                .then(heuristics),
+            ScoringPolicy::LowestLatency => {
+                min_buffer_size(b).cmp(&min_buffer_size(a)).then(heuristics)
+            }
+            ScoringPolicy::Custom(cmp) => cmp(&a.config, &b.config),
+        })
+}
+
+// The significant bits of a sample format, with floating point ranked above integers of the same
+// size.
+fn precision(sample_format: SampleFormat) -> (u32, bool) {
+    match sample_format {
+        SampleFormat::I8 | SampleFormat::U8 => (8, false),
+        SampleFormat::I16 | SampleFormat::U16 => (16, false),
+        SampleFormat::I24 | SampleFormat::I24In32 => (24, false),
+        SampleFormat::I32 | SampleFormat::U32 => (32, false),
+        SampleFormat::F32 => (32, true),
+        SampleFormat::F64 => (64, true),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{
+        best_config, check_config, ConfigConstraints, SampleRateConstraint, ScoringPolicy,
+    };
+    use crate::{
+        BufferSize, ChannelLayout, CheckStreamConfigError, Endianness, QueryStreamConfigError,
+        RejectedParameter, SampleFormat, SampleRate, StreamConfig, SupportedBufferSize,
+        SupportedSampleRates, SupportedStreamConfigRange, UnmetConstraint,
+    };
+    use std::time::Duration;
+
+    fn range(
+        channels: u16,
+        rates: (u32, u32),
+        sample_format: SampleFormat,
+        min_buffer: u32,
+    ) -> SupportedStreamConfigRange {
+        SupportedStreamConfigRange {
+            channels,
+            min_sample_rate: SampleRate(rates.0),
+            max_sample_rate: SampleRate(rates.1),
+            sample_rates: SupportedSampleRates::Continuous,
+            buffer_size: SupportedBufferSize::Range {
+                min: min_buffer,
+                max: 4096,
+                periods: None,
+            },
+            sample_format,
+            endianness: Endianness::NATIVE,
+            channel_layout: None,
+        }
+    }
+
+    fn configs() -> Vec<SupportedStreamConfigRange> {
+        vec![
+            range(2, (44_100, 48_000), SampleFormat::I16, 64),
+            range(2, (8_000, 96_000), SampleFormat::F32, 256),
+            range(6, (48_000, 48_000), SampleFormat::I32, 512),
+        ]
+    }
+
+    #[test]
+    fn default_constraints() {
+        let config = best_config(configs(), &ConfigConstraints::default()).unwrap();
+        assert_eq!(config.channels(), 2);
+        assert_eq!(config.sample_format(), SampleFormat::F32);
+        assert_eq!(config.sample_rate(), SampleRate(96_000));
+    }
+
+    #[test]
+    fn preferences() {
+        let constraints = ConfigConstraints {
+            sample_rate: Some(SampleRateConstraint::Preferred(SampleRate(44_100))),
+            sample_formats: vec![SampleFormat::I32, SampleFormat::I16, SampleFormat::F32],
+            ..Default::default()
+        };
+        let config = best_config(configs(), &constraints).unwrap();
+        assert_eq!(config.sample_format(), SampleFormat::I16);
+        assert_eq!(config.sample_rate(), SampleRate(44_100));
+
+        let constraints = ConfigConstraints {
+            min_channels: Some(4),
+            scoring: ScoringPolicy::HighestQuality,
+            ..Default::default()
+        };
+        let config = best_config(configs(), &constraints).unwrap();
+        assert_eq!(config.sample_format(), SampleFormat::I32);
+
+        let constraints = ConfigConstraints {
+            scoring: ScoringPolicy::LowestLatency,
+            ..Default::default()
+        };
+        let config = best_config(configs(), &constraints).unwrap();
+        assert_eq!(config.sample_format(), SampleFormat::I16);
+    }
+
+    #[test]
+    fn unmet_constraints() {
+        let unmet = |constraints: ConfigConstraints| match best_config(configs(), &constraints) {
+            Err(QueryStreamConfigError::ConstraintNotMet(constraint)) => constraint,
+            other => panic!("unexpected result {:?}", other),
+        };
+        assert_eq!(
+            unmet(ConfigConstraints {
+                max_channels: Some(1),
+                ..Default::default()
+            }),
+            UnmetConstraint::Channels
+        );
+        assert_eq!(
+            unmet(ConfigConstraints {
+                min_channels: Some(6),
+                sample_formats: vec![SampleFormat::F32],
+                ..Default::default()
+            }),
+            UnmetConstraint::SampleFormat
+        );
+        assert_eq!(
+            unmet(ConfigConstraints {
+                sample_rate: Some(SampleRateConstraint::Exact(SampleRate(192_000))),
+                ..Default::default()
+            }),
+            UnmetConstraint::SampleRate
+        );
+        assert_eq!(
+            unmet(ConfigConstraints {
+                latency: Some(Duration::from_millis(1)),
+                ..Default::default()
+            }),
+            UnmetConstraint::Latency
+        );
+        assert!(matches!(
+            best_config(vec![], &ConfigConstraints::default()),
+            Err(QueryStreamConfigError::StreamTypeNotSupported)
+        ));
+    }
+
+    #[test]
+    fn check_config_parameters() {
+        let config = |channels, sample_rate, buffer_size| StreamConfig {
+            channels,
+            sample_rate: SampleRate(sample_rate),
+            buffer_size,
+            channel_layout: None,
+            strict: false,
+        };
+        let not_supported = |config: StreamConfig, sample_format| match check_config(
+            configs(),
+            &config,
+            sample_format,
+        ) {
+            Err(CheckStreamConfigError::NotSupported(rejected)) => Some(rejected),
+            _ => None,
+        };
+
+        assert!(check_config(
+            configs(),
+            &config(6, 48_000, BufferSize::Fixed(512)),
+            SampleFormat::I32
+        )
+        .is_ok());
+        assert_eq!(
+            not_supported(config(2, 48_000, BufferSize::Default), SampleFormat::I24),
+            Some(RejectedParameter::SampleFormat {
+                requested: SampleFormat::I24,
+                supported: vec![SampleFormat::I16, SampleFormat::F32, SampleFormat::I32],
+            })
+        );
+        assert_eq!(
+            not_supported(config(2, 96_000, BufferSize::Default), SampleFormat::I16),
+            Some(RejectedParameter::SampleRate {
+                requested: SampleRate(96_000),
+                min: SampleRate(44_100),
+                max: SampleRate(48_000),
+                rates: SupportedSampleRates::Continuous,
+            })
+        );
+        // The sample format is checked before the channel count.
+        assert_eq!(
+            not_supported(config(6, 48_000, BufferSize::Default), SampleFormat::F32),
+            Some(RejectedParameter::Channels {
+                requested: 6,
+                min: 2,
+                max: 2,
+            })
+        );
+        assert_eq!(
+            not_supported(
+                StreamConfig {
+                    channel_layout: Some(ChannelLayout::quad()),
+                    ..config(2, 48_000, BufferSize::Default)
+                },
+                SampleFormat::F32
+            ),
+            Some(RejectedParameter::ChannelLayout {
+                requested: ChannelLayout::quad(),
+                supported: vec![],
+            })
+        );
+        let buffer_size = SupportedBufferSize::Range {
+            min: 256,
+            max: 4096,
+            periods: None,
+        };
+        assert_eq!(
+            not_supported(config(2, 48_000, BufferSize::Fixed(128)), SampleFormat::F32),
+            Some(RejectedParameter::BufferSize {
+                requested: BufferSize::Fixed(128),
+                supported: buffer_size.clone(),
+            })
+        );
+        let periods = BufferSize::Periods {
+            period_size: 256,
+            periods: 2,
+        };
+        assert!(matches!(
+            not_supported(config(2, 48_000, periods), SampleFormat::F32),
+            Some(RejectedParameter::BufferSize { .. })
+        ));
+
+        // 1ms is 48 frames, which only a strict config insists on.
+        let latency = BufferSize::Latency(Duration::from_millis(1));
+        assert!(check_config(
+            configs(),
+            &config(2, 48_000, latency.clone()),
+            SampleFormat::F32
+        )
+        .is_ok());
+        assert_eq!(
+            not_supported(
+                StreamConfig {
+                    strict: true,
+                    ..config(2, 48_000, latency.clone())
+                },
+                SampleFormat::F32
+            ),
+            Some(RejectedParameter::BufferSize {
+                requested: latency,
+                supported: buffer_size,
+            })
+        );
+
+        assert!(matches!(
+            check_config(
+                vec![],
+                &config(2, 48_000, BufferSize::Default),
+                SampleFormat::F32
+            ),
+            Err(CheckStreamConfigError::StreamTypeNotSupported)
+        ));
+    }
+}
@@ -0,0 +1,583 @@
+//! Bulk conversion of samples between formats.
+//!
+//! Converting one sample at a time through `Sample::from` is convenient but slow for large
+//! buffers. The functions in this module convert whole slices at once, using SIMD kernels for the
+//! most common format pairs where the target supports them. Every kernel produces exactly the same
+//! result as the scalar `Sample` implementations.
+
+use crate::{ChannelCount, Data, DataLayout, Dither, Sample, SampleFormat};
+use std::{ptr, slice};
+
+/// Converts every sample of `source` into the format of `destination`.
+///
+/// Conversions between `I16` and `F32` are vectorised on x86 and x86_64. All other pairs convert
+/// one sample at a time through `Sample::from`.
+///
+/// **panic!**s if `source` and `destination` have different lengths.
+pub fn convert_samples<S, D>(source: &[S], destination: &mut [D])
+where
+    S: Sample,
+    D: Sample,
+{
+    assert_eq!(
+        source.len(),
+        destination.len(),
+        "source and destination must hold the same number of samples"
+    );
+
+    // The casts below are sound because `Sample::FORMAT` describes the exact in-memory
+    // representation of its implementor.
+    match (S::FORMAT, D::FORMAT) {
+        (src, dst) if src == dst => unsafe {
+            ptr::copy_nonoverlapping(
+                source.as_ptr() as *const D,
+                destination.as_mut_ptr(),
+                source.len(),
+            );
+        },
+        (SampleFormat::I16, SampleFormat::F32) => unsafe {
+            i16_to_f32(cast(source), cast_mut(destination));
+        },
+        (SampleFormat::F32, SampleFormat::I16) => unsafe {
+            f32_to_i16(cast(source), cast_mut(destination));
+        },
+        _ => convert_scalar(source, destination),
+    }
+}
+
+/// Converts every sample of `source` into the format of `destination`.
+///
+/// This is the dynamically typed equivalent of `convert_samples`, convenient for raw stream
+/// callbacks. Planar data is converted channel by channel.
+///
+/// **panic!**s if `source` and `destination` have different lengths or layouts.
+pub fn convert_data(source: &Data, destination: &mut Data) {
+    match source.sample_format() {
+        SampleFormat::I8 => convert_to_data::<i8>(source, destination),
+        SampleFormat::U8 => convert_to_data::<u8>(source, destination),
+        SampleFormat::I16 => convert_to_data::<i16>(source, destination),
+        SampleFormat::U16 => convert_to_data::<u16>(source, destination),
+        SampleFormat::I24 => convert_to_data::<crate::I24>(source, destination),
+        SampleFormat::I24In32 => convert_to_data::<crate::I24In32>(source, destination),
+        SampleFormat::I32 => convert_to_data::<i32>(source, destination),
+        SampleFormat::U32 => convert_to_data::<u32>(source, destination),
+        SampleFormat::F32 => convert_to_data::<f32>(source, destination),
+        SampleFormat::F64 => convert_to_data::<f64>(source, destination),
+    }
+}
+
+fn convert_to_data<S>(source: &Data, destination: &mut Data)
+where
+    S: Sample,
+{
+    match destination.sample_format() {
+        SampleFormat::I8 => convert_typed_data::<S, i8>(source, destination),
+        SampleFormat::U8 => convert_typed_data::<S, u8>(source, destination),
+        SampleFormat::I16 => convert_typed_data::<S, i16>(source, destination),
+        SampleFormat::U16 => convert_typed_data::<S, u16>(source, destination),
+        SampleFormat::I24 => convert_typed_data::<S, crate::I24>(source, destination),
+        SampleFormat::I24In32 => convert_typed_data::<S, crate::I24In32>(source, destination),
+        SampleFormat::I32 => convert_typed_data::<S, i32>(source, destination),
+        SampleFormat::U32 => convert_typed_data::<S, u32>(source, destination),
+        SampleFormat::F32 => convert_typed_data::<S, f32>(source, destination),
+        SampleFormat::F64 => convert_typed_data::<S, f64>(source, destination),
+    }
+}
+
+fn convert_typed_data<S, D>(source: &Data, destination: &mut Data)
+where
+    S: Sample,
+    D: Sample,
+{
+    for_each_channel::<S, D, _>(source, destination, |source, destination, _| {
+        convert_samples(source, destination)
+    });
+}
+
+// Calls `f` with the whole buffers of interleaved data, or with each pair of planes and the
+// channel index of planar data.
+pub(crate) fn for_each_channel<S, D, F>(source: &Data, destination: &mut Data, mut f: F)
+where
+    S: Sample,
+    D: Sample,
+    F: FnMut(&[S], &mut [D], Option<usize>),
+{
+    assert_eq!(
+        source.layout(),
+        destination.layout(),
+        "source and destination must have the same layout"
+    );
+    match source.layout() {
+        DataLayout::Interleaved => f(
+            source
+                .as_slice()
+                .expect("`S` must match the source sample format"),
+            destination
+                .as_slice_mut()
+                .expect("`D` must match the destination sample format"),
+            None,
+        ),
+        DataLayout::Planar => {
+            assert_eq!(
+                source.plane_count(),
+                destination.plane_count(),
+                "source and destination must hold the same number of channels"
+            );
+            for channel in 0..source.plane_count() {
+                f(
+                    source
+                        .plane(channel)
+                        .expect("`S` must match the source sample format"),
+                    destination
+                        .plane_mut(channel)
+                        .expect("`D` must match the destination sample format"),
+                    Some(channel),
+                );
+            }
+        }
+    }
+}
+
+unsafe fn cast<S, T>(samples: &[S]) -> &[T] {
+    slice::from_raw_parts(samples.as_ptr() as *const T, samples.len())
+}
+
+unsafe fn cast_mut<S, T>(samples: &mut [S]) -> &mut [T] {
+    slice::from_raw_parts_mut(samples.as_mut_ptr() as *mut T, samples.len())
+}
+
+// Storage for adapting the samples of a typed stream callback to and from the host's native format.
+//
+// The storage is allocated up front. Host buffers holding more frames than it are adapted one chunk
+// at a time, so nothing is allocated in the audio callback. Conversions to narrower integer formats
+// go through the `Dither`, if any.
+pub(crate) struct ScratchBuffer<T> {
+    samples: Vec<T>,
+    frames: usize,
+    channels: usize,
+    dither: Option<Dither>,
+}
+
+impl<T> ScratchBuffer<T>
+where
+    T: Sample,
+{
+    // Storage for `frames` frames of `channels` samples, holding at least one frame.
+    pub(crate) fn new(frames: usize, channels: ChannelCount, dither: Option<Dither>) -> Self {
+        let frames = frames.max(1);
+        ScratchBuffer {
+            samples: vec![T::EQUILIBRIUM; frames * channels as usize],
+            frames,
+            channels: channels as usize,
+            dither,
+        }
+    }
+
+    // Converts interleaved `source` into the storage a chunk at a time, passing each chunk of
+    // converted samples to `f` along with the number of frames that precede it in `source`.
+    pub(crate) fn convert_from<F>(&mut self, source: &Data, mut f: F)
+    where
+        F: FnMut(&[T], usize),
+    {
+        let sample_size = source.sample_format().sample_size();
+        let bytes = source.bytes();
+        self.chunks(source.frame_count(), |scratch, first_frame, start, len| {
+            let chunk = &bytes[start * sample_size..(start + len) * sample_size];
+            let chunk = unsafe {
+                Data::from_parts(
+                    chunk.as_ptr() as *mut (),
+                    len,
+                    scratch.channels,
+                    source.sample_format(),
+                )
+            };
+            let mut data = scratch.data(len);
+            scratch.convert(&chunk, &mut data);
+            f(&scratch.samples[..len], first_frame);
+        });
+    }
+
+    // Fills interleaved `destination` a chunk at a time, with `f` writing each chunk into the
+    // storage, given the number of frames that precede it in `destination`, before it is
+    // converted.
+    pub(crate) fn convert_into<F>(&mut self, destination: &mut Data, mut f: F)
+    where
+        F: FnMut(&mut [T], usize),
+    {
+        let sample_format = destination.sample_format();
+        let sample_size = sample_format.sample_size();
+        let frames = destination.frame_count();
+        let bytes = destination.bytes_mut();
+        self.chunks(frames, |scratch, first_frame, start, len| {
+            f(&mut scratch.samples[..len], first_frame);
+            let chunk = &mut bytes[start * sample_size..(start + len) * sample_size];
+            let mut chunk = unsafe {
+                Data::from_parts(
+                    chunk.as_mut_ptr() as *mut (),
+                    len,
+                    scratch.channels,
+                    sample_format,
+                )
+            };
+            let data = scratch.data(len);
+            scratch.convert(&data, &mut chunk);
+        });
+    }
+
+    fn convert(&mut self, source: &Data, destination: &mut Data) {
+        match self.dither {
+            Some(ref mut dither) => dither.convert_data(source, destination),
+            None => convert_data(source, destination),
+        }
+    }
+
+    // Splits a buffer of `frames` frames into chunks that fit the storage, calling `f` with the
+    // index of the first frame, the index of the first sample and the number of samples of each.
+    // Empty buffers make one empty chunk, so that the callback still runs.
+    fn chunks<F>(&mut self, frames: usize, mut f: F)
+    where
+        F: FnMut(&mut Self, usize, usize, usize),
+    {
+        let mut first_frame = 0;
+        loop {
+            let len = self.frames.min(frames - first_frame);
+            let channels = self.channels;
+            f(self, first_frame, first_frame * channels, len * channels);
+            first_frame += len;
+            if first_frame >= frames {
+                break;
+            }
+        }
+    }
+
+    fn data(&mut self, len: usize) -> Data {
+        let samples = &mut self.samples[..len];
+        unsafe {
+            Data::from_parts(
+                samples.as_mut_ptr() as *mut (),
+                len,
+                self.channels,
+                T::FORMAT,
+            )
+        }
+    }
+}
+
+fn convert_scalar<S, D>(source: &[S], destination: &mut [D])
+where
+    S: Sample,
+    D: Sample,
+{
+    for (dst, src) in destination.iter_mut().zip(source) {
+        *dst = D::from(src);
+    }
+}
+
+#[cfg(all(
+    any(target_arch = "x86", target_arch = "x86_64"),
+    target_feature = "sse2"
+))]
+fn i16_to_f32(source: &[i16], destination: &mut [f32]) {
+    let split = source.len() - source.len() % 8;
+    unsafe { sse2::i16_to_f32(&source[..split], &mut destination[..split]) };
+    convert_scalar(&source[split..], &mut destination[split..]);
+}
+
+#[cfg(not(all(
+    any(target_arch = "x86", target_arch = "x86_64"),
+    target_feature = "sse2"
+)))]
+fn i16_to_f32(source: &[i16], destination: &mut [f32]) {
+    convert_scalar(source, destination);
+}
+
+#[cfg(all(
+    any(target_arch = "x86", target_arch = "x86_64"),
+    target_feature = "sse2"
+))]
+fn f32_to_i16(source: &[f32], destination: &mut [i16]) {
+    let split = source.len() - source.len() % 8;
+    unsafe { sse2::f32_to_i16(&source[..split], &mut destination[..split]) };
+    convert_scalar(&source[split..], &mut destination[split..]);
+}
+
+#[cfg(not(all(
+    any(target_arch = "x86", target_arch = "x86_64"),
+    target_feature = "sse2"
+)))]
+fn f32_to_i16(source: &[f32], destination: &mut [i16]) {
+    convert_scalar(source, destination);
+}
+
+// SSE2 kernels processing 8 samples per iteration. Callers must pass slices of equal length that
+// is a multiple of 8.
+#[cfg(all(
+    any(target_arch = "x86", target_arch = "x86_64"),
+    target_feature = "sse2"
+))]
+mod sse2 {
+    #[cfg(target_arch = "x86")]
+    use std::arch::x86::*;
+    #[cfg(target_arch = "x86_64")]
+    use std::arch::x86_64::*;
+
+    // Mirrors `<i16 as Sample>::to_f32`: negative values are divided by 32768 and the others by
+    // 32767. Division is used rather than multiplication by the reciprocal so that rounding
+    // matches the scalar path exactly.
+    pub unsafe fn i16_to_f32(source: &[i16], destination: &mut [f32]) {
+        let zero = _mm_setzero_ps();
+        let negative_divisor = _mm_set1_ps(-(i16::MIN as f32));
+        let positive_divisor = _mm_set1_ps(i16::MAX as f32);
+        for (src, dst) in source.chunks_exact(8).zip(destination.chunks_exact_mut(8)) {
+            let ints = _mm_loadu_si128(src.as_ptr() as *const __m128i);
+            // Sign-extend each half to 32 bits by moving the samples into the high 16 bits and
+            // shifting them back down arithmetically.
+            let lo = _mm_srai_epi32(_mm_unpacklo_epi16(ints, ints), 16);
+            let hi = _mm_srai_epi32(_mm_unpackhi_epi16(ints, ints), 16);
+            for (i, half) in [lo, hi].iter().enumerate() {
+                let floats = _mm_cvtepi32_ps(*half);
+                let negative = _mm_cmplt_ps(floats, zero);
+                let divisor = _mm_or_ps(
+                    _mm_and_ps(negative, negative_divisor),
+                    _mm_andnot_ps(negative, positive_divisor),
+                );
+                _mm_storeu_ps(dst.as_mut_ptr().add(i * 4), _mm_div_ps(floats, divisor));
+            }
+        }
+    }
+
+    // Mirrors `<f32 as Sample>::to_i16`: non-negative values are scaled by 32767 and negative
+    // ones by 32768, then truncated towards zero. The scalar cast saturates and maps NaN to 0, so
+    // NaN lanes are zeroed and the rest clamped to the `i16` range before truncating.
+    pub unsafe fn f32_to_i16(source: &[f32], destination: &mut [i16]) {
+        let zero = _mm_setzero_ps();
+        let negative_multiplier = _mm_set1_ps(-(i16::MIN as f32));
+        let positive_multiplier = _mm_set1_ps(i16::MAX as f32);
+        let min = _mm_set1_ps(i16::MIN as f32);
+        let max = _mm_set1_ps(i16::MAX as f32);
+        let to_i32 = |floats: __m128| {
+            let floats = _mm_and_ps(floats, _mm_cmpord_ps(floats, floats));
+            let negative = _mm_cmplt_ps(floats, zero);
+            let multiplier = _mm_or_ps(
+                _mm_and_ps(negative, negative_multiplier),
+                _mm_andnot_ps(negative, positive_multiplier),
+            );
+            let scaled = _mm_mul_ps(floats, multiplier);
+            _mm_cvttps_epi32(_mm_min_ps(_mm_max_ps(scaled, min), max))
+        };
+        for (src, dst) in source.chunks_exact(8).zip(destination.chunks_exact_mut(8)) {
+            let lo = to_i32(_mm_loadu_ps(src.as_ptr()));
+            let hi = to_i32(_mm_loadu_ps(src.as_ptr().add(4)));
+            _mm_storeu_si128(dst.as_mut_ptr() as *mut __m128i, _mm_packs_epi32(lo, hi));
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{convert_data, convert_samples, convert_scalar, ScratchBuffer};
+    use crate::{Data, Dither, DitherKind, Sample, SampleFormat, I24};
+
+    // A spread of values covering both signs, the boundaries, values outside of the nominal range
+    // and values that scale to exactly half-way between two integers.
+    fn f32_samples() -> Vec<f32> {
+        let mut samples = vec![
+            0.0,
+            -0.0,
+            1.0,
+            -1.0,
+            0.5,
+            -0.5,
+            1.5,
+            -1.5,
+            1e-9,
+            -1e-9,
+            0.5 / 32767.0,
+            -0.5 / 32768.0,
+            f32::MAX,
+            f32::MIN,
+            f32::INFINITY,
+            f32::NEG_INFINITY,
+            f32::NAN,
+            f32::EPSILON,
+        ];
+        // A deterministic sweep through and slightly beyond the nominal range.
+        let mut x = 0x1234_5678u32;
+        for _ in 0..10_000 {
+            x ^= x << 13;
+            x ^= x >> 17;
+            x ^= x << 5;
+            samples.push(x as f32 / u32::MAX as f32 * 2.2 - 1.1);
+        }
+        samples
+    }
+
+    #[test]
+    fn i16_to_f32_matches_scalar() {
+        let source: Vec<i16> = (i16::MIN..=i16::MAX).collect();
+        let mut fast = vec![0.0f32; source.len()];
+        let mut scalar = vec![0.0f32; source.len()];
+        convert_samples(&source, &mut fast);
+        convert_scalar(&source, &mut scalar);
+        for (i, (a, b)) in fast.iter().zip(&scalar).enumerate() {
+            assert_eq!(a.to_bits(), b.to_bits(), "mismatch for {}", source[i]);
+        }
+    }
+
+    #[test]
+    fn f32_to_i16_matches_scalar() {
+        let source = f32_samples();
+        let mut fast = vec![0i16; source.len()];
+        let mut scalar = vec![0i16; source.len()];
+        convert_samples(&source, &mut fast);
+        convert_scalar(&source, &mut scalar);
+        for (i, (a, b)) in fast.iter().zip(&scalar).enumerate() {
+            assert_eq!(a, b, "mismatch for {}", source[i]);
+        }
+    }
+
+    #[test]
+    fn uneven_lengths() {
+        for len in 0..20 {
+            let source: Vec<i16> = (0..len).map(|i| i * 1000 - 9000).collect();
+            let mut floats = vec![0.0f32; len as usize];
+            convert_samples(&source, &mut floats);
+            for (f, s) in floats.iter().zip(&source) {
+                assert_eq!(*f, s.to_f32());
+            }
+            let mut ints = vec![0i16; len as usize];
+            convert_samples(&floats, &mut ints);
+            for (i, f) in ints.iter().zip(&floats) {
+                assert_eq!(*i, f.to_i16());
+            }
+        }
+    }
+
+    #[test]
+    fn same_format_copies() {
+        let source = [I24::new(-5).unwrap(), I24::new(I24::MAX).unwrap()];
+        let mut destination = [I24::default(); 2];
+        convert_samples(&source, &mut destination);
+        assert_eq!(destination, source);
+    }
+
+    #[test]
+    fn other_formats_match_scalar() {
+        let source = [0u8, 64, 128, 255];
+        let mut destination = [0.0f64; 4];
+        convert_samples(&source, &mut destination);
+        assert_eq!(destination, [-1.0, -0.5, 0.0, 1.0]);
+    }
+
+    #[test]
+    #[should_panic]
+    fn length_mismatch() {
+        convert_samples(&[0i16; 4], &mut [0.0f32; 3]);
+    }
+
+    #[test]
+    fn data_to_data() {
+        let mut source = [i16::MIN, -1, 0, 1, i16::MAX];
+        let mut destination = [0.0f32; 5];
+        let source = unsafe {
+            Data::from_parts(
+                source.as_mut_ptr() as *mut (),
+                source.len(),
+                1,
+                SampleFormat::I16,
+            )
+        };
+        let mut destination_data = unsafe {
+            Data::from_parts(
+                destination.as_mut_ptr() as *mut (),
+                destination.len(),
+                1,
+                SampleFormat::F32,
+            )
+        };
+        convert_data(&source, &mut destination_data);
+        assert_eq!(
+            destination_data.as_slice::<f32>(),
+            Some(&[-1.0, -1.0 / 32768.0, 0.0, 1.0 / 32767.0, 1.0][..])
+        );
+    }
+
+    #[test]
+    fn scratch_buffer() {
+        let mut device = [i16::MIN, 0, 16384, i16::MAX];
+        let mut data = unsafe {
+            Data::from_parts(
+                device.as_mut_ptr() as *mut (),
+                device.len(),
+                2,
+                SampleFormat::I16,
+            )
+        };
+        let mut scratch = ScratchBuffer::<f32>::new(2, 2, None);
+        let mut converted = Vec::new();
+        scratch.convert_from(&data, |samples, frames| {
+            assert_eq!(frames, 0);
+            converted.extend_from_slice(samples);
+        });
+        assert_eq!(converted, [-1.0, 0.0, 16384.0 / 32767.0, 1.0]);
+
+        scratch.convert_into(&mut data, |samples, _| {
+            samples.copy_from_slice(&[1.0, -1.0, 0.0, 0.5])
+        });
+        assert_eq!(device, [i16::MAX, i16::MIN, 0, 16383]);
+    }
+
+    #[test]
+    fn scratch_buffer_chunks() {
+        let mut device = [0i16; 6];
+        let mut data = unsafe {
+            Data::from_parts(
+                device.as_mut_ptr() as *mut (),
+                device.len(),
+                2,
+                SampleFormat::I16,
+            )
+        };
+        // Room for a single frame, so each of the three frames is its own chunk.
+        let mut scratch = ScratchBuffer::<f32>::new(1, 2, None);
+        let mut chunks = Vec::new();
+        scratch.convert_into(&mut data, |samples, frames| {
+            chunks.push(frames);
+            samples.copy_from_slice(&[frames as f32 / 4.0, -1.0]);
+        });
+        assert_eq!(chunks, [0, 1, 2]);
+        assert_eq!(device, [0, i16::MIN, 8191, i16::MIN, 16383, i16::MIN]);
+
+        chunks.clear();
+        let mut converted = Vec::new();
+        scratch.convert_from(&data, |samples, frames| {
+            chunks.push(frames);
+            converted.extend_from_slice(samples);
+        });
+        assert_eq!(chunks, [0, 1, 2]);
+        assert_eq!(converted.len(), 6);
+    }
+
+    #[test]
+    fn scratch_buffer_dither() {
+        let source = [0.3f32 / 127.0, -0.6 / 127.0, 0.5 / 127.0, 0.1 / 127.0];
+        let mut expected = [0i8; 4];
+        Dither::with_seed(DitherKind::Tpdf, 2, 7).convert_samples(&source, &mut expected);
+
+        let mut device = [0i8; 4];
+        let mut data = unsafe {
+            Data::from_parts(
+                device.as_mut_ptr() as *mut (),
+                device.len(),
+                2,
+                SampleFormat::I8,
+            )
+        };
+        let dither = Dither::with_seed(DitherKind::Tpdf, 2, 7);
+        let mut scratch = ScratchBuffer::<f32>::new(1, 2, Some(dither));
+        scratch.convert_into(&mut data, |samples, frames| {
+            samples.copy_from_slice(&source[frames * 2..frames * 2 + 2])
+        });
+        assert_eq!(device, expected);
+    }
+}
@@ -0,0 +1,362 @@
+//! Dithering for conversions that reduce bit depth.
+//!
+//! Converting with `Sample::from` truncates, which correlates the quantisation error with the
+//! signal and is audible as distortion on quiet material. A `Dither` adds a small amount of noise
+//! before rounding so that the error becomes benign, signal-independent noise instead.
+
+use crate::conversions::for_each_channel;
+use crate::{convert_samples, ChannelCount, Data, DataLayout, Sample, SampleFormat};
+
+/// The kind of dither applied by a `Dither`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum DitherKind {
+    /// Triangular probability density function dither spanning ±1 LSB, with a flat noise
+    /// spectrum.
+    Tpdf,
+    /// TPDF dither combined with first-order error feedback, which moves the quantisation noise
+    /// towards high frequencies where it is less audible.
+    NoiseShaped,
+}
+
+/// An opt-in dither stage for converting to integer sample formats.
+///
+/// A `Dither` handles interleaved buffers of whole frames and keeps its noise shaping state per
+/// channel, so the same instance must be used for consecutive buffers of a single stream. The
+/// noise is produced by a seeded pseudo-random generator, making the output deterministic for a
+/// given seed.
+#[derive(Clone, Debug)]
+pub struct Dither {
+    kind: DitherKind,
+    seed: u64,
+    rng: u64,
+    // The quantisation error of the previous sample of each channel.
+    errors: Vec<f64>,
+}
+
+impl Dither {
+    /// Creates a dither stage for `channels` interleaved channels with a fixed default seed.
+    pub fn new(kind: DitherKind, channels: ChannelCount) -> Self {
+        Self::with_seed(kind, channels, 0x853c_49e6_748f_ea9b)
+    }
+
+    /// Creates a dither stage for `channels` interleaved channels using the given seed.
+    ///
+    /// **panic!**s if `channels` is 0.
+    pub fn with_seed(kind: DitherKind, channels: ChannelCount, seed: u64) -> Self {
+        assert!(channels > 0, "a dither stage requires at least one channel");
+        // The generator state must never be zero.
+        let seed = if seed == 0 { 1 } else { seed };
+        Dither {
+            kind,
+            seed,
+            rng: seed,
+            errors: vec![0.0; channels as usize],
+        }
+    }
+
+    /// The kind of dither applied.
+    pub fn kind(&self) -> DitherKind {
+        self.kind
+    }
+
+    /// The number of interleaved channels this stage expects.
+    pub fn channels(&self) -> ChannelCount {
+        self.errors.len() as ChannelCount
+    }
+
+    /// Restores the state this stage was created with, including the random generator.
+    pub fn reset(&mut self) {
+        self.rng = self.seed;
+        for error in self.errors.iter_mut() {
+            *error = 0.0;
+        }
+    }
+
+    /// Converts every sample of `source` into the format of `destination`, dithering when the
+    /// conversion reduces bit depth.
+    ///
+    /// Conversions to floating-point formats, or to integer formats at least as wide as the
+    /// source, are lossless and fall back to `convert_samples`.
+    ///
+    /// **panic!**s if `source` and `destination` have different lengths, or if their length is
+    /// not a multiple of the channel count.
+    pub fn convert_samples<S, D>(&mut self, source: &[S], destination: &mut [D])
+    where
+        S: Sample,
+        D: Sample,
+    {
+        assert_eq!(
+            source.len(),
+            destination.len(),
+            "source and destination must hold the same number of samples"
+        );
+        assert_eq!(
+            source.len() % self.errors.len(),
+            0,
+            "buffers must hold whole frames"
+        );
+
+        self.convert(source, destination, None);
+    }
+
+    /// Converts every sample of `source` into the format of `destination`, dithering when the
+    /// conversion reduces bit depth.
+    ///
+    /// This is the dynamically typed equivalent of `Dither::convert_samples`. Planar data is
+    /// converted channel by channel.
+    ///
+    /// **panic!**s if `source` and `destination` have different lengths or layouts, if
+    /// interleaved data does not hold whole frames, or if planar data does not hold one buffer per
+    /// channel.
+    pub fn convert_data(&mut self, source: &Data, destination: &mut Data) {
+        match source.sample_format() {
+            SampleFormat::I8 => self.convert_to_data::<i8>(source, destination),
+            SampleFormat::U8 => self.convert_to_data::<u8>(source, destination),
+            SampleFormat::I16 => self.convert_to_data::<i16>(source, destination),
+            SampleFormat::U16 => self.convert_to_data::<u16>(source, destination),
+            SampleFormat::I24 => self.convert_to_data::<crate::I24>(source, destination),
+            SampleFormat::I24In32 => self.convert_to_data::<crate::I24In32>(source, destination),
+            SampleFormat::I32 => self.convert_to_data::<i32>(source, destination),
+            SampleFormat::U32 => self.convert_to_data::<u32>(source, destination),
+            SampleFormat::F32 => self.convert_to_data::<f32>(source, destination),
+            SampleFormat::F64 => self.convert_to_data::<f64>(source, destination),
+        }
+    }
+
+    fn convert_to_data<S>(&mut self, source: &Data, destination: &mut Data)
+    where
+        S: Sample,
+    {
+        match destination.sample_format() {
+            SampleFormat::I8 => self.convert_typed_data::<S, i8>(source, destination),
+            SampleFormat::U8 => self.convert_typed_data::<S, u8>(source, destination),
+            SampleFormat::I16 => self.convert_typed_data::<S, i16>(source, destination),
+            SampleFormat::U16 => self.convert_typed_data::<S, u16>(source, destination),
+            SampleFormat::I24 => self.convert_typed_data::<S, crate::I24>(source, destination),
+            SampleFormat::I24In32 => {
+                self.convert_typed_data::<S, crate::I24In32>(source, destination)
+            }
+            SampleFormat::I32 => self.convert_typed_data::<S, i32>(source, destination),
+            SampleFormat::U32 => self.convert_typed_data::<S, u32>(source, destination),
+            SampleFormat::F32 => self.convert_typed_data::<S, f32>(source, destination),
+            SampleFormat::F64 => self.convert_typed_data::<S, f64>(source, destination),
+        }
+    }
+
+    fn convert_typed_data<S, D>(&mut self, source: &Data, destination: &mut Data)
+    where
+        S: Sample,
+        D: Sample,
+    {
+        if source.layout() == DataLayout::Planar {
+            assert_eq!(
+                source.plane_count(),
+                self.errors.len(),
+                "planar data must hold one buffer per channel"
+            );
+        }
+        for_each_channel::<S, D, _>(
+            source,
+            destination,
+            |source, destination, plane| match plane {
+                None => self.convert_samples(source, destination),
+                Some(channel) => {
+                    assert_eq!(
+                        source.len(),
+                        destination.len(),
+                        "source and destination must hold the same number of samples"
+                    );
+                    self.convert(source, destination, Some(channel))
+                }
+            },
+        );
+    }
+
+    // Converts interleaved samples, or the samples of a single channel if `plane` is given.
+    fn convert<S, D>(&mut self, source: &[S], destination: &mut [D], plane: Option<usize>)
+    where
+        S: Sample,
+        D: Sample,
+    {
+        let bits = match (bit_depth(S::FORMAT), bit_depth(D::FORMAT)) {
+            (None, Some(bits)) => bits,
+            (Some(src), Some(bits)) if src > bits => bits,
+            _ => return convert_samples(source, destination),
+        };
+
+        let channels = self.errors.len();
+        for (i, (dst, src)) in destination.iter_mut().zip(source).enumerate() {
+            let channel = plane.unwrap_or(i % channels);
+            let quantised = self.quantise(src.to_f64(), bits, channel);
+            // Aligning the quantised value to the top of an `i32` makes the final conversion
+            // exact for every integer format.
+            *dst = D::from(&(quantised << (32 - bits)));
+        }
+    }
+
+    // Quantises `value` to a signed integer of `bits` bits, scaled the same way as the `Sample`
+    // conversions.
+    fn quantise(&mut self, value: f64, bits: u32, channel: usize) -> i32 {
+        let max = ((1u64 << (bits - 1)) - 1) as f64;
+        let min = -((1u64 << (bits - 1)) as f64);
+        let scaled = if value >= 0.0 {
+            value * max
+        } else {
+            -value * min
+        };
+
+        let shaped = match self.kind {
+            DitherKind::Tpdf => scaled,
+            DitherKind::NoiseShaped => scaled - self.errors[channel],
+        };
+        let noise = self.next_uniform() - self.next_uniform();
+        let quantised = (shaped + noise).round();
+        // The error is taken before clipping so that it stays bounded and the feedback loop
+        // cannot run away on overloaded input.
+        self.errors[channel] = quantised - shaped;
+        quantised.max(min).min(max) as i32
+    }
+
+    // A uniformly distributed value in `[0, 1)` from a xorshift64* generator.
+    fn next_uniform(&mut self) -> f64 {
+        self.rng ^= self.rng >> 12;
+        self.rng ^= self.rng << 25;
+        self.rng ^= self.rng >> 27;
+        let bits = self.rng.wrapping_mul(0x2545_f491_4f6c_dd1d) >> 11;
+        bits as f64 / (1u64 << 53) as f64
+    }
+}
+
+// The number of significant bits of integer formats, or `None` for floating-point ones.
+fn bit_depth(format: SampleFormat) -> Option<u32> {
+    match format {
+        SampleFormat::I8 | SampleFormat::U8 => Some(8),
+        SampleFormat::I16 | SampleFormat::U16 => Some(16),
+        SampleFormat::I24 | SampleFormat::I24In32 => Some(24),
+        SampleFormat::I32 | SampleFormat::U32 => Some(32),
+        SampleFormat::F32 | SampleFormat::F64 => None,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{Dither, DitherKind};
+    use crate::{convert_samples, Sample};
+
+    fn sine(len: usize, amplitude: f32) -> Vec<f32> {
+        (0..len)
+            .map(|i| (i as f32 * 0.05).sin() * amplitude)
+            .collect()
+    }
+
+    #[test]
+    fn deterministic_for_seed() {
+        let source = sine(4096, 0.001);
+        let mut a = vec![0i16; source.len()];
+        let mut b = vec![0i16; source.len()];
+        let mut c = vec![0i16; source.len()];
+        Dither::with_seed(DitherKind::Tpdf, 1, 42).convert_samples(&source, &mut a);
+        Dither::with_seed(DitherKind::Tpdf, 1, 42).convert_samples(&source, &mut b);
+        Dither::with_seed(DitherKind::Tpdf, 1, 43).convert_samples(&source, &mut c);
+        assert_eq!(a, b);
+        assert_ne!(a, c);
+    }
+
+    #[test]
+    fn reset_restores_seed() {
+        let source = sine(1024, 0.001);
+        let mut dither = Dither::new(DitherKind::NoiseShaped, 2);
+        let mut a = vec![0i16; source.len()];
+        let mut b = vec![0i16; source.len()];
+        dither.convert_samples(&source, &mut a);
+        dither.reset();
+        dither.convert_samples(&source, &mut b);
+        assert_eq!(a, b);
+    }
+
+    #[test]
+    fn state_carries_across_buffers() {
+        let source = sine(2048, 0.5);
+        let mut whole = vec![0i16; source.len()];
+        Dither::new(DitherKind::NoiseShaped, 2).convert_samples(&source, &mut whole);
+
+        let mut split = vec![0i16; source.len()];
+        let mut dither = Dither::new(DitherKind::NoiseShaped, 2);
+        let (first, second) = split.split_at_mut(1000);
+        dither.convert_samples(&source[..1000], first);
+        dither.convert_samples(&source[1000..], second);
+        assert_eq!(whole, split);
+    }
+
+    #[test]
+    fn tpdf_error_is_bounded_and_unbiased() {
+        // A constant a quarter of an LSB above zero, which truncation always maps to 0.
+        let source = vec![0.25f32 / 32767.0; 100_000];
+        let mut destination = vec![0i16; source.len()];
+        Dither::new(DitherKind::Tpdf, 1).convert_samples(&source, &mut destination);
+        assert!(destination.iter().all(|&s| (-1..=2).contains(&s)));
+        let mean = destination.iter().map(|&s| s as f64).sum::<f64>() / source.len() as f64;
+        assert!((mean - 0.25).abs() < 0.01, "mean was {}", mean);
+    }
+
+    #[test]
+    fn noise_shaped_error_has_no_dc() {
+        // First-order error feedback makes the total error telescope, so it stays within a
+        // couple of LSBs however long the signal is.
+        let source = sine(100_000, 0.3);
+        let mut destination = vec![0i16; source.len()];
+        Dither::new(DitherKind::NoiseShaped, 1).convert_samples(&source, &mut destination);
+        let total_error: f64 = source
+            .iter()
+            .zip(&destination)
+            .map(|(&s, &d)| d as f64 - s.to_f64() * if s >= 0.0 { 32767.0 } else { 32768.0 })
+            .sum();
+        assert!(total_error.abs() < 2.0, "total error was {}", total_error);
+    }
+
+    #[test]
+    fn clips_to_range() {
+        let source = [1.0f32, -1.0, 2.0, -2.0];
+        let mut destination = [0i16; 4];
+        // One channel per sample, so that no error is fed back between them.
+        Dither::new(DitherKind::NoiseShaped, 4).convert_samples(&source, &mut destination);
+        assert!(destination[0] >= 32766);
+        assert!(destination[1] <= -32767);
+        assert_eq!(destination[2], i16::MAX);
+        assert_eq!(destination[3], i16::MIN);
+    }
+
+    #[test]
+    fn other_integer_formats() {
+        let source = [0.5f64, -0.5];
+        let mut u8s = [0u8; 2];
+        Dither::new(DitherKind::Tpdf, 2).convert_samples(&source, &mut u8s);
+        assert!((190..=193).contains(&u8s[0]), "{}", u8s[0]);
+        assert!((63..=65).contains(&u8s[1]), "{}", u8s[1]);
+
+        let mut i24s = [crate::I24::default(); 2];
+        Dither::new(DitherKind::Tpdf, 2).convert_samples(&source, &mut i24s);
+        assert!((i24s[0].value() - 4_194_303).abs() <= 2);
+        assert!((i24s[1].value() + 4_194_304).abs() <= 2);
+    }
+
+    #[test]
+    fn lossless_conversions_are_untouched() {
+        let source: Vec<i16> = (-100..100).collect();
+        let mut floats = vec![0.0f32; source.len()];
+        let mut expected = vec![0.0f32; source.len()];
+        Dither::new(DitherKind::Tpdf, 1).convert_samples(&source, &mut floats);
+        convert_samples(&source, &mut expected);
+        assert_eq!(floats, expected);
+
+        let mut widened = vec![0i32; source.len()];
+        Dither::new(DitherKind::Tpdf, 1).convert_samples(&source, &mut widened);
+        assert!(widened.iter().zip(&source).all(|(w, s)| *w == s.to_i32()));
+    }
+
+    #[test]
+    #[should_panic]
+    fn partial_frames() {
+        Dither::new(DitherKind::Tpdf, 2).convert_samples(&[0.0f32; 3], &mut [0i16; 3]);
+    }
+}
@@ -1,3 +1,7 @@
+use crate::{
+    BufferSize, ChannelCount, ChannelLayout, DataLayout, SampleFormat, SampleRate,
+    SupportedBufferSize, SupportedSampleRates,
+};
 use thiserror::Error;
 
 /// The requested host, although supported on this platform, is unavailable.
@@ -23,6 +27,28 @@ pub struct BackendSpecificError {
     pub description: String,
 }
 
+/// The string passed to `SampleFormat::from_str` does not name a sample format.
+#[derive(Clone, Debug, Error, PartialEq, Eq)]
+#[error("`{input}` is not a known sample format")]
+pub struct ParseSampleFormatError {
+    pub input: String,
+}
+
+/// The string passed to `HostId::from_str` does not name a host available on this platform.
+#[derive(Clone, Debug, Error, PartialEq, Eq)]
+#[error("`{input}` is not a known host on this platform")]
+pub struct ParseHostIdError {
+    pub input: String,
+}
+
+/// The string passed to `ChannelPosition::from_str` or `ChannelLayout::from_str` contains
+/// something other than a speaker position abbreviation.
+#[derive(Clone, Debug, Error, PartialEq, Eq)]
+#[error("`{input}` is not a known channel position")]
+pub struct ParseChannelPositionError {
+    pub input: String,
+}
+
 /// An error that might occur while attempting to enumerate the available devices on a system.
 #[derive(Debug, Error)]
 pub enum DevicesError {
@@ -83,6 +109,102 @@ pub enum DefaultStreamConfigError {
     },
 }
 
+/// May occur when querying a `Device` for the stream config that best matches some
+/// `ConfigConstraints`.
+#[derive(Debug, Error)]
+pub enum QueryStreamConfigError {
+    /// The device no longer exists. This can happen if the device is disconnected while the
+    /// program is running.
+    #[error("The requested device is no longer available. For example, it has been unplugged.")]
+    DeviceNotAvailable,
+    /// We called something the C-Layer did not understand
+    #[error("Invalid argument passed to the backend.")]
+    InvalidArgument,
+    /// The device supports no configs at all in the requested direction.
+    #[error("The requested stream type is not supported by the device.")]
+    StreamTypeNotSupported,
+    /// None of the configs supported by the device satisfy the given constraint, taking the
+    /// constraints checked before it into account.
+    #[error("No supported stream config satisfies the {0} constraint.")]
+    ConstraintNotMet(UnmetConstraint),
+    /// See the `BackendSpecificError` docs for more information about this error variant.
+    #[error("{err}")]
+    BackendSpecific {
+        #[from]
+        err: BackendSpecificError,
+    },
+}
+
+/// A constraint of `ConfigConstraints`, in the order in which they are checked.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum UnmetConstraint {
+    Channels,
+    SampleFormat,
+    SampleRate,
+    Latency,
+}
+
+impl std::fmt::Display for UnmetConstraint {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.write_str(match self {
+            UnmetConstraint::Channels => "channel count",
+            UnmetConstraint::SampleFormat => "sample format",
+            UnmetConstraint::SampleRate => "sample rate",
+            UnmetConstraint::Latency => "latency",
+        })
+    }
+}
+
+impl From<SupportedStreamConfigsError> for QueryStreamConfigError {
+    fn from(err: SupportedStreamConfigsError) -> Self {
+        match err {
+            SupportedStreamConfigsError::DeviceNotAvailable => {
+                QueryStreamConfigError::DeviceNotAvailable
+            }
+            SupportedStreamConfigsError::InvalidArgument => QueryStreamConfigError::InvalidArgument,
+            SupportedStreamConfigsError::BackendSpecific { err } => err.into(),
+        }
+    }
+}
+
+/// May occur when checking whether a `Device` supports a stream config.
+#[derive(Debug, Error)]
+pub enum CheckStreamConfigError {
+    /// The device no longer exists. This can happen if the device is disconnected while the
+    /// program is running.
+    #[error("The requested device is no longer available. For example, it has been unplugged.")]
+    DeviceNotAvailable,
+    /// We called something the C-Layer did not understand
+    #[error("Invalid argument passed to the backend.")]
+    InvalidArgument,
+    /// The device supports no configs at all in the requested direction.
+    #[error("The requested stream type is not supported by the device.")]
+    StreamTypeNotSupported,
+    /// The device does not support the requested value of a parameter, taking the parameters
+    /// checked before it into account. Parameters are checked in the same order as when building
+    /// a stream, so this is the error that building one would report.
+    #[error("The device does not support the requested {0}.")]
+    NotSupported(RejectedParameter),
+    /// See the `BackendSpecificError` docs for more information about this error variant.
+    #[error("{err}")]
+    BackendSpecific {
+        #[from]
+        err: BackendSpecificError,
+    },
+}
+
+impl From<SupportedStreamConfigsError> for CheckStreamConfigError {
+    fn from(err: SupportedStreamConfigsError) -> Self {
+        match err {
+            SupportedStreamConfigsError::DeviceNotAvailable => {
+                CheckStreamConfigError::DeviceNotAvailable
+            }
+            SupportedStreamConfigsError::InvalidArgument => CheckStreamConfigError::InvalidArgument,
+            SupportedStreamConfigsError::BackendSpecific { err } => err.into(),
+        }
+    }
+}
+
 /// Error that can happen when creating a `Stream`.
 #[derive(Debug, Error)]
 pub enum BuildStreamError {
@@ -93,6 +215,12 @@ pub enum BuildStreamError {
     /// The specified stream configuration is not supported.
     #[error("The requested stream configuration is not supported by the device.")]
     StreamConfigNotSupported,
+    /// The device rejected the requested value of a single parameter.
+    ///
+    /// Only reported by hosts that can tell which parameter is at fault, others report
+    /// `StreamConfigNotSupported` instead.
+    #[error("The device does not support the requested {0}.")]
+    ParameterNotSupported(RejectedParameter),
     /// We called something the C-Layer did not understand
     ///
     /// On ALSA device functions called with a feature they do not support will yield this. E.g.
@@ -110,6 +238,168 @@ pub enum BuildStreamError {
     },
 }
 
+impl From<CheckStreamConfigError> for BuildStreamError {
+    fn from(err: CheckStreamConfigError) -> Self {
+        match err {
+            CheckStreamConfigError::DeviceNotAvailable => BuildStreamError::DeviceNotAvailable,
+            CheckStreamConfigError::InvalidArgument => BuildStreamError::InvalidArgument,
+            CheckStreamConfigError::StreamTypeNotSupported => {
+                BuildStreamError::StreamConfigNotSupported
+            }
+            CheckStreamConfigError::NotSupported(rejected) => {
+                BuildStreamError::ParameterNotSupported(rejected)
+            }
+            CheckStreamConfigError::BackendSpecific { err } => err.into(),
+        }
+    }
+}
+
+impl From<SupportedStreamConfigsError> for BuildStreamError {
+    fn from(err: SupportedStreamConfigsError) -> Self {
+        match err {
+            SupportedStreamConfigsError::DeviceNotAvailable => BuildStreamError::DeviceNotAvailable,
+            SupportedStreamConfigsError::InvalidArgument => BuildStreamError::InvalidArgument,
+            SupportedStreamConfigsError::BackendSpecific { err } => err.into(),
+        }
+    }
+}
+
+/// A stream parameter that a device rejected, along with the value requested and the values that
+/// the device supports, taking the parameters it already settled on into account.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum RejectedParameter {
+    Channels {
+        requested: ChannelCount,
+        min: ChannelCount,
+        max: ChannelCount,
+    },
+    SampleFormat {
+        requested: SampleFormat,
+        supported: Vec<SampleFormat>,
+    },
+    SampleRate {
+        requested: SampleRate,
+        min: SampleRate,
+        max: SampleRate,
+        /// Whether all rates between `min` and `max` are supported or only some of them.
+        rates: SupportedSampleRates,
+    },
+    /// The speaker positions of `StreamConfig::channel_layout`, along with the layouts the device
+    /// offers for the requested channel count.
+    ChannelLayout {
+        requested: ChannelLayout,
+        supported: Vec<ChannelLayout>,
+    },
+    BufferSize {
+        requested: BufferSize,
+        supported: SupportedBufferSize,
+    },
+    /// The way in which samples are exchanged with the device, such as ALSA's read and write
+    /// access to interleaved buffers.
+    AccessMode {
+        requested: DataLayout,
+        supported: Vec<DataLayout>,
+    },
+}
+
+impl std::fmt::Display for RejectedParameter {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        fn list<T: std::fmt::Debug>(f: &mut std::fmt::Formatter, values: &[T]) -> std::fmt::Result {
+            if values.is_empty() {
+                return f.write_str("none");
+            }
+            for (i, value) in values.iter().enumerate() {
+                if i > 0 {
+                    f.write_str(", ")?;
+                }
+                write!(f, "{:?}", value)?;
+            }
+            Ok(())
+        }
+
+        match self {
+            RejectedParameter::Channels {
+                requested,
+                min,
+                max,
+            } => write!(
+                f,
+                "channel count of {} (supported: {} to {})",
+                requested, min, max
+            ),
+            RejectedParameter::SampleFormat {
+                requested,
+                supported,
+            } => {
+                write!(f, "sample format {:?} (supported: ", requested)?;
+                list(f, supported)?;
+                f.write_str(")")
+            }
+            RejectedParameter::SampleRate {
+                requested,
+                min,
+                max,
+                rates,
+            } => {
+                write!(f, "sample rate of {} Hz (supported: ", requested.0)?;
+                match rates {
+                    SupportedSampleRates::Continuous => write!(f, "{} to {}", min.0, max.0)?,
+                    SupportedSampleRates::Discrete(rates) => {
+                        let rates: Vec<u32> = rates.iter().map(|rate| rate.0).collect();
+                        list(f, &rates)?;
+                    }
+                }
+                f.write_str(" Hz)")
+            }
+            RejectedParameter::ChannelLayout {
+                requested,
+                supported,
+            } => {
+                write!(f, "channel layout \"{}\" (supported: ", requested)?;
+                if supported.is_empty() {
+                    f.write_str("none")?;
+                }
+                for (i, layout) in supported.iter().enumerate() {
+                    if i > 0 {
+                        f.write_str(", ")?;
+                    }
+                    write!(f, "\"{}\"", layout)?;
+                }
+                f.write_str(")")
+            }
+            RejectedParameter::BufferSize {
+                requested,
+                supported,
+            } => {
+                f.write_str("buffer size of ")?;
+                match requested {
+                    BufferSize::Default => f.write_str("the default")?,
+                    BufferSize::Fixed(frames) => write!(f, "{} frames", frames)?,
+                    BufferSize::Periods {
+                        period_size,
+                        periods,
+                    } => write!(f, "{} periods of {} frames", periods, period_size)?,
+                    BufferSize::Latency(latency) => write!(f, "{:?} of latency", latency)?,
+                }
+                match supported {
+                    SupportedBufferSize::Range { min, max, .. } => {
+                        write!(f, " (supported: {} to {} frames)", min, max)
+                    }
+                    SupportedBufferSize::Unknown => Ok(()),
+                }
+            }
+            RejectedParameter::AccessMode {
+                requested,
+                supported,
+            } => {
+                write!(f, "access mode for {:?} data (supported: ", requested)?;
+                list(f, supported)?;
+                f.write_str(")")
+            }
+        }
+    }
+}
+
 /// Errors that might occur when calling `play_stream`.
 ///
 /// As of writing this, only macOS may immediately return an error while calling this method. This
@@ -147,7 +437,7 @@ pub enum PauseStreamError {
 }
 
 /// Errors that might occur while a stream is running.
-#[derive(Debug, Error)]
+#[derive(Clone, Debug, Error)]
 pub enum StreamError {
     /// The device no longer exists. This can happen if the device is disconnected while the
     /// program is running.
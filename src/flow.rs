@@ -0,0 +1,173 @@
+use crate::{
+    Data, I24In32, OutputCallbackInfo, Sample, SampleFormat, SampleRate, StreamInstant, I24,
+};
+use std::time::Duration;
+
+/// What a stream does after its data callback returns, for streams built with
+/// `DeviceTrait::build_input_stream_flow` or `build_output_stream_flow`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum CallbackFlow {
+    /// Keep the stream running and call back for the next buffer.
+    Continue,
+    /// Stop the stream after this buffer, discarding any output the device has yet to play.
+    Stop,
+    /// Stop the stream once all output handed to the device, including this buffer, has been
+    /// played. Input streams stop right away, as with `Stop`.
+    Drain,
+}
+
+// Emulates `CallbackFlow` on top of a plain data callback, for hosts that cannot stop a stream
+// from its own callback.
+//
+// Once the user's callback has asked to stop, it is not called again, output is filled with
+// silence and the completion callback runs. When draining, that happens once the callback
+// timestamp has passed the end of the last buffer's playback.
+pub(crate) struct FlowEmulation<C> {
+    state: FlowState,
+    completion_callback: Option<C>,
+}
+
+enum FlowState {
+    Running,
+    Draining { until: StreamInstant },
+    Stopped,
+}
+
+impl<C: FnOnce()> FlowEmulation<C> {
+    pub(crate) fn new(completion_callback: C) -> Self {
+        FlowEmulation {
+            state: FlowState::Running,
+            completion_callback: Some(completion_callback),
+        }
+    }
+
+    pub(crate) fn input<F>(&mut self, data_callback: F)
+    where
+        F: FnOnce() -> CallbackFlow,
+    {
+        if let FlowState::Running = self.state {
+            if data_callback() != CallbackFlow::Continue {
+                self.stop();
+            }
+        }
+    }
+
+    pub(crate) fn output<F>(
+        &mut self,
+        data: &mut Data,
+        info: &OutputCallbackInfo,
+        sample_rate: SampleRate,
+        data_callback: F,
+    ) where
+        F: FnOnce(&mut Data) -> CallbackFlow,
+    {
+        match self.state {
+            FlowState::Running => match data_callback(data) {
+                CallbackFlow::Continue => (),
+                CallbackFlow::Stop => self.stop(),
+                CallbackFlow::Drain => {
+                    let frames = data.frame_count() as f64;
+                    let duration = Duration::from_secs_f64(frames / sample_rate.0 as f64);
+                    match info.timestamp.playback.add(duration) {
+                        Some(until) => self.state = FlowState::Draining { until },
+                        None => self.stop(),
+                    }
+                }
+            },
+            FlowState::Draining { until } => {
+                fill_equilibrium(data);
+                if info.timestamp.callback >= until {
+                    self.stop();
+                }
+            }
+            FlowState::Stopped => fill_equilibrium(data),
+        }
+    }
+
+    fn stop(&mut self) {
+        self.state = FlowState::Stopped;
+        if let Some(completion_callback) = self.completion_callback.take() {
+            completion_callback();
+        }
+    }
+}
+
+pub(crate) fn fill_equilibrium(data: &mut Data) {
+    fn fill<T: Sample>(data: &mut Data) {
+        for sample in data.as_slice_mut::<T>().into_iter().flatten() {
+            *sample = T::EQUILIBRIUM;
+        }
+    }
+    match data.sample_format() {
+        SampleFormat::I8 => fill::<i8>(data),
+        SampleFormat::U8 => fill::<u8>(data),
+        SampleFormat::I16 => fill::<i16>(data),
+        SampleFormat::U16 => fill::<u16>(data),
+        SampleFormat::I24 => fill::<I24>(data),
+        SampleFormat::I24In32 => fill::<I24In32>(data),
+        SampleFormat::I32 => fill::<i32>(data),
+        SampleFormat::U32 => fill::<u32>(data),
+        SampleFormat::F32 => fill::<f32>(data),
+        SampleFormat::F64 => fill::<f64>(data),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{CallbackFlow, FlowEmulation};
+    use crate::{
+        Data, OutputCallbackInfo, OutputStreamTimestamp, SampleFormat, SampleRate, StreamInstant,
+    };
+    use std::cell::Cell;
+
+    fn info(callback_ms: u32, playback_ms: u32) -> OutputCallbackInfo {
+        let instant = |ms: u32| StreamInstant::new(0, ms * 1_000_000);
+        OutputCallbackInfo {
+            timestamp: OutputStreamTimestamp {
+                callback: instant(callback_ms),
+                playback: instant(playback_ms),
+            },
+        }
+    }
+
+    #[test]
+    fn drain() {
+        let completed = Cell::new(false);
+        let mut flow = FlowEmulation::new(|| completed.set(true));
+        let mut samples = [0u16; 96];
+        let mut data = unsafe {
+            Data::from_parts(
+                samples.as_mut_ptr() as *mut (),
+                samples.len(),
+                2,
+                SampleFormat::U16,
+            )
+        };
+        let rate = SampleRate(48_000);
+
+        // 48 frames played from 10ms end at 11ms.
+        flow.output(&mut data, &info(0, 10), rate, |_| CallbackFlow::Drain);
+        assert!(!completed.get());
+        let mut called = false;
+        flow.output(&mut data, &info(5, 15), rate, |_| {
+            called = true;
+            CallbackFlow::Continue
+        });
+        assert!(!called);
+        assert!(!completed.get());
+        flow.output(&mut data, &info(11, 21), rate, |_| CallbackFlow::Continue);
+        assert!(completed.get());
+        assert!(samples.iter().all(|&s| s == 32768));
+    }
+
+    #[test]
+    fn stop_input() {
+        let completed = Cell::new(0);
+        let mut flow = FlowEmulation::new(|| completed.set(completed.get() + 1));
+        flow.input(|| CallbackFlow::Continue);
+        assert_eq!(completed.get(), 0);
+        flow.input(|| CallbackFlow::Stop);
+        flow.input(|| panic!("called after stopping"));
+        assert_eq!(completed.get(), 1);
+    }
+}
@@ -0,0 +1,341 @@
+//! Adapters exposing streams to asynchronous code, available with the `futures` feature.
+//!
+//! `InputStream` is a `futures_core::Stream` of captured sample buffers and `OutputStream` a
+//! `futures_sink::Sink` of sample buffers to play. Both wrap a regular callback stream built
+//! through `DeviceTrait` and exchange samples with it through a bounded queue, waking the task
+//! that polls them whenever the callback makes progress, so they work with any executor.
+//!
+//! As with the `blocking` streams, the queue is a lock-free ring buffer that the callback never
+//! waits for, a full input queue or an empty output queue counts an xrun, and only whole frames
+//! are moved between the queue and the callback.
+
+use crate::blocking::{build_input_stream, build_output_stream, Shared};
+use crate::traits::{DeviceTrait, StreamTrait};
+use crate::{BuildStreamError, FrameCount, Sample, StreamConfig, StreamError};
+use futures_core::Stream;
+use futures_sink::Sink;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+
+/// An input stream yielding the samples captured since it was last polled.
+///
+/// The stream ends after yielding the first error reported by the device.
+pub struct InputStream<T> {
+    stream: Box<dyn StreamTrait>,
+    shared: Arc<Shared<T>>,
+    done: bool,
+}
+
+/// An output stream accepting buffers of samples to play.
+///
+/// At most one buffer is held beyond the queue, so a full queue applies backpressure through
+/// `poll_ready`. Closing the sink waits for the queue to be played.
+pub struct OutputStream<T> {
+    stream: Box<dyn StreamTrait>,
+    shared: Arc<Shared<T>>,
+    pending: Vec<T>,
+}
+
+// No field is ever pinned, so moving the pending samples is fine.
+impl<T> Unpin for OutputStream<T> {}
+
+impl<T> InputStream<T>
+where
+    T: Sample + Send + 'static,
+{
+    /// Build and start an input stream on `device`, queueing up to `capacity` frames.
+    pub fn new<D>(
+        device: &D,
+        config: &StreamConfig,
+        capacity: FrameCount,
+    ) -> Result<Self, BuildStreamError>
+    where
+        D: DeviceTrait,
+        D::Stream: 'static,
+    {
+        let shared = Arc::new(Shared::new(config.channels, capacity));
+        let stream = build_input_stream(device, config, &shared)?;
+        Ok(InputStream {
+            stream,
+            shared,
+            done: false,
+        })
+    }
+
+    /// The number of times captured frames were dropped because the queue was full.
+    pub fn xruns(&self) -> usize {
+        self.shared.xruns()
+    }
+
+    /// The underlying callback stream, e.g. for pausing it or querying its latency.
+    pub fn stream(&self) -> &dyn StreamTrait {
+        &*self.stream
+    }
+}
+
+impl<T> OutputStream<T>
+where
+    T: Sample + Send + 'static,
+{
+    /// Build and start an output stream on `device`, queueing up to `capacity` frames.
+    pub fn new<D>(
+        device: &D,
+        config: &StreamConfig,
+        capacity: FrameCount,
+    ) -> Result<Self, BuildStreamError>
+    where
+        D: DeviceTrait,
+        D::Stream: 'static,
+    {
+        let shared = Arc::new(Shared::new(config.channels, capacity));
+        let stream = build_output_stream(device, config, &shared)?;
+        Ok(OutputStream {
+            stream,
+            shared,
+            pending: Vec::new(),
+        })
+    }
+
+    /// The number of times the queue ran dry since the first buffer was sent, each heard as a
+    /// gap.
+    pub fn xruns(&self) -> usize {
+        self.shared.xruns()
+    }
+
+    /// The underlying callback stream, e.g. for pausing it or querying its latency.
+    pub fn stream(&self) -> &dyn StreamTrait {
+        &*self.stream
+    }
+}
+
+impl<T: Sample> OutputStream<T> {
+    // Moves as much of the pending buffer into the queue as fits, completing once all of it has.
+    fn poll_pending(&mut self, cx: &mut Context) -> Poll<Result<(), StreamError>> {
+        loop {
+            if let Some(err) = self.shared.error() {
+                return Poll::Ready(Err(err));
+            }
+            let n = self.shared.enqueue(&self.pending);
+            self.pending.drain(..n);
+            if self.pending.is_empty() {
+                return Poll::Ready(Ok(()));
+            }
+            let shared = &self.shared;
+            if !shared.register(cx.waker(), || shared.space() > 0) {
+                return Poll::Pending;
+            }
+        }
+    }
+}
+
+impl<T: Sample> Stream for InputStream<T> {
+    type Item = Result<Vec<T>, StreamError>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Option<Self::Item>> {
+        let this = self.get_mut();
+        if this.done {
+            return Poll::Ready(None);
+        }
+        let shared = &this.shared;
+        loop {
+            let queued = shared.queued();
+            if queued > 0 {
+                let mut samples = vec![T::EQUILIBRIUM; queued];
+                shared.dequeue(&mut samples);
+                return Poll::Ready(Some(Ok(samples)));
+            }
+            if let Some(err) = shared.error() {
+                this.done = true;
+                return Poll::Ready(Some(Err(err)));
+            }
+            if !shared.register(cx.waker(), || shared.queued() > 0) {
+                return Poll::Pending;
+            }
+        }
+    }
+}
+
+impl<T, B> Sink<B> for OutputStream<T>
+where
+    T: Sample,
+    B: AsRef<[T]>,
+{
+    type Error = StreamError;
+
+    fn poll_ready(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Result<(), Self::Error>> {
+        self.get_mut().poll_pending(cx)
+    }
+
+    fn start_send(self: Pin<&mut Self>, item: B) -> Result<(), Self::Error> {
+        let this = self.get_mut();
+        this.shared.mark_written();
+        this.pending.extend_from_slice(item.as_ref());
+        Ok(())
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Result<(), Self::Error>> {
+        self.get_mut().poll_pending(cx)
+    }
+
+    fn poll_close(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Result<(), Self::Error>> {
+        let this = self.get_mut();
+        match this.poll_pending(cx) {
+            Poll::Ready(Ok(())) => (),
+            poll => return poll,
+        }
+        let shared = &this.shared;
+        loop {
+            if let Some(err) = shared.error() {
+                return Poll::Ready(Err(err));
+            }
+            if shared.queued() == 0 {
+                return Poll::Ready(Ok(()));
+            }
+            if !shared.register(cx.waker(), || shared.queued() == 0) {
+                return Poll::Pending;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{InputStream, OutputStream};
+    use crate::blocking::Shared;
+    use crate::traits::StreamTrait;
+    use crate::{PauseStreamError, PlayStreamError, StreamError};
+    use futures_core::Stream;
+    use futures_sink::Sink;
+    use std::pin::Pin;
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::sync::Arc;
+    use std::task::{Context, Poll, Wake, Waker};
+
+    struct NullStream;
+
+    impl StreamTrait for NullStream {
+        fn play(&self) -> Result<(), PlayStreamError> {
+            Ok(())
+        }
+        fn pause(&self) -> Result<(), PauseStreamError> {
+            Ok(())
+        }
+    }
+
+    #[derive(Default)]
+    struct Flag(AtomicBool);
+
+    impl Wake for Flag {
+        fn wake(self: Arc<Self>) {
+            self.0.store(true, Ordering::SeqCst);
+        }
+    }
+
+    #[test]
+    fn input_stream() {
+        let shared = Arc::new(Shared::new(2, 2));
+        let mut stream = InputStream {
+            stream: Box::new(NullStream),
+            shared: shared.clone(),
+            done: false,
+        };
+        let flag = Arc::new(Flag::default());
+        let waker = Waker::from(flag.clone());
+        let mut cx = Context::from_waker(&waker);
+
+        assert!(Pin::new(&mut stream).poll_next(&mut cx).is_pending());
+        shared.capture(&[1i16, 2, 3, 4, 5, 6]);
+        assert!(flag.0.load(Ordering::SeqCst));
+        assert_eq!(stream.xruns(), 1);
+        match Pin::new(&mut stream).poll_next(&mut cx) {
+            Poll::Ready(Some(Ok(samples))) => assert_eq!(samples, [1, 2, 3, 4]),
+            _ => panic!("expected captured samples"),
+        }
+
+        shared.report(StreamError::DeviceNotAvailable);
+        assert!(matches!(
+            Pin::new(&mut stream).poll_next(&mut cx),
+            Poll::Ready(Some(Err(StreamError::DeviceNotAvailable)))
+        ));
+        assert!(matches!(
+            Pin::new(&mut stream).poll_next(&mut cx),
+            Poll::Ready(None)
+        ));
+    }
+
+    #[test]
+    fn output_backpressure() {
+        let shared = Arc::new(Shared::new(1, 4));
+        let mut sink = OutputStream {
+            stream: Box::new(NullStream),
+            shared: shared.clone(),
+            pending: Vec::new(),
+        };
+        let flag = Arc::new(Flag::default());
+        let waker = Waker::from(flag.clone());
+        let mut cx = Context::from_waker(&waker);
+
+        let mut sink = Pin::new(&mut sink);
+        assert!(matches!(
+            Sink::<&[f32]>::poll_ready(sink.as_mut(), &mut cx),
+            Poll::Ready(Ok(()))
+        ));
+        sink.as_mut()
+            .start_send(&[1.0f32, 2.0, 3.0, 4.0, 5.0, 6.0][..])
+            .unwrap();
+        assert!(Sink::<&[f32]>::poll_ready(sink.as_mut(), &mut cx).is_pending());
+
+        let mut data = [0.0; 4];
+        shared.play(&mut data);
+        assert_eq!(data, [1.0, 2.0, 3.0, 4.0]);
+        assert!(flag.0.load(Ordering::SeqCst));
+        assert!(matches!(
+            Sink::<&[f32]>::poll_flush(sink.as_mut(), &mut cx),
+            Poll::Ready(Ok(()))
+        ));
+        assert!(Sink::<&[f32]>::poll_close(sink.as_mut(), &mut cx).is_pending());
+        shared.play(&mut data);
+        assert_eq!(data, [5.0, 6.0, 0.0, 0.0]);
+        assert_eq!(sink.xruns(), 1);
+        assert!(matches!(
+            Sink::<&[f32]>::poll_close(sink.as_mut(), &mut cx),
+            Poll::Ready(Ok(()))
+        ));
+    }
+
+    #[cfg(feature = "dummy")]
+    #[test]
+    fn dummy_input() {
+        use crate::host::dummy::Device;
+        use crate::{BufferSize, SampleRate, StreamConfig};
+        use std::thread;
+        use std::time::Duration;
+
+        let config = StreamConfig {
+            channels: 2,
+            sample_rate: SampleRate(48_000),
+            buffer_size: BufferSize::Fixed(64),
+            channel_layout: None,
+            strict: false,
+        };
+        let mut stream = InputStream::<f32>::new(&Device, &config, 256).unwrap();
+        let flag = Arc::new(Flag::default());
+        let waker = Waker::from(flag.clone());
+        let mut cx = Context::from_waker(&waker);
+
+        let samples = loop {
+            match Pin::new(&mut stream).poll_next(&mut cx) {
+                Poll::Ready(Some(Ok(samples))) => break samples,
+                Poll::Pending => {
+                    while !flag.0.swap(false, Ordering::SeqCst) {
+                        thread::sleep(Duration::from_millis(1));
+                    }
+                }
+                _ => panic!("expected captured samples"),
+            }
+        };
+        assert!(!samples.is_empty());
+        assert!(samples.iter().all(|&sample| sample == 0.0));
+    }
+}
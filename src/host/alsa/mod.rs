@@ -5,11 +5,13 @@ extern crate parking_lot;
 use self::alsa::poll::Descriptors;
 use self::parking_lot::Mutex;
 use crate::{
-    BackendSpecificError, BufferSize, BuildStreamError, ChannelCount, Data,
-    DefaultStreamConfigError, DeviceNameError, DevicesError, InputCallbackInfo, OutputCallbackInfo,
-    PauseStreamError, PlayStreamError, SampleFormat, SampleRate, StreamConfig, StreamError,
-    SupportedBufferSize, SupportedStreamConfig, SupportedStreamConfigRange,
-    SupportedStreamConfigsError,
+    BackendSpecificError, BufferSize, BuildStreamError, CallbackFlow, ChannelCount, ChannelLayout,
+    ChannelPosition, CheckStreamConfigError, Data, DataLayout, DefaultStreamConfigError,
+    DeviceNameError, DevicesError, DuplexCallbackInfo, DuplexStreamConfig, Endianness, FrameCount,
+    InputCallbackInfo, NegotiatedStreamConfig, OutputCallbackInfo, PauseStreamError,
+    PlayStreamError, RejectedParameter, SampleFormat, SampleRate, StreamConfig, StreamError,
+    SupportedBufferSize, SupportedPeriods, SupportedSampleRates, SupportedStreamConfig,
+    SupportedStreamConfigRange, SupportedStreamConfigsError,
 };
 use std::cmp;
 use std::convert::TryInto;
@@ -82,6 +84,22 @@ impl DeviceTrait for Device {
         Device::default_input_config(self)
     }
 
+    fn check_input_config(
+        &self,
+        config: &StreamConfig,
+        sample_format: SampleFormat,
+    ) -> Result<(), CheckStreamConfigError> {
+        self.check_config(config, sample_format, alsa::Direction::Capture)
+    }
+
+    fn check_output_config(
+        &self,
+        config: &StreamConfig,
+        sample_format: SampleFormat,
+    ) -> Result<(), CheckStreamConfigError> {
+        self.check_config(config, sample_format, alsa::Direction::Playback)
+    }
+
     fn default_output_config(&self) -> Result<SupportedStreamConfig, DefaultStreamConfigError> {
         Device::default_output_config(self)
     }
@@ -90,33 +108,128 @@ impl DeviceTrait for Device {
         &self,
         conf: &StreamConfig,
         sample_format: SampleFormat,
-        data_callback: D,
+        mut data_callback: D,
         error_callback: E,
     ) -> Result<Self::Stream, BuildStreamError>
     where
         D: FnMut(&Data, &InputCallbackInfo) + Send + 'static,
         E: FnMut(StreamError) + Send + 'static,
+    {
+        self.build_input_stream_flow_raw(
+            conf,
+            sample_format,
+            move |data, info| {
+                data_callback(data, info);
+                CallbackFlow::Continue
+            },
+            error_callback,
+            || (),
+        )
+    }
+
+    fn build_output_stream_raw<D, E>(
+        &self,
+        conf: &StreamConfig,
+        sample_format: SampleFormat,
+        mut data_callback: D,
+        error_callback: E,
+    ) -> Result<Self::Stream, BuildStreamError>
+    where
+        D: FnMut(&mut Data, &OutputCallbackInfo) + Send + 'static,
+        E: FnMut(StreamError) + Send + 'static,
+    {
+        self.build_output_stream_flow_raw(
+            conf,
+            sample_format,
+            move |data, info| {
+                data_callback(data, info);
+                CallbackFlow::Continue
+            },
+            error_callback,
+            || (),
+        )
+    }
+
+    fn build_input_stream_flow_raw<D, E, C>(
+        &self,
+        conf: &StreamConfig,
+        sample_format: SampleFormat,
+        data_callback: D,
+        error_callback: E,
+        completion_callback: C,
+    ) -> Result<Self::Stream, BuildStreamError>
+    where
+        D: FnMut(&Data, &InputCallbackInfo) -> CallbackFlow + Send + 'static,
+        E: FnMut(StreamError) + Send + 'static,
+        C: FnOnce() + Send + 'static,
     {
         let stream_inner =
             self.build_stream_inner(conf, sample_format, alsa::Direction::Capture)?;
-        let stream = Stream::new_input(Arc::new(stream_inner), data_callback, error_callback);
+        stream_inner.channel.start()?;
+        let stream = Stream::new_input(
+            Arc::new(stream_inner),
+            data_callback,
+            error_callback,
+            completion_callback,
+        );
         Ok(stream)
     }
 
-    fn build_output_stream_raw<D, E>(
+    fn build_output_stream_flow_raw<D, E, C>(
         &self,
         conf: &StreamConfig,
         sample_format: SampleFormat,
         data_callback: D,
         error_callback: E,
+        completion_callback: C,
     ) -> Result<Self::Stream, BuildStreamError>
     where
-        D: FnMut(&mut Data, &OutputCallbackInfo) + Send + 'static,
+        D: FnMut(&mut Data, &OutputCallbackInfo) -> CallbackFlow + Send + 'static,
         E: FnMut(StreamError) + Send + 'static,
+        C: FnOnce() + Send + 'static,
     {
         let stream_inner =
             self.build_stream_inner(conf, sample_format, alsa::Direction::Playback)?;
-        let stream = Stream::new_output(Arc::new(stream_inner), data_callback, error_callback);
+        let stream = Stream::new_output(
+            Arc::new(stream_inner),
+            data_callback,
+            error_callback,
+            completion_callback,
+        );
+        Ok(stream)
+    }
+
+    fn build_duplex_stream_raw<D, E>(
+        &self,
+        conf: &DuplexStreamConfig,
+        sample_format: SampleFormat,
+        data_callback: D,
+        error_callback: E,
+    ) -> Result<Self::Stream, BuildStreamError>
+    where
+        D: FnMut(&Data, &mut Data, &DuplexCallbackInfo) + Send + 'static,
+        E: FnMut(StreamError) + Send + 'static,
+    {
+        let input = self.build_stream_inner(
+            &conf.input_config(),
+            sample_format,
+            alsa::Direction::Capture,
+        )?;
+        let output = self.build_stream_inner(
+            &conf.output_config(),
+            sample_format,
+            alsa::Direction::Playback,
+        )?;
+        // Linked PCMs start, stop and recover together, keeping input and output sample-aligned.
+        // Plugins that cannot be linked, such as `null`, are started one after the other instead.
+        input.channel.link(&output.channel).ok();
+        start_duplex(&input, &output, &mut Vec::new())?;
+        let stream = Stream::new_duplex(
+            Arc::new(input),
+            Arc::new(output),
+            data_callback,
+            error_callback,
+        );
         Ok(stream)
     }
 }
@@ -247,7 +360,11 @@ impl Device {
             Err((e, _)) => return Err(e.into()),
             Ok(handle) => handle,
         };
-        let can_pause = set_hw_params_from_format(&handle, conf, sample_format)?;
+        let (can_pause, endianness) = set_hw_params_from_format(&handle, conf, sample_format)?;
+        let negotiated = negotiated_config(&handle.hw_params_current()?, sample_format)?;
+        if let Some(ref layout) = conf.channel_layout {
+            set_channel_layout(&handle, layout)?;
+        }
         let period_len = set_sw_params_from_format(&handle, conf, stream_type)?;
 
         handle.prepare()?;
@@ -270,16 +387,15 @@ impl Device {
             _ => None,
         };
 
-        if let alsa::Direction::Capture = stream_type {
-            handle.start()?;
-        }
-
         let stream_inner = StreamInner {
             channel: handle,
             sample_format,
+            endianness,
             num_descriptors,
             conf: conf.clone(),
             period_len,
+            negotiated,
+            stream_type,
             can_pause,
             creation_instant,
         };
@@ -314,79 +430,10 @@ impl Device {
 
         let hw_params = alsa::pcm::HwParams::any(handle)?;
 
-        // TODO: check endianess
-        const FORMATS: [(SampleFormat, alsa::pcm::Format); 3] = [
-            //SND_PCM_FORMAT_S8,
-            //SND_PCM_FORMAT_U8,
-            (SampleFormat::I16, alsa::pcm::Format::S16LE),
-            //SND_PCM_FORMAT_S16_BE,
-            (SampleFormat::U16, alsa::pcm::Format::U16LE),
-            //SND_PCM_FORMAT_U16_BE,
-            //SND_PCM_FORMAT_S24_LE,
-            //SND_PCM_FORMAT_S24_BE,
-            //SND_PCM_FORMAT_U24_LE,
-            //SND_PCM_FORMAT_U24_BE,
-            //SND_PCM_FORMAT_S32_LE,
-            //SND_PCM_FORMAT_S32_BE,
-            //SND_PCM_FORMAT_U32_LE,
-            //SND_PCM_FORMAT_U32_BE,
-            (SampleFormat::F32, alsa::pcm::Format::FloatLE),
-            //SND_PCM_FORMAT_FLOAT_BE,
-            //SND_PCM_FORMAT_FLOAT64_LE,
-            //SND_PCM_FORMAT_FLOAT64_BE,
-            //SND_PCM_FORMAT_IEC958_SUBFRAME_LE,
-            //SND_PCM_FORMAT_IEC958_SUBFRAME_BE,
-            //SND_PCM_FORMAT_MU_LAW,
-            //SND_PCM_FORMAT_A_LAW,
-            //SND_PCM_FORMAT_IMA_ADPCM,
-            //SND_PCM_FORMAT_MPEG,
-            //SND_PCM_FORMAT_GSM,
-            //SND_PCM_FORMAT_SPECIAL,
-            //SND_PCM_FORMAT_S24_3LE,
-            //SND_PCM_FORMAT_S24_3BE,
-            //SND_PCM_FORMAT_U24_3LE,
-            //SND_PCM_FORMAT_U24_3BE,
-            //SND_PCM_FORMAT_S20_3LE,
-            //SND_PCM_FORMAT_S20_3BE,
-            //SND_PCM_FORMAT_U20_3LE,
-            //SND_PCM_FORMAT_U20_3BE,
-            //SND_PCM_FORMAT_S18_3LE,
-            //SND_PCM_FORMAT_S18_3BE,
-            //SND_PCM_FORMAT_U18_3LE,
-            //SND_PCM_FORMAT_U18_3BE,
-        ];
-
-        let mut supported_formats = Vec::new();
-        for &(sample_format, alsa_format) in FORMATS.iter() {
-            if hw_params.test_format(alsa_format).is_ok() {
-                supported_formats.push(sample_format);
-            }
-        }
-
-        let min_rate = hw_params.get_rate_min()?;
-        let max_rate = hw_params.get_rate_max()?;
+        let supported_formats = supported_sample_formats(&hw_params);
 
-        let sample_rates = if min_rate == max_rate || hw_params.test_rate(min_rate + 1).is_ok() {
-            vec![(min_rate, max_rate)]
-        } else {
-            const RATES: [libc::c_uint; 13] = [
-                5512, 8000, 11025, 16000, 22050, 32000, 44100, 48000, 64000, 88200, 96000, 176400,
-                192000,
-            ];
-
-            let mut rates = Vec::new();
-            for &rate in RATES.iter() {
-                if hw_params.test_rate(rate).is_ok() {
-                    rates.push((rate, rate));
-                }
-            }
-
-            if rates.is_empty() {
-                vec![(min_rate, max_rate)]
-            } else {
-                rates
-            }
-        };
+        let (SampleRate(min_rate), SampleRate(max_rate), sample_rates) =
+            supported_sample_rates(&hw_params)?;
 
         let min_channels = hw_params.get_channels_min()?;
         let max_channels = hw_params.get_channels_max()?;
@@ -402,28 +449,26 @@ impl Device {
             })
             .collect::<Vec<_>>();
 
-        let min_buffer_size = hw_params.get_buffer_size_min()?;
-        let max_buffer_size = hw_params.get_buffer_size_max()?;
+        let channel_layouts = supported_channel_layouts(handle);
 
-        let buffer_size_range = SupportedBufferSize::Range {
-            min: min_buffer_size as u32,
-            max: max_buffer_size as u32,
-        };
+        let buffer_size_range = supported_buffer_size(&hw_params)?;
 
-        let mut output = Vec::with_capacity(
-            supported_formats.len() * supported_channels.len() * sample_rates.len(),
-        );
-        for &sample_format in supported_formats.iter() {
+        let mut output = Vec::with_capacity(supported_formats.len() * supported_channels.len());
+        for &(sample_format, endianness) in supported_formats.iter() {
             for &channels in supported_channels.iter() {
-                for &(min_rate, max_rate) in sample_rates.iter() {
-                    output.push(SupportedStreamConfigRange {
-                        channels,
-                        min_sample_rate: SampleRate(min_rate as u32),
-                        max_sample_rate: SampleRate(max_rate as u32),
-                        buffer_size: buffer_size_range.clone(),
-                        sample_format,
-                    });
-                }
+                output.push(SupportedStreamConfigRange {
+                    channels,
+                    min_sample_rate: SampleRate(min_rate),
+                    max_sample_rate: SampleRate(max_rate),
+                    sample_rates: sample_rates.clone(),
+                    buffer_size: buffer_size_range.clone(),
+                    sample_format,
+                    endianness,
+                    channel_layout: channel_layouts
+                        .iter()
+                        .find(|layout| layout.channels() == channels)
+                        .cloned(),
+                });
             }
         }
 
@@ -442,6 +487,32 @@ impl Device {
         self.supported_configs(alsa::Direction::Playback)
     }
 
+    // Runs the checks made when building a stream, without applying the resulting parameters.
+    fn check_config(
+        &self,
+        config: &StreamConfig,
+        sample_format: SampleFormat,
+        stream_t: alsa::Direction,
+    ) -> Result<(), CheckStreamConfigError> {
+        let mut guard = self.handles.lock();
+        let handle_result = guard
+            .get_mut(&self.name, stream_t)
+            .map_err(|e| (e, e.errno()));
+
+        let handle = match handle_result {
+            Err((_, nix::errno::Errno::ENOENT)) | Err((_, nix::errno::Errno::EBUSY)) => {
+                return Err(CheckStreamConfigError::DeviceNotAvailable)
+            }
+            Err((_, nix::errno::Errno::EINVAL)) => {
+                return Err(CheckStreamConfigError::InvalidArgument)
+            }
+            Err((e, _)) => return Err(e.into()),
+            Ok(handle) => handle,
+        };
+
+        hw_params_from_config(handle, config, sample_format).map(|_| ())
+    }
+
     // ALSA does not offer default stream formats, so instead we compare all supported formats by
     // the `SupportedStreamConfigRange::cmp_default_heuristics` order and select the greatest.
     fn default_config(
@@ -469,14 +540,12 @@ impl Device {
 
         match formats.into_iter().last() {
             Some(f) => {
-                let min_r = f.min_sample_rate;
-                let max_r = f.max_sample_rate;
-                let mut format = f.with_max_sample_rate();
                 const HZ_44100: SampleRate = SampleRate(44_100);
-                if min_r <= HZ_44100 && HZ_44100 <= max_r {
-                    format.sample_rate = HZ_44100;
+                if f.supports_sample_rate(HZ_44100) {
+                    Ok(f.with_sample_rate(HZ_44100))
+                } else {
+                    Ok(f.with_max_sample_rate())
                 }
-                Ok(format)
             }
             None => Err(DefaultStreamConfigError::StreamTypeNotSupported),
         }
@@ -502,12 +571,22 @@ struct StreamInner {
     // Format of the samples.
     sample_format: SampleFormat,
 
+    // Byte order of the samples as exchanged with ALSA. Samples are swapped to and from native
+    // byte order around the user's callback when this is not the native byte order.
+    endianness: Endianness,
+
     // The configuration used to open this stream.
     conf: StreamConfig,
 
     // Minimum number of samples to put in the buffer.
     period_len: usize,
 
+    // The configuration that ALSA settled on.
+    negotiated: NegotiatedStreamConfig,
+
+    // Whether this is a playback or a capture stream.
+    stream_type: alsa::Direction,
+
     #[allow(dead_code)]
     // Whether or not the hardware supports pausing the stream.
     // TODO: We need an API to expose this. See #197, #284.
@@ -541,6 +620,9 @@ pub struct Stream {
     /// Handle to the underlying stream for playback controls.
     inner: Arc<StreamInner>,
 
+    /// The capture half of a duplex stream, in which case `inner` is the playback half.
+    duplex_input: Option<Arc<StreamInner>>,
+
     /// Used to signal to stop processing.
     trigger: TriggerSender,
 }
@@ -551,16 +633,17 @@ struct StreamWorkerContext {
     buffer: Vec<u8>,
 }
 
+// Returns `true` if the data callback stopped the stream, or `false` once the stream is dropped.
 fn input_stream_worker(
-    rx: TriggerReceiver,
+    rx: &TriggerReceiver,
     stream: &StreamInner,
-    data_callback: &mut (dyn FnMut(&Data, &InputCallbackInfo) + Send + 'static),
+    data_callback: &mut (dyn FnMut(&Data, &InputCallbackInfo) -> CallbackFlow + Send + 'static),
     error_callback: &mut (dyn FnMut(StreamError) + Send + 'static),
-) {
+) -> bool {
     let mut ctxt = StreamWorkerContext::default();
     loop {
         let flow = report_error(
-            poll_descriptors_and_prepare_buffer(&rx, stream, &mut ctxt),
+            poll_descriptors_and_prepare_buffer(rx, stream, &mut ctxt),
             error_callback,
         )
         .unwrap_or(PollDescriptorsFlow::Continue);
@@ -573,7 +656,7 @@ fn input_stream_worker(
                 report_error(stream.channel.prepare(), error_callback);
                 continue;
             }
-            PollDescriptorsFlow::Return => return,
+            PollDescriptorsFlow::Return => return false,
             PollDescriptorsFlow::Ready {
                 status,
                 avail_frames: _,
@@ -592,22 +675,32 @@ fn input_stream_worker(
                     delay_frames,
                     data_callback,
                 );
-                report_error(res, error_callback);
+                match report_error(res, error_callback) {
+                    Some(CallbackFlow::Continue) | None => (),
+                    // There is no buffered input worth waiting for.
+                    Some(CallbackFlow::Stop) | Some(CallbackFlow::Drain) => {
+                        report_error(stream.channel.drop(), error_callback);
+                        return true;
+                    }
+                }
             }
         }
     }
 }
 
+// Returns `true` if the data callback stopped the stream, or `false` once the stream is dropped.
 fn output_stream_worker(
-    rx: TriggerReceiver,
+    rx: &TriggerReceiver,
     stream: &StreamInner,
-    data_callback: &mut (dyn FnMut(&mut Data, &OutputCallbackInfo) + Send + 'static),
+    data_callback: &mut (dyn FnMut(&mut Data, &OutputCallbackInfo) -> CallbackFlow
+              + Send
+              + 'static),
     error_callback: &mut (dyn FnMut(StreamError) + Send + 'static),
-) {
+) -> bool {
     let mut ctxt = StreamWorkerContext::default();
     loop {
         let flow = report_error(
-            poll_descriptors_and_prepare_buffer(&rx, stream, &mut ctxt),
+            poll_descriptors_and_prepare_buffer(rx, stream, &mut ctxt),
             error_callback,
         )
         .unwrap_or(PollDescriptorsFlow::Continue);
@@ -618,7 +711,7 @@ fn output_stream_worker(
                 report_error(stream.channel.prepare(), error_callback);
                 continue;
             }
-            PollDescriptorsFlow::Return => return,
+            PollDescriptorsFlow::Return => return false,
             PollDescriptorsFlow::Ready {
                 status,
                 avail_frames,
@@ -639,6 +732,70 @@ fn output_stream_worker(
                     data_callback,
                     error_callback,
                 );
+                match report_error(res, error_callback) {
+                    Some(CallbackFlow::Continue) | None => (),
+                    Some(CallbackFlow::Stop) => {
+                        report_error(stream.channel.drop(), error_callback);
+                        return true;
+                    }
+                    Some(CallbackFlow::Drain) => {
+                        report_error(drain(stream), error_callback);
+                        return true;
+                    }
+                }
+            }
+        }
+    }
+}
+
+fn duplex_stream_worker(
+    rx: TriggerReceiver,
+    input: &StreamInner,
+    output: &StreamInner,
+    data_callback: &mut (dyn FnMut(&Data, &mut Data, &DuplexCallbackInfo) + Send + 'static),
+    error_callback: &mut (dyn FnMut(StreamError) + Send + 'static),
+) {
+    let mut ctxt = StreamWorkerContext::default();
+    let mut output_buffer = Vec::new();
+    loop {
+        // The capture half drives the stream, with each period of input answered by output.
+        let flow = report_error(
+            poll_descriptors_and_prepare_buffer(&rx, input, &mut ctxt),
+            error_callback,
+        )
+        .unwrap_or(PollDescriptorsFlow::Continue);
+
+        match flow {
+            PollDescriptorsFlow::Continue => continue,
+            PollDescriptorsFlow::XRun => {
+                report_error(
+                    start_duplex(input, output, &mut output_buffer),
+                    error_callback,
+                );
+                continue;
+            }
+            PollDescriptorsFlow::Return => return,
+            PollDescriptorsFlow::Ready {
+                status,
+                avail_frames,
+                delay_frames,
+                stream_type,
+            } => {
+                assert_eq!(
+                    stream_type,
+                    StreamType::Input,
+                    "expected input stream, but polling descriptors indicated output",
+                );
+                let res = process_duplex(
+                    input,
+                    output,
+                    &mut ctxt.buffer,
+                    &mut output_buffer,
+                    status,
+                    avail_frames,
+                    delay_frames,
+                    data_callback,
+                );
                 report_error(res, error_callback);
             }
         }
@@ -765,13 +922,16 @@ fn process_input(
     buffer: &mut [u8],
     status: alsa::pcm::Status,
     delay_frames: usize,
-    data_callback: &mut (dyn FnMut(&Data, &InputCallbackInfo) + Send + 'static),
-) -> Result<(), BackendSpecificError> {
+    data_callback: &mut (dyn FnMut(&Data, &InputCallbackInfo) -> CallbackFlow + Send + 'static),
+) -> Result<CallbackFlow, BackendSpecificError> {
     stream.channel.io_bytes().readi(buffer)?;
     let sample_format = stream.sample_format;
+    if !stream.endianness.is_native() {
+        swap_sample_bytes(buffer, sample_format);
+    }
     let data = buffer.as_mut_ptr() as *mut ();
     let len = buffer.len() / sample_format.sample_size();
-    let data = unsafe { Data::from_parts(data, len, sample_format) };
+    let data = unsafe { Data::from_parts(data, len, stream.conf.channels as usize, sample_format) };
     let callback = stream_timestamp(&status, stream.creation_instant)?;
     let delay_duration = frames_to_duration(delay_frames, stream.conf.sample_rate);
     let capture = callback
@@ -779,29 +939,30 @@ fn process_input(
         .expect("`capture` is earlier than representation supported by `StreamInstant`");
     let timestamp = crate::InputStreamTimestamp { callback, capture };
     let info = crate::InputCallbackInfo { timestamp };
-    data_callback(&data, &info);
-
-    Ok(())
+    Ok(data_callback(&data, &info))
 }
 
 // Request data from the user's function and write it via ALSA.
 //
-// Returns `true`
+// Returns what the user's function asked the stream to do next.
 fn process_output(
     stream: &StreamInner,
     buffer: &mut [u8],
     status: alsa::pcm::Status,
     available_frames: usize,
     delay_frames: usize,
-    data_callback: &mut (dyn FnMut(&mut Data, &OutputCallbackInfo) + Send + 'static),
+    data_callback: &mut (dyn FnMut(&mut Data, &OutputCallbackInfo) -> CallbackFlow
+              + Send
+              + 'static),
     error_callback: &mut dyn FnMut(StreamError),
-) -> Result<(), BackendSpecificError> {
-    {
+) -> Result<CallbackFlow, BackendSpecificError> {
+    let flow = {
         // We're now sure that we're ready to write data.
         let sample_format = stream.sample_format;
         let data = buffer.as_mut_ptr() as *mut ();
         let len = buffer.len() / sample_format.sample_size();
-        let mut data = unsafe { Data::from_parts(data, len, sample_format) };
+        let mut data =
+            unsafe { Data::from_parts(data, len, stream.conf.channels as usize, sample_format) };
         let callback = stream_timestamp(&status, stream.creation_instant)?;
         let delay_duration = frames_to_duration(delay_frames, stream.conf.sample_rate);
         let playback = callback
@@ -809,8 +970,12 @@ fn process_output(
             .expect("`playback` occurs beyond representation supported by `StreamInstant`");
         let timestamp = crate::OutputStreamTimestamp { callback, playback };
         let info = crate::OutputCallbackInfo { timestamp };
-        data_callback(&mut data, &info);
-    }
+        let flow = data_callback(&mut data, &info);
+        if !stream.endianness.is_native() {
+            swap_sample_bytes(buffer, sample_format);
+        }
+        flow
+    };
     loop {
         match stream.channel.io_bytes().writei(buffer) {
             Err(err) if err.errno() == nix::errno::Errno::EPIPE => {
@@ -836,6 +1001,125 @@ fn process_output(
             }
         }
     }
+    Ok(flow)
+}
+
+// Plays out the output buffered by `stream`, then stops it.
+fn drain(stream: &StreamInner) -> Result<(), BackendSpecificError> {
+    // The handle is non-blocking, so this only begins draining, which is then waited out here.
+    match stream.channel.drain() {
+        Err(err) if err.errno() != nix::errno::Errno::EAGAIN => return Err(err.into()),
+        _ => (),
+    }
+    while stream.channel.state() == alsa::pcm::State::Draining {
+        let delay_frames = stream.channel.delay().unwrap_or(0).max(0) as usize;
+        let delay = frames_to_duration(delay_frames, stream.conf.sample_rate);
+        thread::sleep(cmp::max(delay, std::time::Duration::from_millis(1)));
+    }
+    Ok(())
+}
+
+// (Re)starts both halves of a duplex stream from any state. The playback buffer is filled with
+// silence first, so that the output written in response to each period of input does not run
+// dry before the next one arrives.
+fn start_duplex(
+    input: &StreamInner,
+    output: &StreamInner,
+    silence: &mut Vec<u8>,
+) -> Result<(), BackendSpecificError> {
+    // Either half may already be stopped, or have been stopped along with the other.
+    input.channel.drop().ok();
+    output.channel.drop().ok();
+    input.channel.prepare()?;
+    output.channel.prepare()?;
+
+    let frames = output.negotiated.buffer_size().unwrap_or(0) as usize;
+    let sample_format = output.sample_format;
+    silence.resize(
+        frames * output.conf.channels as usize * sample_format.sample_size(),
+        0,
+    );
+    fill_silence(silence, sample_format, output.endianness);
+    // Reaching the start threshold starts the playback half, and a linked capture half with it.
+    output.channel.io_bytes().writei(silence)?;
+    if input.channel.state() != alsa::pcm::State::Running {
+        input.channel.start()?;
+    }
+    Ok(())
+}
+
+// Read input data from ALSA, hand it to the user along with a buffer for the same number of
+// frames of output, and write that output via ALSA.
+#[allow(clippy::too_many_arguments)]
+fn process_duplex(
+    input: &StreamInner,
+    output: &StreamInner,
+    input_buffer: &mut [u8],
+    output_buffer: &mut Vec<u8>,
+    status: alsa::pcm::Status,
+    available_frames: usize,
+    delay_frames: usize,
+    data_callback: &mut (dyn FnMut(&Data, &mut Data, &DuplexCallbackInfo) + Send + 'static),
+) -> Result<(), BackendSpecificError> {
+    input.channel.io_bytes().readi(input_buffer)?;
+    let sample_format = input.sample_format;
+    if !input.endianness.is_native() {
+        swap_sample_bytes(input_buffer, sample_format);
+    }
+    let output_channels = output.conf.channels as usize;
+    output_buffer.resize(
+        available_frames * output_channels * sample_format.sample_size(),
+        0,
+    );
+    {
+        let data = input_buffer.as_mut_ptr() as *mut ();
+        let len = input_buffer.len() / sample_format.sample_size();
+        let input_data =
+            unsafe { Data::from_parts(data, len, input.conf.channels as usize, sample_format) };
+        let data = output_buffer.as_mut_ptr() as *mut ();
+        let len = output_buffer.len() / sample_format.sample_size();
+        let mut output_data =
+            unsafe { Data::from_parts(data, len, output_channels, sample_format) };
+
+        let callback = stream_timestamp(&status, input.creation_instant)?;
+        let capture = callback
+            .sub(frames_to_duration(delay_frames, input.conf.sample_rate))
+            .expect("`capture` is earlier than representation supported by `StreamInstant`");
+        let output_delay_frames = output.channel.status()?.get_delay().max(0) as usize;
+        let playback = callback
+            .add(frames_to_duration(
+                output_delay_frames,
+                output.conf.sample_rate,
+            ))
+            .expect("`playback` occurs beyond representation supported by `StreamInstant`");
+        let timestamp = crate::DuplexStreamTimestamp {
+            callback,
+            capture,
+            playback,
+        };
+        let info = crate::DuplexCallbackInfo { timestamp };
+        data_callback(&input_data, &mut output_data, &info);
+    }
+    if !output.endianness.is_native() {
+        swap_sample_bytes(output_buffer, sample_format);
+    }
+
+    let frame_size = output_channels * sample_format.sample_size();
+    let mut written = 0;
+    while written < output_buffer.len() {
+        match output.channel.io_bytes().writei(&output_buffer[written..]) {
+            Ok(frames) => written += frames * frame_size,
+            Err(err) if err.errno() == nix::errno::Errno::EAGAIN => {
+                output.channel.wait(None)?;
+            }
+            Err(err) if err.errno() == nix::errno::Errno::EPIPE => {
+                // Buffer underrun, after which both halves restart to stay aligned.
+                // TODO: Notify the user of this.
+                return start_duplex(input, output, output_buffer);
+            }
+            Err(err) => return Err(err.into()),
+        }
+    }
     Ok(())
 }
 
@@ -871,6 +1155,7 @@ fn stream_timestamp(
 
 // Adapted from `timestamp2ns` here:
 // https://fossies.org/linux/alsa-lib/test/audio_time.c
+#[allow(clippy::unnecessary_cast)]
 fn timespec_to_nanos(ts: libc::timespec) -> i64 {
     ts.tv_sec as i64 * 1_000_000_000 + ts.tv_nsec as i64
 }
@@ -890,14 +1175,16 @@ fn frames_to_duration(frames: usize, rate: crate::SampleRate) -> std::time::Dura
 }
 
 impl Stream {
-    fn new_input<D, E>(
+    fn new_input<D, E, C>(
         inner: Arc<StreamInner>,
         mut data_callback: D,
         mut error_callback: E,
+        completion_callback: C,
     ) -> Stream
     where
-        D: FnMut(&Data, &InputCallbackInfo) + Send + 'static,
+        D: FnMut(&Data, &InputCallbackInfo) -> CallbackFlow + Send + 'static,
         E: FnMut(StreamError) + Send + 'static,
+        C: FnOnce() + Send + 'static,
     {
         let (tx, rx) = trigger();
         // Clone the handle for passing into worker thread.
@@ -905,24 +1192,30 @@ impl Stream {
         let thread = thread::Builder::new()
             .name("cpal_alsa_in".to_owned())
             .spawn(move || {
-                input_stream_worker(rx, &*stream, &mut data_callback, &mut error_callback);
+                if input_stream_worker(&rx, &stream, &mut data_callback, &mut error_callback) {
+                    completion_callback();
+                    wait_for_drop(rx);
+                }
             })
             .unwrap();
         Stream {
             thread: Some(thread),
             inner,
+            duplex_input: None,
             trigger: tx,
         }
     }
 
-    fn new_output<D, E>(
+    fn new_output<D, E, C>(
         inner: Arc<StreamInner>,
         mut data_callback: D,
         mut error_callback: E,
+        completion_callback: C,
     ) -> Stream
     where
-        D: FnMut(&mut Data, &OutputCallbackInfo) + Send + 'static,
+        D: FnMut(&mut Data, &OutputCallbackInfo) -> CallbackFlow + Send + 'static,
         E: FnMut(StreamError) + Send + 'static,
+        C: FnOnce() + Send + 'static,
     {
         let (tx, rx) = trigger();
         // Clone the handle for passing into worker thread.
@@ -930,17 +1223,63 @@ impl Stream {
         let thread = thread::Builder::new()
             .name("cpal_alsa_out".to_owned())
             .spawn(move || {
-                output_stream_worker(rx, &*stream, &mut data_callback, &mut error_callback);
+                if output_stream_worker(&rx, &stream, &mut data_callback, &mut error_callback) {
+                    completion_callback();
+                    wait_for_drop(rx);
+                }
             })
             .unwrap();
         Stream {
             thread: Some(thread),
             inner,
+            duplex_input: None,
+            trigger: tx,
+        }
+    }
+}
+
+impl Stream {
+    fn new_duplex<D, E>(
+        input: Arc<StreamInner>,
+        output: Arc<StreamInner>,
+        mut data_callback: D,
+        mut error_callback: E,
+    ) -> Stream
+    where
+        D: FnMut(&Data, &mut Data, &DuplexCallbackInfo) + Send + 'static,
+        E: FnMut(StreamError) + Send + 'static,
+    {
+        let (tx, rx) = trigger();
+        // Clone the handles for passing into worker thread.
+        let input_stream = input.clone();
+        let output_stream = output.clone();
+        let thread = thread::Builder::new()
+            .name("cpal_alsa_duplex".to_owned())
+            .spawn(move || {
+                duplex_stream_worker(
+                    rx,
+                    &input_stream,
+                    &output_stream,
+                    &mut data_callback,
+                    &mut error_callback,
+                );
+            })
+            .unwrap();
+        Stream {
+            thread: Some(thread),
+            inner: output,
+            duplex_input: Some(input),
             trigger: tx,
         }
     }
 }
 
+// Blocks a worker whose stream has stopped until the stream is dropped, keeping the trigger open
+// for the wakeup sent on drop.
+fn wait_for_drop(rx: TriggerReceiver) {
+    rx.clear_pipe();
+}
+
 impl Drop for Stream {
     fn drop(&mut self) {
         self.trigger.wakeup();
@@ -951,56 +1290,430 @@ impl Drop for Stream {
 impl StreamTrait for Stream {
     fn play(&self) -> Result<(), PlayStreamError> {
         self.inner.channel.pause(false).ok();
+        if let Some(ref input) = self.duplex_input {
+            input.channel.pause(false).ok();
+        }
         Ok(())
     }
     fn pause(&self) -> Result<(), PauseStreamError> {
         self.inner.channel.pause(true).ok();
+        if let Some(ref input) = self.duplex_input {
+            input.channel.pause(true).ok();
+        }
         Ok(())
     }
+    fn buffer_latency(&self) -> Option<std::time::Duration> {
+        let negotiated = &self.inner.negotiated;
+        let buffer_size = negotiated.buffer_size()?;
+        Some(frames_to_duration(
+            buffer_size as usize,
+            negotiated.sample_rate(),
+        ))
+    }
+    fn negotiated_config(&self) -> Option<NegotiatedStreamConfig> {
+        Some(self.inner.negotiated.clone())
+    }
+    fn input_latency(&self) -> Option<std::time::Duration> {
+        match self.inner.stream_type {
+            alsa::Direction::Capture => current_latency(&self.inner),
+            alsa::Direction::Playback => {
+                self.duplex_input.as_ref().and_then(|i| current_latency(i))
+            }
+        }
+    }
+    fn output_latency(&self) -> Option<std::time::Duration> {
+        match self.inner.stream_type {
+            alsa::Direction::Capture => None,
+            alsa::Direction::Playback => current_latency(&self.inner),
+        }
+    }
+}
+
+// The frames that the device currently delays audio by. While the stream is not running, audio
+// would have to pass through the whole buffer.
+fn current_latency(stream: &StreamInner) -> Option<std::time::Duration> {
+    let delay = stream.channel.status().ok()?.get_delay();
+    let frames = if delay > 0 {
+        delay as usize
+    } else {
+        stream.negotiated.buffer_size()? as usize
+    };
+    Some(frames_to_duration(frames, stream.negotiated.sample_rate()))
+}
+
+// The ALSA format laying out samples of `sample_format` in the given byte order.
+fn alsa_format(sample_format: SampleFormat, endianness: Endianness) -> alsa::pcm::Format {
+    match (sample_format, endianness) {
+        (SampleFormat::I8, _) => alsa::pcm::Format::S8,
+        (SampleFormat::U8, _) => alsa::pcm::Format::U8,
+        (SampleFormat::I16, Endianness::Little) => alsa::pcm::Format::S16LE,
+        (SampleFormat::I16, Endianness::Big) => alsa::pcm::Format::S16BE,
+        (SampleFormat::U16, Endianness::Little) => alsa::pcm::Format::U16LE,
+        (SampleFormat::U16, Endianness::Big) => alsa::pcm::Format::U16BE,
+        (SampleFormat::I24, Endianness::Little) => alsa::pcm::Format::S243LE,
+        (SampleFormat::I24, Endianness::Big) => alsa::pcm::Format::S243BE,
+        (SampleFormat::I24In32, Endianness::Little) => alsa::pcm::Format::S24LE,
+        (SampleFormat::I24In32, Endianness::Big) => alsa::pcm::Format::S24BE,
+        (SampleFormat::I32, Endianness::Little) => alsa::pcm::Format::S32LE,
+        (SampleFormat::I32, Endianness::Big) => alsa::pcm::Format::S32BE,
+        (SampleFormat::U32, Endianness::Little) => alsa::pcm::Format::U32LE,
+        (SampleFormat::U32, Endianness::Big) => alsa::pcm::Format::U32BE,
+        (SampleFormat::F32, Endianness::Little) => alsa::pcm::Format::FloatLE,
+        (SampleFormat::F32, Endianness::Big) => alsa::pcm::Format::FloatBE,
+        (SampleFormat::F64, Endianness::Little) => alsa::pcm::Format::Float64LE,
+        (SampleFormat::F64, Endianness::Big) => alsa::pcm::Format::Float64BE,
+    }
+}
+
+// Formats such as `U24`, `S20_3`, `MU_LAW` or `IEC958_SUBFRAME` have no `SampleFormat`
+// counterpart and are not reported.
+const SAMPLE_FORMATS: [SampleFormat; 10] = [
+    SampleFormat::I8,
+    SampleFormat::U8,
+    SampleFormat::I16,
+    SampleFormat::U16,
+    SampleFormat::I24,
+    SampleFormat::I24In32,
+    SampleFormat::I32,
+    SampleFormat::U32,
+    SampleFormat::F32,
+    SampleFormat::F64,
+];
+
+// The sample formats left in the configuration space, with the byte order each is offered in.
+fn supported_sample_formats(hw_params: &alsa::pcm::HwParams) -> Vec<(SampleFormat, Endianness)> {
+    SAMPLE_FORMATS
+        .iter()
+        .filter_map(|&sample_format| {
+            supported_endianness(hw_params, sample_format).map(|e| (sample_format, e))
+        })
+        .collect()
+}
+
+// The buffer sizes left in the configuration space.
+fn supported_buffer_size(
+    hw_params: &alsa::pcm::HwParams,
+) -> Result<SupportedBufferSize, alsa::Error> {
+    let min_buffer_size = hw_params.get_buffer_size_min()?;
+    let max_buffer_size = hw_params.get_buffer_size_max()?;
+    let min_period_size = hw_params.get_period_size_min()?.max(1);
+    let max_period_size = hw_params.get_period_size_max()?.max(1);
+
+    // The `alsa` crate does not expose the bounds on the number of periods, so derive them from
+    // the buffer and period size bounds.
+    let periods = SupportedPeriods {
+        min_period_size: min_period_size as u32,
+        max_period_size: max_period_size as u32,
+        min_periods: cmp::max((min_buffer_size + max_period_size - 1) / max_period_size, 1) as u32,
+        max_periods: cmp::max(max_buffer_size / min_period_size, 1) as u32,
+    };
+
+    Ok(SupportedBufferSize::Range {
+        min: min_buffer_size as u32,
+        max: max_buffer_size as u32,
+        periods: Some(periods),
+    })
+}
+
+// The data layouts that the device offers read and write access for.
+fn supported_data_layouts(pcm_handle: &alsa::pcm::PCM) -> Result<Vec<DataLayout>, alsa::Error> {
+    let mut layouts = Vec::new();
+    for &(access, layout) in [
+        (alsa::pcm::Access::RWInterleaved, DataLayout::Interleaved),
+        (alsa::pcm::Access::RWNonInterleaved, DataLayout::Planar),
+    ]
+    .iter()
+    {
+        if alsa::pcm::HwParams::any(pcm_handle)?
+            .set_access(access)
+            .is_ok()
+        {
+            layouts.push(layout);
+        }
+    }
+    Ok(layouts)
+}
+
+// The byte order in which the device accepts `sample_format`, preferring the native one.
+//
+// Returns `None` if the device supports the format in neither byte order.
+fn supported_endianness(
+    hw_params: &alsa::pcm::HwParams,
+    sample_format: SampleFormat,
+) -> Option<Endianness> {
+    [Endianness::NATIVE, Endianness::NATIVE.swapped()]
+        .iter()
+        .cloned()
+        .find(|&endianness| {
+            hw_params
+                .test_format(alsa_format(sample_format, endianness))
+                .is_ok()
+        })
+}
+
+// Fills `buffer` with silence laid out in the given byte order. Unsigned formats are silent at the
+// midpoint of their range, where only the most significant bit is set.
+fn fill_silence(buffer: &mut [u8], sample_format: SampleFormat, endianness: Endianness) {
+    for byte in buffer.iter_mut() {
+        *byte = 0;
+    }
+    if matches!(
+        sample_format,
+        SampleFormat::U8 | SampleFormat::U16 | SampleFormat::U32
+    ) {
+        let sample_size = sample_format.sample_size();
+        let msb = match endianness {
+            Endianness::Big => 0,
+            Endianness::Little => sample_size - 1,
+        };
+        for sample in buffer.chunks_exact_mut(sample_size) {
+            sample[msb] = 0x80;
+        }
+    }
+}
+
+// Reverses the byte order of every sample in `buffer`.
+fn swap_sample_bytes(buffer: &mut [u8], sample_format: SampleFormat) {
+    let sample_size = sample_format.sample_size();
+    if sample_size > 1 {
+        for sample in buffer.chunks_exact_mut(sample_size) {
+            sample.reverse();
+        }
+    }
+}
+
+// The layout described by an ALSA channel map.
+//
+// The `alsa` crate cannot convert every `SND_CHMAP_*` position, so the map is read through its
+// textual form, e.g. "FL FR RL RR FC LFE". Positions with no `ChannelPosition` counterpart, such
+// as the wide or high front channels, become `Unknown`.
+fn chmap_layout(chmap: &alsa::pcm::Chmap) -> Option<ChannelLayout> {
+    use std::fmt::Write;
+    let mut text = String::new();
+    write!(text, "{}", chmap).ok()?;
+    let positions = text
+        .split_whitespace()
+        .map(|name| {
+            name.trim_end_matches("[INV]")
+                .parse()
+                .unwrap_or(ChannelPosition::Unknown)
+        })
+        .collect();
+    Some(ChannelLayout::new(positions))
+}
+
+// The channel layouts offered by the device, in the order reported by the driver.
+fn supported_channel_layouts(pcm_handle: &alsa::pcm::PCM) -> Vec<ChannelLayout> {
+    pcm_handle
+        .query_chmaps()
+        .filter_map(|(_, chmap)| chmap_layout(&chmap))
+        .collect()
+}
+
+// Applies the requested layout, which must match one of the device's channel maps.
+fn set_channel_layout(
+    pcm_handle: &alsa::pcm::PCM,
+    layout: &ChannelLayout,
+) -> Result<(), BuildStreamError> {
+    let current = pcm_handle.get_chmap().ok();
+    if current.as_ref().and_then(chmap_layout).as_ref() == Some(layout) {
+        return Ok(());
+    }
+    // Only maps listed verbatim can be applied, as the `alsa` crate cannot build a `Chmap`
+    // holding positions such as `FC` or `LFE`.
+    for (_, chmap) in pcm_handle.query_chmaps() {
+        if chmap_layout(&chmap).as_ref() == Some(layout) {
+            pcm_handle.set_chmap(&chmap)?;
+            return Ok(());
+        }
+    }
+    Err(BuildStreamError::StreamConfigNotSupported)
 }
 
 fn set_hw_params_from_format(
     pcm_handle: &alsa::pcm::PCM,
     config: &StreamConfig,
     sample_format: SampleFormat,
-) -> Result<bool, BackendSpecificError> {
+) -> Result<(bool, Endianness), BuildStreamError> {
+    let (hw_params, endianness) = hw_params_from_config(pcm_handle, config, sample_format)?;
+    pcm_handle.hw_params(&hw_params)?;
+    Ok((hw_params.can_pause(), endianness))
+}
+
+// Narrows the configuration space of `pcm_handle` down to `config` without applying it, one
+// parameter at a time in the order access, format, rate, channels, channel layout and buffer
+// size, so that the first parameter the device cannot provide is the one reported. Building a
+// stream and checking a config both go through here.
+fn hw_params_from_config<'a>(
+    pcm_handle: &'a alsa::pcm::PCM,
+    config: &StreamConfig,
+    sample_format: SampleFormat,
+) -> Result<(alsa::pcm::HwParams<'a>, Endianness), CheckStreamConfigError> {
+    let rejected = CheckStreamConfigError::NotSupported;
     let hw_params = alsa::pcm::HwParams::any(pcm_handle)?;
-    hw_params.set_access(alsa::pcm::Access::RWInterleaved)?;
+    if hw_params
+        .set_access(alsa::pcm::Access::RWInterleaved)
+        .is_err()
+    {
+        return Err(rejected(RejectedParameter::AccessMode {
+            requested: DataLayout::Interleaved,
+            supported: supported_data_layouts(pcm_handle)?,
+        }));
+    }
 
-    let sample_format = if cfg!(target_endian = "big") {
-        match sample_format {
-            SampleFormat::I16 => alsa::pcm::Format::S16BE,
-            SampleFormat::U16 => alsa::pcm::Format::U16BE,
-            SampleFormat::F32 => alsa::pcm::Format::FloatBE,
+    // Prefer the native byte order, falling back to the opposite one if that is all the device
+    // offers.
+    let endianness = supported_endianness(&hw_params, sample_format).unwrap_or(Endianness::NATIVE);
+    if hw_params
+        .set_format(alsa_format(sample_format, endianness))
+        .is_err()
+    {
+        return Err(rejected(RejectedParameter::SampleFormat {
+            requested: sample_format,
+            supported: supported_sample_formats(&hw_params)
+                .into_iter()
+                .map(|(sample_format, _)| sample_format)
+                .collect(),
+        }));
+    }
+    if hw_params
+        .set_rate(config.sample_rate.0, alsa::ValueOr::Nearest)
+        .is_err()
+    {
+        let (min, max, rates) = supported_sample_rates(&hw_params)?;
+        return Err(rejected(RejectedParameter::SampleRate {
+            requested: config.sample_rate,
+            min,
+            max,
+            rates,
+        }));
+    }
+    if hw_params.set_channels(config.channels as u32).is_err() {
+        return Err(rejected(RejectedParameter::Channels {
+            requested: config.channels,
+            min: hw_params.get_channels_min()? as ChannelCount,
+            max: hw_params.get_channels_max()? as ChannelCount,
+        }));
+    }
+    if let Some(ref layout) = config.channel_layout {
+        let mut supported: Vec<_> = supported_channel_layouts(pcm_handle)
+            .into_iter()
+            .filter(|supported| supported.channels() == config.channels)
+            .collect();
+        if let Some(current) = pcm_handle.get_chmap().ok().as_ref().and_then(chmap_layout) {
+            if current.channels() == config.channels && !supported.contains(&current) {
+                supported.push(current);
+            }
         }
-    } else {
-        match sample_format {
-            SampleFormat::I16 => alsa::pcm::Format::S16LE,
-            SampleFormat::U16 => alsa::pcm::Format::U16LE,
-            SampleFormat::F32 => alsa::pcm::Format::FloatLE,
+        if !supported.contains(layout) {
+            return Err(rejected(RejectedParameter::ChannelLayout {
+                requested: layout.clone(),
+                supported,
+            }));
         }
-    };
-
-    hw_params.set_format(sample_format)?;
-    hw_params.set_rate(config.sample_rate.0, alsa::ValueOr::Nearest)?;
-    hw_params.set_channels(config.channels as u32)?;
+    }
 
+    let supported_buffer_size = supported_buffer_size(&hw_params)?;
+    let reject_buffer_size = || {
+        rejected(RejectedParameter::BufferSize {
+            requested: config.buffer_size.clone(),
+            supported: supported_buffer_size.clone(),
+        })
+    };
     match config.buffer_size {
         BufferSize::Fixed(v) => {
-            hw_params.set_period_size_near((v / 4) as alsa::pcm::Frames, alsa::ValueOr::Nearest)?;
-            hw_params.set_buffer_size(v as alsa::pcm::Frames)?;
+            hw_params
+                .set_period_size_near((v / 4) as alsa::pcm::Frames, alsa::ValueOr::Nearest)
+                .and_then(|_| hw_params.set_buffer_size(v as alsa::pcm::Frames))
+                .map_err(|_| reject_buffer_size())?;
         }
         BufferSize::Default => {
             // These values together represent a moderate latency and wakeup interval.
             // Without them, we are at the mercy of the device
-            hw_params.set_period_time_near(25_000, alsa::ValueOr::Nearest)?;
-            hw_params.set_buffer_time_near(100_000, alsa::ValueOr::Nearest)?;
+            hw_params
+                .set_period_time_near(25_000, alsa::ValueOr::Nearest)
+                .and_then(|_| hw_params.set_buffer_time_near(100_000, alsa::ValueOr::Nearest))
+                .map_err(|_| reject_buffer_size())?;
+        }
+        BufferSize::Periods {
+            period_size,
+            periods,
+        } => {
+            if period_size.checked_mul(periods).is_none() {
+                return Err(reject_buffer_size());
+            }
+            hw_params
+                .set_period_size(period_size as alsa::pcm::Frames, alsa::ValueOr::Nearest)
+                .and_then(|()| hw_params.set_periods(periods, alsa::ValueOr::Nearest))
+                .map_err(|_| reject_buffer_size())?;
+        }
+        BufferSize::Latency(latency) => {
+            // Split the buffer into four periods, like the default configuration does.
+            let buffer_time = latency.as_micros().min(u32::MAX as u128) as u32;
+            hw_params
+                .set_period_time_near(buffer_time / 4, alsa::ValueOr::Nearest)
+                .and_then(|_| hw_params.set_buffer_time_near(buffer_time, alsa::ValueOr::Nearest))
+                .map_err(|_| reject_buffer_size())?;
         }
     }
 
-    pcm_handle.hw_params(&hw_params)?;
+    if config.strict {
+        if let Some(rejected_parameter) =
+            negotiated_config(&hw_params, sample_format)?.mismatch(config)
+        {
+            return Err(rejected(rejected_parameter));
+        }
+    }
+
+    Ok((hw_params, endianness))
+}
+
+// The range of sample rates allowed by `hw_params`.
+//
+// Devices either accept any rate within their bounds or a handful of standard rates.
+fn supported_sample_rates(
+    hw_params: &alsa::pcm::HwParams,
+) -> Result<(SampleRate, SampleRate, SupportedSampleRates), alsa::Error> {
+    let min_rate = hw_params.get_rate_min()?;
+    let max_rate = hw_params.get_rate_max()?;
+    if min_rate == max_rate || hw_params.test_rate(min_rate + 1).is_ok() {
+        return Ok((
+            SampleRate(min_rate),
+            SampleRate(max_rate),
+            SupportedSampleRates::Continuous,
+        ));
+    }
 
-    Ok(hw_params.can_pause())
+    const RATES: [libc::c_uint; 13] = [
+        5512, 8000, 11025, 16000, 22050, 32000, 44100, 48000, 64000, 88200, 96000, 176400, 192000,
+    ];
+    let rates: Vec<SampleRate> = RATES
+        .iter()
+        .filter(|&&rate| hw_params.test_rate(rate).is_ok())
+        .map(|&rate| SampleRate(rate))
+        .collect();
+    Ok(match (rates.first(), rates.last()) {
+        (Some(&first), Some(&last)) => (first, last, SupportedSampleRates::Discrete(rates)),
+        _ => (
+            SampleRate(min_rate),
+            SampleRate(max_rate),
+            SupportedSampleRates::Continuous,
+        ),
+    })
+}
+
+// The configuration that `hw_params` settle on.
+fn negotiated_config(
+    hw_params: &alsa::pcm::HwParams,
+    sample_format: SampleFormat,
+) -> Result<NegotiatedStreamConfig, alsa::Error> {
+    Ok(NegotiatedStreamConfig {
+        channels: hw_params.get_channels()? as ChannelCount,
+        sample_rate: SampleRate(hw_params.get_rate()?),
+        sample_format,
+        period_size: Some(hw_params.get_period_size()? as FrameCount),
+        buffer_size: Some(hw_params.get_buffer_size()? as FrameCount),
+    })
 }
 
 fn set_sw_params_from_format(
@@ -1058,6 +1771,13 @@ impl From<alsa::Error> for SupportedStreamConfigsError {
     }
 }
 
+impl From<alsa::Error> for CheckStreamConfigError {
+    fn from(err: alsa::Error) -> Self {
+        let err: BackendSpecificError = err.into();
+        err.into()
+    }
+}
+
 impl From<alsa::Error> for PlayStreamError {
     fn from(err: alsa::Error) -> Self {
         let err: BackendSpecificError = err.into();
@@ -1078,3 +1798,241 @@ impl From<alsa::Error> for StreamError {
         err.into()
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::parking_lot::Mutex;
+    use super::{alsa, current_latency, fill_silence, Device};
+    use crate::{
+        BufferSize, BuildStreamError, CallbackFlow, ChannelLayout, CheckStreamConfigError,
+        DuplexStreamConfig, Endianness, RejectedParameter, SampleFormat, SampleRate, StreamConfig,
+        SupportedBufferSize,
+    };
+    use std::sync::mpsc;
+    use std::time::Duration;
+    use traits::{DeviceTrait, StreamTrait};
+
+    // The `null` plugin accepts any hardware parameters, so it works without a sound card.
+    fn null_device() -> Device {
+        Device {
+            name: "null".to_owned(),
+            handles: Mutex::new(Default::default()),
+        }
+    }
+
+    #[test]
+    fn buffer_size_latency() {
+        let config = StreamConfig {
+            channels: 2,
+            sample_rate: SampleRate(48_000),
+            buffer_size: BufferSize::Latency(Duration::from_millis(20)),
+            channel_layout: None,
+            strict: false,
+        };
+        let stream = null_device()
+            .build_stream_inner(&config, SampleFormat::I16, alsa::Direction::Playback)
+            .unwrap();
+        assert_eq!(stream.negotiated.buffer_size(), Some(960));
+        assert_eq!(stream.negotiated.period_size(), Some(240));
+    }
+
+    #[test]
+    fn strict_periods() {
+        let config = StreamConfig {
+            channels: 2,
+            sample_rate: SampleRate(44_100),
+            buffer_size: BufferSize::Periods {
+                period_size: 1000,
+                periods: 3,
+            },
+            channel_layout: None,
+            strict: true,
+        };
+        let stream = null_device()
+            .build_stream_inner(&config, SampleFormat::F32, alsa::Direction::Capture)
+            .unwrap();
+        assert_eq!(stream.negotiated.mismatch(&config), None);
+        assert_eq!(stream.negotiated.sample_rate(), SampleRate(44_100));
+        assert_eq!(stream.negotiated.buffer_size(), Some(3000));
+        assert_eq!(
+            current_latency(&stream),
+            Some(Duration::from_nanos(3000 * 1_000_000_000 / 44_100))
+        );
+    }
+
+    #[test]
+    fn check_config() {
+        let device = null_device();
+        let mut config = StreamConfig {
+            channels: 2,
+            sample_rate: SampleRate(48_000),
+            buffer_size: BufferSize::Fixed(1024),
+            channel_layout: None,
+            strict: false,
+        };
+        assert!(device
+            .check_config(&config, SampleFormat::I16, alsa::Direction::Playback)
+            .is_ok());
+        config.buffer_size = BufferSize::Latency(Duration::from_millis(20));
+        config.strict = true;
+        assert!(device
+            .check_config(&config, SampleFormat::I16, alsa::Direction::Playback)
+            .is_ok());
+        config.channel_layout = Some(ChannelLayout::stereo());
+        assert!(matches!(
+            device.check_config(&config, SampleFormat::I16, alsa::Direction::Playback),
+            Err(CheckStreamConfigError::NotSupported(
+                RejectedParameter::ChannelLayout { .. }
+            ))
+        ));
+        config.channel_layout = None;
+        config.buffer_size = BufferSize::Fixed(1 << 30);
+        let checked = device.check_config(&config, SampleFormat::I16, alsa::Direction::Playback);
+        let built =
+            device.build_stream_inner(&config, SampleFormat::I16, alsa::Direction::Playback);
+        match (checked, built) {
+            (
+                Err(CheckStreamConfigError::NotSupported(checked)),
+                Err(BuildStreamError::ParameterNotSupported(built)),
+            ) => {
+                assert!(matches!(checked, RejectedParameter::BufferSize { .. }));
+                assert_eq!(checked, built);
+            }
+            _ => panic!("expected the buffer size to be rejected"),
+        }
+    }
+
+    #[test]
+    fn rejected_buffer_size() {
+        let config = StreamConfig {
+            channels: 2,
+            sample_rate: SampleRate(48_000),
+            buffer_size: BufferSize::Fixed(1 << 30),
+            channel_layout: None,
+            strict: false,
+        };
+        let result =
+            null_device().build_stream_inner(&config, SampleFormat::I16, alsa::Direction::Playback);
+        match result {
+            Err(BuildStreamError::ParameterNotSupported(RejectedParameter::BufferSize {
+                requested,
+                supported: SupportedBufferSize::Range { min, max, .. },
+            })) => {
+                assert_eq!(requested, BufferSize::Fixed(1 << 30));
+                assert!(min <= max && max < 1 << 30);
+            }
+            Err(err) => panic!("unexpected error: {}", err),
+            Ok(_) => panic!("expected the buffer size to be rejected"),
+        }
+    }
+
+    #[test]
+    fn planar() {
+        let config = StreamConfig {
+            channels: 2,
+            sample_rate: SampleRate(48_000),
+            buffer_size: BufferSize::Fixed(256),
+            channel_layout: None,
+            strict: false,
+        };
+        let (tx, rx) = mpsc::sync_channel(1);
+        let input = null_device()
+            .build_input_stream_planar_raw(
+                &config,
+                SampleFormat::F32,
+                move |data, _| {
+                    let lens: Vec<_> = data.planes::<f32>().unwrap().map(<[f32]>::len).collect();
+                    tx.try_send((lens, data.frame_count())).ok();
+                },
+                |err| panic!("{}", err),
+            )
+            .unwrap();
+        let (lens, frames) = rx.recv_timeout(Duration::from_secs(5)).unwrap();
+        assert_eq!(lens, [frames, frames]);
+        assert!(frames > 0);
+        drop(input);
+    }
+
+    #[test]
+    fn silence() {
+        let mut buffer = [0xff; 4];
+        fill_silence(&mut buffer, SampleFormat::U16, Endianness::Little);
+        assert_eq!(buffer, [0, 0x80, 0, 0x80]);
+        fill_silence(&mut buffer, SampleFormat::U16, Endianness::Big);
+        assert_eq!(buffer, [0x80, 0, 0x80, 0]);
+        fill_silence(&mut buffer, SampleFormat::F32, Endianness::Big);
+        assert_eq!(buffer, [0; 4]);
+    }
+
+    #[test]
+    fn duplex() {
+        let config = DuplexStreamConfig {
+            input_channels: 1,
+            output_channels: 2,
+            sample_rate: SampleRate(48_000),
+            buffer_size: BufferSize::Fixed(256),
+            strict: true,
+        };
+        let (tx, rx) = mpsc::sync_channel(1);
+        let stream = null_device()
+            .build_duplex_stream_raw(
+                &config,
+                SampleFormat::I16,
+                move |input, output, _| {
+                    tx.try_send((input.len(), output.len())).ok();
+                },
+                |err| panic!("{}", err),
+            )
+            .unwrap();
+        let (input_len, output_len) = rx.recv_timeout(Duration::from_secs(5)).unwrap();
+        assert_eq!(input_len * 2, output_len);
+        assert_eq!(stream.negotiated_config().unwrap().channels(), 2);
+        assert!(stream.input_latency().is_some());
+    }
+
+    #[test]
+    fn flow() {
+        let config = StreamConfig {
+            channels: 2,
+            sample_rate: SampleRate(48_000),
+            buffer_size: BufferSize::Fixed(256),
+            channel_layout: None,
+            strict: false,
+        };
+        let device = null_device();
+
+        let (tx, rx) = mpsc::channel();
+        let mut calls = 0;
+        let output = device
+            .build_output_stream_flow_raw(
+                &config,
+                SampleFormat::F32,
+                move |_, _| {
+                    calls += 1;
+                    match calls {
+                        1 => CallbackFlow::Continue,
+                        2 => CallbackFlow::Drain,
+                        _ => panic!("called after draining"),
+                    }
+                },
+                |err| panic!("{}", err),
+                move || tx.send(()).unwrap(),
+            )
+            .unwrap();
+        rx.recv_timeout(Duration::from_secs(5)).unwrap();
+        drop(output);
+
+        let (tx, rx) = mpsc::channel();
+        let input = device
+            .build_input_stream_flow_raw(
+                &config,
+                SampleFormat::F32,
+                |_, _| CallbackFlow::Stop,
+                |err| panic!("{}", err),
+                move || tx.send(()).unwrap(),
+            )
+            .unwrap();
+        rx.recv_timeout(Duration::from_secs(5)).unwrap();
+        drop(input);
+    }
+}
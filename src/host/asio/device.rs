@@ -10,9 +10,11 @@ use BackendSpecificError;
 use DefaultStreamConfigError;
 use DeviceNameError;
 use DevicesError;
+use Endianness;
 use SampleFormat;
 use SampleRate;
 use SupportedBufferSize;
+use SupportedSampleRates;
 use SupportedStreamConfig;
 use SupportedStreamConfigRange;
 use SupportedStreamConfigsError;
@@ -82,8 +84,11 @@ impl Device {
                     channels,
                     min_sample_rate: rate,
                     max_sample_rate: rate,
+                    sample_rates: SupportedSampleRates::Continuous,
                     buffer_size: f.buffer_size.clone(),
                     sample_format: f.sample_format.clone(),
+                    endianness: Endianness::NATIVE,
+                    channel_layout: None,
                 })
             }
         }
@@ -119,8 +124,11 @@ impl Device {
                     channels,
                     min_sample_rate: rate,
                     max_sample_rate: rate,
+                    sample_rates: SupportedSampleRates::Continuous,
                     buffer_size: f.buffer_size.clone(),
                     sample_format: f.sample_format.clone(),
+                    endianness: Endianness::NATIVE,
+                    channel_layout: None,
                 })
             }
         }
@@ -135,6 +143,7 @@ impl Device {
         let buffer_size = SupportedBufferSize::Range {
             min: min as u32,
             max: max as u32,
+            periods: None,
         };
         // Map th ASIO sample type to a CPAL sample type
         let data_type = self.driver.input_data_type().map_err(default_config_err)?;
@@ -145,6 +154,8 @@ impl Device {
             sample_rate,
             buffer_size,
             sample_format,
+            endianness: Endianness::NATIVE,
+            channel_layout: None,
         })
     }
 
@@ -156,6 +167,7 @@ impl Device {
         let buffer_size = SupportedBufferSize::Range {
             min: min as u32,
             max: max as u32,
+            periods: None,
         };
         let data_type = self.driver.output_data_type().map_err(default_config_err)?;
         let sample_format = convert_data_type(&data_type)
@@ -165,6 +177,8 @@ impl Device {
             sample_rate,
             buffer_size,
             sample_format,
+            endianness: Endianness::NATIVE,
+            channel_layout: None,
         })
     }
 }
@@ -209,13 +223,10 @@ pub(crate) fn convert_data_type(ty: &sys::AsioSampleType) -> Option<SampleFormat
         sys::AsioSampleType::ASIOSTInt16LSB => SampleFormat::I16,
         sys::AsioSampleType::ASIOSTFloat32MSB => SampleFormat::F32,
         sys::AsioSampleType::ASIOSTFloat32LSB => SampleFormat::F32,
-        // NOTE: While ASIO does not support these formats directly, the stream callback created by
-        // CPAL supports converting back and forth between the following. This is because many ASIO
-        // drivers only support `Int32` formats, while CPAL does not support this format at all. We
-        // allow for this implicit conversion temporarily until CPAL gets support for an `I32`
-        // format.
-        sys::AsioSampleType::ASIOSTInt32MSB => SampleFormat::I16,
-        sys::AsioSampleType::ASIOSTInt32LSB => SampleFormat::I16,
+        sys::AsioSampleType::ASIOSTInt32MSB => SampleFormat::I32,
+        sys::AsioSampleType::ASIOSTInt32LSB => SampleFormat::I32,
+        sys::AsioSampleType::ASIOSTFloat64MSB => SampleFormat::F64,
+        sys::AsioSampleType::ASIOSTFloat64LSB => SampleFormat::F64,
         _ => return None,
     };
     Some(fmt)
@@ -5,9 +5,9 @@ use self::num_traits::PrimInt;
 use super::parking_lot::Mutex;
 use super::Device;
 use crate::{
-    BackendSpecificError, BufferSize, BuildStreamError, Data, InputCallbackInfo,
-    OutputCallbackInfo, PauseStreamError, PlayStreamError, Sample, SampleFormat, StreamConfig,
-    StreamError,
+    duration_to_frames, BackendSpecificError, BufferSize, BuildStreamError, Data,
+    InputCallbackInfo, OutputCallbackInfo, PauseStreamError, PlayStreamError, Sample, SampleFormat,
+    StreamConfig, StreamError,
 };
 use std;
 use std::sync::atomic::{AtomicBool, Ordering};
@@ -131,7 +131,7 @@ impl Device {
                 // 2. Deliver the interleaved buffer to the callback.
                 let data = interleaved.as_mut_ptr() as *mut ();
                 let len = interleaved.len();
-                let data = Data::from_parts(data, len, B::FORMAT);
+                let data = Data::from_parts(data, len, n_channels, B::FORMAT);
                 let callback = system_time_to_stream_instant(asio_info.system_time);
                 let delay = frames_to_duration(n_frames, sample_rate);
                 let capture = callback
@@ -178,11 +178,8 @@ impl Device {
                     );
                 }
 
-                // TODO: Add support for the following sample formats to CPAL and simplify the
-                // `process_output_callback` function above by removing the unnecessary sample
-                // conversion function.
-                (&sys::AsioSampleType::ASIOSTInt32LSB, SampleFormat::I16) => {
-                    process_input_callback::<i32, i16, _, _>(
+                (&sys::AsioSampleType::ASIOSTInt32LSB, SampleFormat::I32) => {
+                    process_input_callback::<i32, i32, _, _>(
                         &mut data_callback,
                         &mut interleaved,
                         asio_stream,
@@ -191,8 +188,8 @@ impl Device {
                         from_le,
                     );
                 }
-                (&sys::AsioSampleType::ASIOSTInt32MSB, SampleFormat::I16) => {
-                    process_input_callback::<i32, i16, _, _>(
+                (&sys::AsioSampleType::ASIOSTInt32MSB, SampleFormat::I32) => {
+                    process_input_callback::<i32, i32, _, _>(
                         &mut data_callback,
                         &mut interleaved,
                         asio_stream,
@@ -203,9 +200,9 @@ impl Device {
                 }
                 // TODO: Handle endianness conversion for floats? We currently use the `PrimInt`
                 // trait for the `to_le` and `to_be` methods, but this does not support floats.
-                (&sys::AsioSampleType::ASIOSTFloat64LSB, SampleFormat::F32)
-                | (&sys::AsioSampleType::ASIOSTFloat64MSB, SampleFormat::F32) => {
-                    process_input_callback::<f64, f32, _, _>(
+                (&sys::AsioSampleType::ASIOSTFloat64LSB, SampleFormat::F64)
+                | (&sys::AsioSampleType::ASIOSTFloat64MSB, SampleFormat::F64) => {
+                    process_input_callback::<f64, f64, _, _>(
                         &mut data_callback,
                         &mut interleaved,
                         asio_stream,
@@ -326,11 +323,12 @@ impl Device {
             {
                 // 1. Render interleaved buffer from callback.
                 let interleaved: &mut [A] = cast_slice_mut(interleaved);
+                let n_frames = asio_stream.buffer_size as usize;
+                let n_channels = interleaved.len() / n_frames;
                 let data = interleaved.as_mut_ptr() as *mut ();
                 let len = interleaved.len();
-                let mut data = Data::from_parts(data, len, A::FORMAT);
+                let mut data = Data::from_parts(data, len, n_channels, A::FORMAT);
                 let callback = system_time_to_stream_instant(asio_info.system_time);
-                let n_frames = asio_stream.buffer_size as usize;
                 let delay = frames_to_duration(n_frames, sample_rate);
                 let playback = callback
                     .add(delay)
@@ -340,7 +338,6 @@ impl Device {
                 data_callback(&mut data, &info);
 
                 // 2. Silence ASIO channels if necessary.
-                let n_channels = interleaved.len() / n_frames;
                 let buffer_index = asio_info.buffer_index as usize;
                 if silence_asio_buffer {
                     for ch_ix in 0..n_channels {
@@ -401,11 +398,8 @@ impl Device {
                     );
                 }
 
-                // TODO: Add support for the following sample formats to CPAL and simplify the
-                // `process_output_callback` function above by removing the unnecessary sample
-                // conversion function.
-                (SampleFormat::I16, &sys::AsioSampleType::ASIOSTInt32LSB) => {
-                    process_output_callback::<i16, i32, _, _>(
+                (SampleFormat::I32, &sys::AsioSampleType::ASIOSTInt32LSB) => {
+                    process_output_callback::<i32, i32, _, _>(
                         &mut data_callback,
                         &mut interleaved,
                         silence,
@@ -415,8 +409,8 @@ impl Device {
                         to_le,
                     );
                 }
-                (SampleFormat::I16, &sys::AsioSampleType::ASIOSTInt32MSB) => {
-                    process_output_callback::<i16, i32, _, _>(
+                (SampleFormat::I32, &sys::AsioSampleType::ASIOSTInt32MSB) => {
+                    process_output_callback::<i32, i32, _, _>(
                         &mut data_callback,
                         &mut interleaved,
                         silence,
@@ -428,9 +422,9 @@ impl Device {
                 }
                 // TODO: Handle endianness conversion for floats? We currently use the `PrimInt`
                 // trait for the `to_le` and `to_be` methods, but this does not support floats.
-                (SampleFormat::F32, &sys::AsioSampleType::ASIOSTFloat64LSB)
-                | (SampleFormat::F32, &sys::AsioSampleType::ASIOSTFloat64MSB) => {
-                    process_output_callback::<f32, f64, _, _>(
+                (SampleFormat::F64, &sys::AsioSampleType::ASIOSTFloat64LSB)
+                | (SampleFormat::F64, &sys::AsioSampleType::ASIOSTFloat64MSB) => {
+                    process_output_callback::<f64, f64, _, _>(
                         &mut data_callback,
                         &mut interleaved,
                         silence,
@@ -485,7 +479,11 @@ impl Device {
 
         let buffer_size = match config.buffer_size {
             BufferSize::Fixed(v) => Some(v as i32),
+            BufferSize::Latency(latency) => {
+                Some(duration_to_frames(latency, config.sample_rate) as i32)
+            }
             BufferSize::Default => None,
+            BufferSize::Periods { .. } => return Err(BuildStreamError::StreamConfigNotSupported),
         };
 
         // Either create a stream if thers none or had back the
@@ -532,7 +530,11 @@ impl Device {
 
         let buffer_size = match config.buffer_size {
             BufferSize::Fixed(v) => Some(v as i32),
+            BufferSize::Latency(latency) => {
+                Some(duration_to_frames(latency, config.sample_rate) as i32)
+            }
             BufferSize::Default => None,
+            BufferSize::Periods { .. } => return Err(BuildStreamError::StreamConfigNotSupported),
         };
 
         // Either create a stream if thers none or had back the
@@ -593,12 +595,10 @@ impl AsioSample for i16 {
 
 impl AsioSample for i32 {
     fn to_cpal_sample<T: Sample>(&self) -> T {
-        let s = (*self >> 16) as i16;
-        s.to_cpal_sample()
+        T::from(self)
     }
     fn from_cpal_sample<T: Sample>(t: &T) -> Self {
-        let s = i16::from_cpal_sample(t);
-        (s as i32) << 16
+        Sample::from(t)
     }
 }
 
@@ -613,12 +613,10 @@ impl AsioSample for f32 {
 
 impl AsioSample for f64 {
     fn to_cpal_sample<T: Sample>(&self) -> T {
-        let f = *self as f32;
-        f.to_cpal_sample()
+        T::from(self)
     }
     fn from_cpal_sample<T: Sample>(t: &T) -> Self {
-        let f = f32::from_cpal_sample(t);
-        f as f64
+        Sample::from(t)
     }
 }
 
@@ -658,6 +656,7 @@ fn check_config(
         channels,
         sample_rate,
         buffer_size,
+        ..
     } = config;
     // Try and set the sample rate to what the user selected.
     let sample_rate = sample_rate.0.into();
@@ -673,10 +672,15 @@ fn check_config(
             return Err(BuildStreamError::StreamConfigNotSupported);
         }
     }
-    // unsigned formats are not supported by asio
+    // unsigned, 8-bit and 24-bit formats are not supported by asio
     match sample_format {
-        SampleFormat::I16 | SampleFormat::F32 => (),
-        SampleFormat::U16 => return Err(BuildStreamError::StreamConfigNotSupported),
+        SampleFormat::I16 | SampleFormat::I32 | SampleFormat::F32 | SampleFormat::F64 => (),
+        SampleFormat::I8
+        | SampleFormat::U8
+        | SampleFormat::U16
+        | SampleFormat::I24
+        | SampleFormat::I24In32
+        | SampleFormat::U32 => return Err(BuildStreamError::StreamConfigNotSupported),
     }
     if *channels > num_asio_channels {
         return Err(BuildStreamError::StreamConfigNotSupported);
@@ -24,9 +24,10 @@ use traits::{DeviceTrait, HostTrait, StreamTrait};
 
 use crate::{
     BackendSpecificError, BufferSize, BuildStreamError, Data, DefaultStreamConfigError,
-    DeviceNameError, DevicesError, InputCallbackInfo, OutputCallbackInfo, PauseStreamError,
-    PlayStreamError, SampleFormat, SampleRate, StreamConfig, StreamError, SupportedBufferSize,
-    SupportedStreamConfig, SupportedStreamConfigRange, SupportedStreamConfigsError,
+    DeviceNameError, DevicesError, Endianness, InputCallbackInfo, OutputCallbackInfo,
+    PauseStreamError, PlayStreamError, SampleFormat, SampleRate, StreamConfig, StreamError,
+    SupportedBufferSize, SupportedSampleRates, SupportedStreamConfig, SupportedStreamConfigRange,
+    SupportedStreamConfigsError,
 };
 
 use self::enumerate::{
@@ -91,8 +92,11 @@ impl Device {
             channels: stream_config.channels,
             min_sample_rate: stream_config.sample_rate,
             max_sample_rate: stream_config.sample_rate,
+            sample_rates: SupportedSampleRates::Continuous,
             buffer_size: stream_config.buffer_size.clone(),
             sample_format: SUPPORTED_SAMPLE_FORMAT,
+            endianness: Endianness::NATIVE,
+            channel_layout: None,
         }]
         .into_iter())
     }
@@ -112,8 +116,11 @@ impl Device {
                 channels,
                 min_sample_rate: stream_config.sample_rate,
                 max_sample_rate: stream_config.sample_rate,
+                sample_rates: SupportedSampleRates::Continuous,
                 buffer_size: stream_config.buffer_size.clone(),
                 sample_format: SUPPORTED_SAMPLE_FORMAT,
+                endianness: Endianness::NATIVE,
+                channel_layout: None,
             })
             .collect();
         Ok(configs.into_iter())
@@ -194,7 +201,7 @@ impl DeviceTrait for Device {
 
         // Set the buffersize
         match config.buffer_size {
-            BufferSize::Fixed(_) => {
+            BufferSize::Fixed(_) | BufferSize::Periods { .. } | BufferSize::Latency(_) => {
                 return Err(BuildStreamError::StreamConfigNotSupported);
             }
             BufferSize::Default => (),
@@ -219,7 +226,7 @@ impl DeviceTrait for Device {
 
             let data = data as *mut ();
             let len = (data_byte_size as usize / bytes_per_channel) as usize;
-            let data = Data::from_parts(data, len, sample_format);
+            let data = Data::from_parts(data, len, channels as usize, sample_format);
 
             // TODO: Need a better way to get delay, for now we assume a double-buffer offset.
             let callback = match host_time_to_stream_instant(args.time_stamp.mHostTime) {
@@ -262,7 +269,7 @@ impl DeviceTrait for Device {
         E: FnMut(StreamError) + Send + 'static,
     {
         match config.buffer_size {
-            BufferSize::Fixed(_) => {
+            BufferSize::Fixed(_) | BufferSize::Periods { .. } | BufferSize::Latency(_) => {
                 return Err(BuildStreamError::StreamConfigNotSupported);
             }
             BufferSize::Default => (),
@@ -295,7 +302,7 @@ impl DeviceTrait for Device {
 
             let data = data as *mut ();
             let len = (data_byte_size as usize / bytes_per_channel) as usize;
-            let mut data = Data::from_parts(data, len, sample_format);
+            let mut data = Data::from_parts(data, len, channels as usize, sample_format);
 
             let callback = match host_time_to_stream_instant(args.time_stamp.mHostTime) {
                 Err(err) => {
@@ -421,11 +428,17 @@ fn default_input_asbd() -> Result<AudioStreamBasicDescription, coreaudio::Error>
 }
 
 fn stream_config_from_asbd(asbd: AudioStreamBasicDescription) -> SupportedStreamConfig {
-    let buffer_size = SupportedBufferSize::Range { min: 0, max: 0 };
+    let buffer_size = SupportedBufferSize::Range {
+        min: 0,
+        max: 0,
+        periods: None,
+    };
     SupportedStreamConfig {
         channels: asbd.mChannelsPerFrame as u16,
         sample_rate: SampleRate(asbd.mSampleRate as u32),
         buffer_size: buffer_size.clone(),
         sample_format: SUPPORTED_SAMPLE_FORMAT,
+        endianness: Endianness::NATIVE,
+        channel_layout: None,
     }
 }
@@ -22,11 +22,11 @@ use self::coreaudio::sys::{
 };
 use crate::traits::{DeviceTrait, HostTrait, StreamTrait};
 use crate::{
-    BackendSpecificError, BufferSize, BuildStreamError, ChannelCount, Data,
-    DefaultStreamConfigError, DeviceNameError, DevicesError, InputCallbackInfo, OutputCallbackInfo,
-    PauseStreamError, PlayStreamError, SampleFormat, SampleRate, StreamConfig, StreamError,
-    SupportedBufferSize, SupportedStreamConfig, SupportedStreamConfigRange,
-    SupportedStreamConfigsError,
+    duration_to_frames, BackendSpecificError, BufferSize, BuildStreamError, ChannelCount, Data,
+    DefaultStreamConfigError, DeviceNameError, DevicesError, Endianness, InputCallbackInfo,
+    OutputCallbackInfo, PauseStreamError, PlayStreamError, SampleFormat, SampleRate, StreamConfig,
+    StreamError, SupportedBufferSize, SupportedSampleRates, SupportedStreamConfig,
+    SupportedStreamConfigRange, SupportedStreamConfigsError,
 };
 use std::cell::RefCell;
 use std::ffi::CStr;
@@ -286,8 +286,11 @@ impl Device {
                     channels: n_channels as ChannelCount,
                     min_sample_rate: SampleRate(range.mMinimum as _),
                     max_sample_rate: SampleRate(range.mMaximum as _),
+                    sample_rates: SupportedSampleRates::Continuous,
                     buffer_size: buffer_size.clone(),
                     sample_format,
+                    endianness: Endianness::NATIVE,
+                    channel_layout: None,
                 };
                 fmts.push(fmt);
             }
@@ -386,6 +389,8 @@ impl Device {
                 channels: asbd.mChannelsPerFrame as _,
                 buffer_size,
                 sample_format,
+                endianness: Endianness::NATIVE,
+                channel_layout: None,
             };
             Ok(config)
         }
@@ -622,7 +627,7 @@ impl Device {
             BufferSize::Fixed(v) => {
                 let buffer_size_range = get_io_buffer_frame_size_range(&audio_unit)?;
                 match buffer_size_range {
-                    SupportedBufferSize::Range { min, max } => {
+                    SupportedBufferSize::Range { min, max, .. } => {
                         if v >= min && v <= max {
                             audio_unit.set_property(
                                 kAudioDevicePropertyBufferFrameSize,
@@ -637,6 +642,24 @@ impl Device {
                     SupportedBufferSize::Unknown => (),
                 }
             }
+            BufferSize::Latency(latency) => {
+                // Use the closest buffer size that the device supports.
+                let mut v = duration_to_frames(latency, config.sample_rate);
+                if let SupportedBufferSize::Range { min, max, .. } =
+                    get_io_buffer_frame_size_range(&audio_unit)?
+                {
+                    v = v.max(min).min(max);
+                }
+                audio_unit.set_property(
+                    kAudioDevicePropertyBufferFrameSize,
+                    scope,
+                    element,
+                    Some(&v),
+                )?
+            }
+            BufferSize::Periods { .. } => {
+                return Err(BuildStreamError::StreamConfigNotSupported);
+            }
             BufferSize::Default => (),
         }
 
@@ -659,7 +682,7 @@ impl Device {
 
             let data = data as *mut ();
             let len = (data_byte_size as usize / bytes_per_channel) as usize;
-            let data = Data::from_parts(data, len, sample_format);
+            let data = Data::from_parts(data, len, channels as usize, sample_format);
 
             // TODO: Need a better way to get delay, for now we assume a double-buffer offset.
             let callback = match host_time_to_stream_instant(args.time_stamp.mHostTime) {
@@ -716,7 +739,7 @@ impl Device {
             BufferSize::Fixed(v) => {
                 let buffer_size_range = get_io_buffer_frame_size_range(&audio_unit)?;
                 match buffer_size_range {
-                    SupportedBufferSize::Range { min, max } => {
+                    SupportedBufferSize::Range { min, max, .. } => {
                         if v >= min && v <= max {
                             audio_unit.set_property(
                                 kAudioDevicePropertyBufferFrameSize,
@@ -731,6 +754,24 @@ impl Device {
                     SupportedBufferSize::Unknown => (),
                 }
             }
+            BufferSize::Latency(latency) => {
+                // Use the closest buffer size that the device supports.
+                let mut v = duration_to_frames(latency, config.sample_rate);
+                if let SupportedBufferSize::Range { min, max, .. } =
+                    get_io_buffer_frame_size_range(&audio_unit)?
+                {
+                    v = v.max(min).min(max);
+                }
+                audio_unit.set_property(
+                    kAudioDevicePropertyBufferFrameSize,
+                    scope,
+                    element,
+                    Some(&v),
+                )?
+            }
+            BufferSize::Periods { .. } => {
+                return Err(BuildStreamError::StreamConfigNotSupported);
+            }
             BufferSize::Default => (),
         }
 
@@ -751,7 +792,7 @@ impl Device {
 
             let data = data as *mut ();
             let len = (data_byte_size as usize / bytes_per_channel) as usize;
-            let mut data = Data::from_parts(data, len, sample_format);
+            let mut data = Data::from_parts(data, len, channels as usize, sample_format);
 
             let callback = match host_time_to_stream_instant(args.time_stamp.mHostTime) {
                 Err(err) => {
@@ -838,5 +879,6 @@ fn get_io_buffer_frame_size_range(
     Ok(SupportedBufferSize::Range {
         min: buffer_size_range.mMinimum as u32,
         max: buffer_size_range.mMaximum as u32,
+        periods: None,
     })
 }
@@ -52,7 +52,9 @@ fn asbd_from_config(
     let frames_per_packet = 1;
     let bytes_per_packet = frames_per_packet * bytes_per_frame;
     let format_flags = match sample_format {
-        SampleFormat::F32 => (kAudioFormatFlagIsFloat | kAudioFormatFlagIsPacked) as u32,
+        SampleFormat::F32 | SampleFormat::F64 => {
+            (kAudioFormatFlagIsFloat | kAudioFormatFlagIsPacked) as u32
+        }
         _ => kAudioFormatFlagIsPacked as u32,
     };
     AudioStreamBasicDescription {
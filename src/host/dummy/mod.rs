@@ -1,14 +1,19 @@
 use std::{
-    sync::mpsc::{self, Sender},
+    cmp,
+    sync::mpsc::{self, RecvTimeoutError, Sender},
     thread::{self, JoinHandle},
+    time::Duration,
 };
 
+use crate::flow::fill_equilibrium;
 use crate::{
-    BuildStreamError, Data, DefaultStreamConfigError, DeviceNameError, DevicesError,
-    InputCallbackInfo, OutputCallbackInfo, OutputStreamTimestamp, PauseStreamError,
-    PlayStreamError, SampleFormat, SampleRate, StreamConfig, StreamError, StreamInstant,
-    SupportedBufferSize, SupportedStreamConfig, SupportedStreamConfigRange,
-    SupportedStreamConfigsError,
+    duration_to_frames, BufferSize, BuildStreamError, ChannelCount, CheckStreamConfigError, Data,
+    DefaultStreamConfigError, DeviceNameError, DevicesError, DuplexCallbackInfo,
+    DuplexStreamConfig, DuplexStreamTimestamp, Endianness, FrameCount, InputCallbackInfo,
+    InputStreamTimestamp, NegotiatedStreamConfig, OutputCallbackInfo, OutputStreamTimestamp,
+    PauseStreamError, PlayStreamError, RejectedParameter, SampleFormat, SampleRate, StreamConfig,
+    StreamError, StreamInstant, SupportedBufferSize, SupportedSampleRates, SupportedStreamConfig,
+    SupportedStreamConfigRange, SupportedStreamConfigsError,
 };
 use traits::{DeviceTrait, HostTrait, StreamTrait};
 
@@ -24,6 +29,9 @@ pub struct Host;
 pub struct Stream {
     audio_thread: Option<JoinHandle<()>>,
     sender: Option<Sender<()>>,
+    negotiated: NegotiatedStreamConfig,
+    is_input: bool,
+    is_output: bool,
 }
 
 impl Drop for Stream {
@@ -38,8 +46,8 @@ impl Drop for Stream {
     }
 }
 
-pub struct SupportedInputConfigs;
-pub struct SupportedOutputConfigs;
+pub type SupportedInputConfigs = std::vec::IntoIter<SupportedStreamConfigRange>;
+pub type SupportedOutputConfigs = std::vec::IntoIter<SupportedStreamConfigRange>;
 
 impl Host {
     #[allow(dead_code)]
@@ -68,14 +76,14 @@ impl DeviceTrait for Device {
     fn supported_input_configs(
         &self,
     ) -> Result<SupportedInputConfigs, SupportedStreamConfigsError> {
-        Ok(SupportedInputConfigs {})
+        Ok(supported_configs().into_iter())
     }
 
     #[inline]
     fn supported_output_configs(
         &self,
     ) -> Result<SupportedOutputConfigs, SupportedStreamConfigsError> {
-        Ok(SupportedOutputConfigs {})
+        Ok(supported_configs().into_iter())
     }
 
     #[inline]
@@ -84,47 +92,96 @@ impl DeviceTrait for Device {
             channels: 1,
             sample_rate: SampleRate(48000),
             buffer_size: SupportedBufferSize::Range {
-                min: 0,
+                min: 1,
                 max: u32::MAX,
+                periods: None,
             },
             sample_format: SampleFormat::F32,
+            endianness: Endianness::NATIVE,
+            channel_layout: None,
         })
     }
 
+    fn check_input_config(
+        &self,
+        config: &StreamConfig,
+        _sample_format: SampleFormat,
+    ) -> Result<(), CheckStreamConfigError> {
+        check_config(config).map_err(CheckStreamConfigError::NotSupported)
+    }
+
+    fn check_output_config(
+        &self,
+        config: &StreamConfig,
+        _sample_format: SampleFormat,
+    ) -> Result<(), CheckStreamConfigError> {
+        check_config(config).map_err(CheckStreamConfigError::NotSupported)
+    }
+
     #[inline]
     fn default_output_config(&self) -> Result<SupportedStreamConfig, DefaultStreamConfigError> {
         Ok(SupportedStreamConfig {
             channels: 1,
             sample_rate: SampleRate(48000),
             buffer_size: SupportedBufferSize::Range {
-                min: 0,
+                min: 1,
                 max: u32::MAX,
+                periods: None,
             },
             sample_format: SampleFormat::F32,
+            endianness: Endianness::NATIVE,
+            channel_layout: None,
         })
     }
 
+    /// Create an input stream, whose callback receives silence once per buffer.
     fn build_input_stream_raw<D, E>(
         &self,
-        _config: &StreamConfig,
-        _sample_format: SampleFormat,
-        _data_callback: D,
+        config: &StreamConfig,
+        sample_format: SampleFormat,
+        mut data_callback: D,
         _error_callback: E,
     ) -> Result<Self::Stream, BuildStreamError>
     where
         D: FnMut(&Data, &InputCallbackInfo) + Send + 'static,
         E: FnMut(StreamError) + Send + 'static,
     {
+        check_config(config).map_err(BuildStreamError::ParameterNotSupported)?;
+        let channels = config.channels as usize;
+        let frames = buffer_frames(config);
+        let period = buffer_duration(config);
+        let (sender, receiver) = mpsc::channel();
+        let handle = thread::spawn(move || {
+            // Large and aligned enough to hold the samples in any `SampleFormat`.
+            let mut buffer = vec![0f64; frames * channels];
+            let data = buffer.as_mut_ptr() as *mut ();
+            let mut data = unsafe { Data::from_parts(data, buffer.len(), channels, sample_format) };
+            fill_equilibrium(&mut data);
+            let mut capture = StreamInstant::new(0, 0);
+            // Deliver a buffer each time one would have been captured, until the stream is dropped.
+            while let Err(RecvTimeoutError::Timeout) = receiver.recv_timeout(period) {
+                let callback = advance(capture, period);
+                let info = InputCallbackInfo {
+                    timestamp: InputStreamTimestamp { callback, capture },
+                };
+                data_callback(&data, &info);
+                capture = callback;
+            }
+        });
+
         Ok(Self::Stream {
-            audio_thread: None,
-            sender: None,
+            audio_thread: Some(handle),
+            sender: Some(sender),
+            negotiated: negotiated_config(config, sample_format),
+            is_input: true,
+            is_output: false,
         })
     }
 
     /// Create an output stream.
     fn build_output_stream_raw<D, E>(
         &self,
-        _config: &StreamConfig,
+        config: &StreamConfig,
         sample_format: SampleFormat,
         mut data_callback: D,
         _error_callback: E,
@@ -133,15 +190,75 @@ impl DeviceTrait for Device {
         D: FnMut(&mut Data, &OutputCallbackInfo) + Send + 'static,
         E: FnMut(StreamError) + Send + 'static,
     {
+        check_config(config).map_err(BuildStreamError::ParameterNotSupported)?;
+        let channels = config.channels as usize;
+        let frames = buffer_frames(config);
+        let period = buffer_duration(config);
         let (sender, receiver) = mpsc::channel();
         let handle = thread::spawn(move || {
-            let mut buf = [0f32; 128];
-            let buffer: &mut [f32] = &mut buf;
+            // Large and aligned enough to hold the samples in any `SampleFormat`.
+            let mut buffer = vec![0f64; frames * channels];
             let data = buffer.as_mut_ptr() as *mut ();
-            let mut data = unsafe { Data::from_parts(data, 128, sample_format) };
-            let info = OutputCallbackInfo {
-                timestamp: OutputStreamTimestamp {
+            let mut data = unsafe { Data::from_parts(data, buffer.len(), channels, sample_format) };
+            let mut callback = StreamInstant::new(0, 0);
+            // Request a buffer each time one would have been played, until the stream is dropped.
+            while let Err(RecvTimeoutError::Timeout) = receiver.recv_timeout(period) {
+                callback = advance(callback, period);
+                let info = OutputCallbackInfo {
+                    timestamp: OutputStreamTimestamp {
+                        callback,
+                        playback: advance(callback, period),
+                    },
+                };
+                data_callback(&mut data, &info);
+            }
+        });
+
+        Ok(Self::Stream {
+            audio_thread: Some(handle),
+            sender: Some(sender),
+            negotiated: negotiated_config(config, sample_format),
+            is_input: false,
+            is_output: true,
+        })
+    }
+
+    /// Create a duplex stream, whose callback always receives silence.
+    fn build_duplex_stream_raw<D, E>(
+        &self,
+        config: &DuplexStreamConfig,
+        sample_format: SampleFormat,
+        mut data_callback: D,
+        _error_callback: E,
+    ) -> Result<Self::Stream, BuildStreamError>
+    where
+        D: FnMut(&Data, &mut Data, &DuplexCallbackInfo) + Send + 'static,
+        E: FnMut(StreamError) + Send + 'static,
+    {
+        let input_config = config.input_config();
+        let output_config = config.output_config();
+        check_config(&input_config).map_err(BuildStreamError::ParameterNotSupported)?;
+        check_config(&output_config).map_err(BuildStreamError::ParameterNotSupported)?;
+        let input_channels = config.input_channels as usize;
+        let output_channels = config.output_channels as usize;
+        let frames = buffer_frames(&output_config);
+        let (sender, receiver) = mpsc::channel();
+        let handle = thread::spawn(move || {
+            // Large and aligned enough to hold the samples in any `SampleFormat`.
+            let mut input_buffer = vec![0f64; frames * input_channels];
+            let mut output_buffer = vec![0f64; frames * output_channels];
+            let input = input_buffer.as_mut_ptr() as *mut ();
+            let input = unsafe {
+                Data::from_parts(input, input_buffer.len(), input_channels, sample_format)
+            };
+            let output = output_buffer.as_mut_ptr() as *mut ();
+            let mut output = unsafe {
+                Data::from_parts(output, output_buffer.len(), output_channels, sample_format)
+            };
+            let info = DuplexCallbackInfo {
+                timestamp: DuplexStreamTimestamp {
                     callback: StreamInstant { secs: 0, nanos: 0 },
+                    capture: StreamInstant { secs: 0, nanos: 0 },
                     playback: StreamInstant { secs: 0, nanos: 0 },
                 },
             };
@@ -149,13 +266,16 @@ impl DeviceTrait for Device {
                 if let Ok(()) = receiver.try_recv() {
                     break;
                 }
-                data_callback(&mut data, &info);
+                data_callback(&input, &mut output, &info);
             }
         });
 
         Ok(Self::Stream {
             audio_thread: Some(handle),
             sender: Some(sender),
+            negotiated: negotiated_config(&output_config, sample_format),
+            is_input: true,
+            is_output: true,
         })
     }
 }
@@ -189,6 +309,130 @@ impl StreamTrait for Stream {
     fn pause(&self) -> Result<(), PauseStreamError> {
         Ok(())
     }
+
+    fn buffer_latency(&self) -> Option<Duration> {
+        let frames = self.negotiated.buffer_size()?;
+        Some(Duration::from_secs_f64(
+            frames as f64 / self.negotiated.sample_rate().0 as f64,
+        ))
+    }
+
+    fn negotiated_config(&self) -> Option<NegotiatedStreamConfig> {
+        Some(self.negotiated.clone())
+    }
+
+    fn input_latency(&self) -> Option<Duration> {
+        self.buffer_latency().filter(|_| self.is_input)
+    }
+
+    fn output_latency(&self) -> Option<Duration> {
+        self.buffer_latency().filter(|_| self.is_output)
+    }
+}
+
+// The number of frames passed to each callback, which defaults to 128 samples' worth. Zero if the
+// requested periods hold more frames than a `FrameCount` can count.
+fn buffer_frames(config: &StreamConfig) -> usize {
+    match config.buffer_size {
+        BufferSize::Default => cmp::max(128 / config.channels as usize, 1),
+        BufferSize::Fixed(frames) => frames as usize,
+        BufferSize::Periods {
+            period_size,
+            periods,
+        } => period_size.checked_mul(periods).unwrap_or(0) as usize,
+        BufferSize::Latency(latency) => duration_to_frames(latency, config.sample_rate) as usize,
+    }
+}
+
+// The time each buffer takes to play or capture, which is also the time between callbacks.
+fn buffer_duration(config: &StreamConfig) -> Duration {
+    Duration::from_secs_f64(buffer_frames(config) as f64 / config.sample_rate.0 as f64)
+}
+
+fn advance(instant: StreamInstant, duration: Duration) -> StreamInstant {
+    instant
+        .add(duration)
+        .expect("dummy stream time beyond representation supported by `StreamInstant`")
+}
+
+// Any config works, as long as it leaves the stream with some samples to process.
+fn check_config(config: &StreamConfig) -> Result<(), RejectedParameter> {
+    Err(if config.channels == 0 {
+        RejectedParameter::Channels {
+            requested: config.channels,
+            min: 1,
+            max: ChannelCount::MAX,
+        }
+    } else if config.sample_rate.0 == 0 {
+        RejectedParameter::SampleRate {
+            requested: config.sample_rate,
+            min: SampleRate(1),
+            max: SampleRate(u32::MAX),
+            rates: SupportedSampleRates::Continuous,
+        }
+    } else if buffer_frames(config) == 0 {
+        RejectedParameter::BufferSize {
+            requested: config.buffer_size.clone(),
+            supported: SupportedBufferSize::Range {
+                min: 1,
+                max: u32::MAX,
+                periods: None,
+            },
+        }
+    } else {
+        return Ok(());
+    })
+}
+
+// Every sample format at any buffer size. Builds accept any number of channels and any rate, but
+// only the common ones are listed.
+fn supported_configs() -> Vec<SupportedStreamConfigRange> {
+    const MAX_LISTED_CHANNELS: ChannelCount = 8;
+    let mut configs = Vec::new();
+    for &sample_format in &SAMPLE_FORMATS {
+        for channels in 1..=MAX_LISTED_CHANNELS {
+            configs.push(SupportedStreamConfigRange {
+                channels,
+                min_sample_rate: SampleRate(8_000),
+                max_sample_rate: SampleRate(192_000),
+                sample_rates: SupportedSampleRates::Continuous,
+                buffer_size: SupportedBufferSize::Range {
+                    min: 1,
+                    max: u32::MAX,
+                    periods: None,
+                },
+                sample_format,
+                endianness: Endianness::NATIVE,
+                channel_layout: None,
+            });
+        }
+    }
+    configs
+}
+
+const SAMPLE_FORMATS: [SampleFormat; 10] = [
+    SampleFormat::I8,
+    SampleFormat::U8,
+    SampleFormat::I16,
+    SampleFormat::U16,
+    SampleFormat::I24,
+    SampleFormat::I24In32,
+    SampleFormat::I32,
+    SampleFormat::U32,
+    SampleFormat::F32,
+    SampleFormat::F64,
+];
+
+// Every request is honoured exactly, with each callback processing the whole buffer.
+fn negotiated_config(config: &StreamConfig, sample_format: SampleFormat) -> NegotiatedStreamConfig {
+    let frames = buffer_frames(config) as FrameCount;
+    NegotiatedStreamConfig {
+        channels: config.channels,
+        sample_rate: config.sample_rate,
+        sample_format,
+        period_size: Some(frames),
+        buffer_size: Some(frames),
+    }
 }
 
 impl Iterator for Devices {
@@ -200,20 +444,231 @@ impl Iterator for Devices {
     }
 }
 
-impl Iterator for SupportedInputConfigs {
-    type Item = SupportedStreamConfigRange;
+#[cfg(test)]
+mod test {
+    use super::Device;
+    use crate::traits::{DeviceTrait, StreamTrait};
+    use crate::{
+        BufferSize, BuildStreamError, CallbackFlow, ConfigConstraints, DuplexStreamConfig,
+        RejectedParameter, SampleFormat, SampleRate, StreamConfig,
+    };
+    use std::sync::mpsc;
+    use std::time::Duration;
 
-    #[inline]
-    fn next(&mut self) -> Option<SupportedStreamConfigRange> {
-        None
+    #[test]
+    fn buffer_size_latency() {
+        let config = StreamConfig {
+            channels: 2,
+            sample_rate: SampleRate(48_000),
+            buffer_size: BufferSize::Latency(Duration::from_millis(10)),
+            channel_layout: None,
+            strict: false,
+        };
+        let stream = Device
+            .build_output_stream_raw(
+                &config,
+                SampleFormat::F32,
+                |data, _| assert_eq!(data.frame_count(), 480),
+                |err| panic!("{}", err),
+            )
+            .unwrap();
+        assert_eq!(stream.buffer_latency(), Some(Duration::from_millis(10)));
+        assert_eq!(stream.output_latency(), Some(Duration::from_millis(10)));
+        assert_eq!(stream.input_latency(), None);
     }
-}
 
-impl Iterator for SupportedOutputConfigs {
-    type Item = SupportedStreamConfigRange;
+    #[test]
+    fn query() {
+        let supported = Device
+            .query_output_config(&ConfigConstraints::default())
+            .unwrap();
+        assert_eq!(supported.sample_rate(), SampleRate(192_000));
+        let config = supported.config();
+        Device
+            .check_output_config(&config, supported.sample_format())
+            .unwrap();
+        Device
+            .build_output_stream_raw(
+                &config,
+                supported.sample_format(),
+                |_, _| (),
+                |err| panic!("{}", err),
+            )
+            .unwrap();
+    }
 
-    #[inline]
-    fn next(&mut self) -> Option<SupportedStreamConfigRange> {
-        None
+    #[test]
+    fn unlisted_config_checked() {
+        let config = StreamConfig {
+            channels: 16,
+            sample_rate: SampleRate(384_000),
+            buffer_size: BufferSize::Fixed(64),
+            channel_layout: None,
+            strict: false,
+        };
+        Device
+            .check_input_config(&config, SampleFormat::I16)
+            .unwrap();
+        Device
+            .check_output_config(&config, SampleFormat::I16)
+            .unwrap();
+        let zero_channels = StreamConfig {
+            channels: 0,
+            ..config
+        };
+        assert!(Device
+            .check_output_config(&zero_channels, SampleFormat::I16)
+            .is_err());
+    }
+
+    #[test]
+    fn unlisted_config_adapted() {
+        let config = StreamConfig {
+            channels: 16,
+            sample_rate: SampleRate(384_000),
+            buffer_size: BufferSize::Fixed(64),
+            channel_layout: None,
+            strict: false,
+        };
+        let stream = Device
+            .build_output_stream_adapted::<i16, _, _>(
+                &config,
+                None,
+                |data, _| assert_eq!(data.len(), 16 * 64),
+                |err| panic!("{}", err),
+            )
+            .unwrap();
+        let negotiated = stream.negotiated_config().unwrap();
+        assert_eq!(negotiated.sample_format(), SampleFormat::I16);
+    }
+
+    #[test]
+    fn empty_buffer_rejected() {
+        let config = StreamConfig {
+            channels: 2,
+            sample_rate: SampleRate(48_000),
+            buffer_size: BufferSize::Fixed(0),
+            channel_layout: None,
+            strict: false,
+        };
+        let result = Device.build_input_stream_raw(
+            &config,
+            SampleFormat::F32,
+            |_, _| (),
+            |err| panic!("{}", err),
+        );
+        match result {
+            Err(BuildStreamError::ParameterNotSupported(RejectedParameter::BufferSize {
+                requested,
+                ..
+            })) => assert_eq!(requested, BufferSize::Fixed(0)),
+            _ => panic!("expected the buffer size to be rejected"),
+        }
+    }
+
+    #[test]
+    fn overflowing_periods_rejected() {
+        let config = StreamConfig {
+            channels: 2,
+            sample_rate: SampleRate(48_000),
+            buffer_size: BufferSize::Periods {
+                period_size: 1 << 16,
+                periods: 1 << 16,
+            },
+            channel_layout: None,
+            strict: false,
+        };
+        let result = Device.build_output_stream_raw(
+            &config,
+            SampleFormat::F32,
+            |_, _| (),
+            |err| panic!("{}", err),
+        );
+        assert!(matches!(
+            result,
+            Err(BuildStreamError::ParameterNotSupported(
+                RejectedParameter::BufferSize { .. }
+            ))
+        ));
+    }
+
+    #[test]
+    fn frame_channels_rejected() {
+        let config = StreamConfig {
+            channels: 2,
+            sample_rate: SampleRate(48_000),
+            buffer_size: BufferSize::Fixed(64),
+            channel_layout: None,
+            strict: false,
+        };
+        let result = Device.build_output_stream_frames(
+            &config,
+            |_: &mut [[f32; 6]], _| (),
+            |err| panic!("{}", err),
+        );
+        match result {
+            Err(BuildStreamError::ParameterNotSupported(rejected)) => assert_eq!(
+                rejected,
+                RejectedParameter::Channels {
+                    requested: 2,
+                    min: 6,
+                    max: 6,
+                }
+            ),
+            _ => panic!("expected the channel count to be rejected"),
+        }
+    }
+
+    #[test]
+    fn drain() {
+        let config = StreamConfig {
+            channels: 2,
+            sample_rate: SampleRate(48_000),
+            buffer_size: BufferSize::Fixed(64),
+            channel_layout: None,
+            strict: false,
+        };
+        let (sender, receiver) = mpsc::channel();
+        let mut calls = 0;
+        let _stream = Device
+            .build_output_stream_flow(
+                &config,
+                move |data: &mut [f32], _| {
+                    calls += 1;
+                    data.iter_mut().for_each(|sample| *sample = 0.5);
+                    match calls {
+                        1 => CallbackFlow::Drain,
+                        _ => panic!("called after draining"),
+                    }
+                },
+                |err| panic!("{}", err),
+                move || sender.send(()).unwrap(),
+            )
+            .unwrap();
+        receiver.recv_timeout(Duration::from_secs(2)).unwrap();
+    }
+
+    #[test]
+    fn duplex() {
+        let config = DuplexStreamConfig {
+            input_channels: 1,
+            output_channels: 2,
+            sample_rate: SampleRate(48_000),
+            buffer_size: BufferSize::Fixed(64),
+            strict: false,
+        };
+        let (sender, receiver) = mpsc::sync_channel(1);
+        let stream = Device
+            .build_duplex_stream(
+                &config,
+                move |input: &[i16], output: &mut [i16], _| {
+                    let _ = sender.try_send((input.len(), output.len()));
+                },
+                |err| panic!("{}", err),
+            )
+            .unwrap();
+        assert_eq!(receiver.recv(), Ok((64, 128)));
+        assert_eq!(stream.input_latency(), stream.output_latency());
+        assert_eq!(stream.negotiated_config().unwrap().channels(), 2);
     }
 }
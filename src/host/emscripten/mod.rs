@@ -9,9 +9,9 @@ use stdweb::Reference;
 
 use crate::{
     BufferSize, BuildStreamError, Data, DefaultStreamConfigError, DeviceNameError, DevicesError,
-    InputCallbackInfo, OutputCallbackInfo, PauseStreamError, PlayStreamError, SampleFormat,
-    SampleRate, StreamConfig, StreamError, SupportedBufferSize, SupportedStreamConfig,
-    SupportedStreamConfigRange, SupportedStreamConfigsError,
+    Endianness, InputCallbackInfo, OutputCallbackInfo, PauseStreamError, PlayStreamError,
+    SampleFormat, SampleRate, StreamConfig, StreamError, SupportedBufferSize, SupportedSampleRates,
+    SupportedStreamConfig, SupportedStreamConfigRange, SupportedStreamConfigsError,
 };
 use traits::{DeviceTrait, HostTrait, StreamTrait};
 
@@ -84,14 +84,18 @@ impl Device {
         let buffer_size = SupportedBufferSize::Range {
             min: MIN_BUFFER_SIZE,
             max: MAX_BUFFER_SIZE,
+            periods: None,
         };
         let configs: Vec<_> = (MIN_CHANNELS..=MAX_CHANNELS)
             .map(|channels| SupportedStreamConfigRange {
                 channels,
                 min_sample_rate: MIN_SAMPLE_RATE,
                 max_sample_rate: MAX_SAMPLE_RATE,
+                sample_rates: SupportedSampleRates::Continuous,
                 buffer_size: buffer_size.clone(),
                 sample_format: SUPPORTED_SAMPLE_FORMAT,
+                endianness: Endianness::NATIVE,
+                channel_layout: None,
             })
             .collect();
         Ok(configs.into_iter())
@@ -202,7 +206,12 @@ impl DeviceTrait for Device {
                     v as usize
                 }
             }
+            BufferSize::Latency(latency) => match duration_to_frames(latency, config.sample_rate) {
+                0 => return Err(BuildStreamError::StreamConfigNotSupported),
+                v => v as usize,
+            },
             BufferSize::Default => DEFAULT_BUFFER_SIZE,
+            BufferSize::Periods { .. } => return Err(BuildStreamError::StreamConfigNotSupported),
         };
 
         // Create the stream.
@@ -276,7 +285,7 @@ fn audio_callback_fn<D, E>(
         {
             let len = temporary_buffer.len();
             let data = temporary_buffer.as_mut_ptr() as *mut ();
-            let mut data = Data::from_parts(data, len, sample_format);
+            let mut data = Data::from_parts(data, len, num_channels, sample_format);
 
             let now_secs: f64 = js!(@{audio_ctxt}.getOutputTimestamp().currentTime)
                 .try_into()
@@ -1,11 +1,13 @@
 use crate::{
-    BackendSpecificError, BuildStreamError, Data, DefaultStreamConfigError, DeviceNameError,
-    InputCallbackInfo, OutputCallbackInfo, SampleFormat, SampleRate, StreamConfig, StreamError,
-    SupportedBufferSize, SupportedStreamConfig, SupportedStreamConfigRange,
-    SupportedStreamConfigsError,
+    BackendSpecificError, BufferSize, BuildStreamError, ChannelCount, ChannelLayout,
+    ChannelPosition, CheckStreamConfigError, Data, DataLayout, DefaultStreamConfigError,
+    DeviceNameError, DuplexCallbackInfo, DuplexStreamConfig, Endianness, InputCallbackInfo,
+    NegotiatedStreamConfig, OutputCallbackInfo, RejectedParameter, SampleFormat, SampleRate,
+    StreamConfig, StreamError, SupportedBufferSize, SupportedSampleRates, SupportedStreamConfig,
+    SupportedStreamConfigRange, SupportedStreamConfigsError,
 };
 use std::hash::{Hash, Hasher};
-use traits::DeviceTrait;
+use traits::{DeviceTrait, StreamTrait};
 
 use super::stream::Stream;
 use super::JACK_SAMPLE_FORMAT;
@@ -17,7 +19,7 @@ const DEFAULT_NUM_CHANNELS: u16 = 2;
 const DEFAULT_SUPPORTED_CHANNELS: [u16; 10] = [1, 2, 4, 6, 8, 16, 24, 32, 48, 64];
 
 /// If a device is for input or output.
-/// Until we have duplex stream support JACK clients and CPAL devices for JACK will be either input or output.
+/// Either kind of device can build duplex streams, which register ports in both directions.
 #[derive(Clone, Debug)]
 pub enum DeviceType {
     InputDevice,
@@ -31,6 +33,8 @@ pub struct Device {
     device_type: DeviceType,
     start_server_automatically: bool,
     connect_ports_automatically: bool,
+    // The positions of the system ports that the streams connect to, in connection order.
+    system_port_positions: Vec<ChannelPosition>,
 }
 
 impl Device {
@@ -48,12 +52,24 @@ impl Device {
         // This is a hack due to the fact that the Client must be moved to create the AsyncClient.
         match super::get_client(&name, client_options) {
             Ok(client) => Ok(Device {
+                system_port_positions: {
+                    let pattern = match device_type {
+                        DeviceType::InputDevice => super::SYSTEM_CAPTURE_PORTS,
+                        DeviceType::OutputDevice => super::SYSTEM_PLAYBACK_PORTS,
+                    };
+                    super::system_ports(&client, pattern, None)
+                        .iter()
+                        .flatten()
+                        .map(|port| super::port_position(&client, port))
+                        .collect()
+                },
                 // The name given to the client by JACK, could potentially be different from the name supplied e.g.if there is a name collision
                 name: client.name().to_string(),
                 sample_rate: SampleRate(client.sample_rate() as u32),
                 buffer_size: SupportedBufferSize::Range {
                     min: client.buffer_size(),
                     max: client.buffer_size(),
+                    periods: None,
                 },
                 device_type,
                 start_server_automatically,
@@ -104,6 +120,8 @@ impl Device {
             sample_rate,
             buffer_size,
             sample_format,
+            endianness: Endianness::NATIVE,
+            channel_layout: self.channel_layout(channels),
         })
     }
 
@@ -120,13 +138,113 @@ impl Device {
                 channels,
                 min_sample_rate: f.sample_rate,
                 max_sample_rate: f.sample_rate,
+                sample_rates: SupportedSampleRates::Discrete(vec![f.sample_rate]),
                 buffer_size: f.buffer_size.clone(),
                 sample_format: f.sample_format,
+                endianness: Endianness::NATIVE,
+                channel_layout: self.channel_layout(channels),
             });
         }
         supported_configs
     }
 
+    // The layout of the first `channels` system ports, if all of their positions are known.
+    fn channel_layout(&self, channels: ChannelCount) -> Option<ChannelLayout> {
+        let positions = self.system_port_positions.get(..channels as usize)?;
+        Some(ChannelLayout::new(positions.to_vec())).filter(ChannelLayout::is_known)
+    }
+
+    // The checks made when building a stream, in the order in which they are made, without
+    // creating a client. Any number of ports can be registered, so the channel count is not
+    // restricted.
+    fn check_config(
+        &self,
+        conf: &StreamConfig,
+        sample_format: SampleFormat,
+    ) -> Result<(), RejectedParameter> {
+        self.check_format(conf, sample_format)?;
+        self.check_channel_layout(conf)?;
+        self.check_buffer_size(conf)?;
+        if conf.strict {
+            // Each cycle processes the whole buffer, whose size the server decides.
+            let buffer_size = match self.buffer_size {
+                SupportedBufferSize::Range { max, .. } => Some(max),
+                SupportedBufferSize::Unknown => None,
+            };
+            let negotiated = NegotiatedStreamConfig {
+                channels: conf.channels,
+                sample_rate: self.sample_rate,
+                sample_format: JACK_SAMPLE_FORMAT,
+                period_size: buffer_size,
+                buffer_size,
+            };
+            if let Some(rejected) = negotiated.mismatch(conf) {
+                return Err(rejected);
+            }
+        }
+        Ok(())
+    }
+
+    // Whether the server runs at the requested sample rate, and audio ports take the requested
+    // sample format.
+    fn check_format(
+        &self,
+        conf: &StreamConfig,
+        sample_format: SampleFormat,
+    ) -> Result<(), RejectedParameter> {
+        if sample_format != JACK_SAMPLE_FORMAT {
+            return Err(RejectedParameter::SampleFormat {
+                requested: sample_format,
+                supported: vec![JACK_SAMPLE_FORMAT],
+            });
+        }
+        if conf.sample_rate != self.sample_rate {
+            return Err(RejectedParameter::SampleRate {
+                requested: conf.sample_rate,
+                min: self.sample_rate,
+                max: self.sample_rate,
+                rates: SupportedSampleRates::Continuous,
+            });
+        }
+        Ok(())
+    }
+
+    // Whether the ports of a stream can be connected according to the requested layout.
+    fn check_channel_layout(&self, conf: &StreamConfig) -> Result<(), RejectedParameter> {
+        match conf.channel_layout {
+            Some(ref layout) if self.connect_ports_automatically => {
+                let available = |position: &ChannelPosition| {
+                    *position != ChannelPosition::Unknown
+                        && self.system_port_positions.contains(position)
+                };
+                if layout.channels() != conf.channels || !layout.positions().iter().all(available) {
+                    return Err(RejectedParameter::ChannelLayout {
+                        requested: layout.clone(),
+                        supported: self.channel_layout(conf.channels).into_iter().collect(),
+                    });
+                }
+                Ok(())
+            }
+            _ => Ok(()),
+        }
+    }
+
+    // Whether the server's buffer matches the requested periods. The server decides how many
+    // periods the hardware buffer holds, so only the period size can be checked.
+    fn check_buffer_size(&self, conf: &StreamConfig) -> Result<(), RejectedParameter> {
+        match (&conf.buffer_size, &self.buffer_size) {
+            (BufferSize::Periods { period_size, .. }, SupportedBufferSize::Range { max, .. })
+                if period_size != max =>
+            {
+                Err(RejectedParameter::BufferSize {
+                    requested: conf.buffer_size.clone(),
+                    supported: self.buffer_size.clone(),
+                })
+            }
+            _ => Ok(()),
+        }
+    }
+
     pub fn is_input(&self) -> bool {
         matches!(self.device_type, DeviceType::InputDevice)
     }
@@ -134,6 +252,95 @@ impl Device {
     pub fn is_output(&self) -> bool {
         matches!(self.device_type, DeviceType::OutputDevice)
     }
+
+    fn build_input_stream_inner<D, E>(
+        &self,
+        conf: &StreamConfig,
+        sample_format: SampleFormat,
+        layout: DataLayout,
+        data_callback: D,
+        error_callback: E,
+    ) -> Result<Stream, BuildStreamError>
+    where
+        D: FnMut(&Data, &InputCallbackInfo) + Send + 'static,
+        E: FnMut(StreamError) + Send + 'static,
+    {
+        if let DeviceType::OutputDevice = &self.device_type {
+            // Trying to create an input stream from an output device
+            return Err(BuildStreamError::StreamConfigNotSupported);
+        }
+        self.check_config(conf, sample_format)
+            .map_err(BuildStreamError::ParameterNotSupported)?;
+        // The settings should be fine, create a Client
+        let client_options = super::get_client_options(self.start_server_automatically);
+        let client;
+        match super::get_client(&self.name, client_options) {
+            Ok(c) => client = c,
+            Err(e) => {
+                return Err(BuildStreamError::BackendSpecific {
+                    err: BackendSpecificError { description: e },
+                })
+            }
+        };
+        let mut stream =
+            Stream::new_input(client, conf.channels, layout, data_callback, error_callback);
+        if conf.strict {
+            if let Some(rejected) = stream.negotiated_config().and_then(|c| c.mismatch(conf)) {
+                return Err(BuildStreamError::ParameterNotSupported(rejected));
+            }
+        }
+
+        if self.connect_ports_automatically {
+            stream.connect_to_system_inputs_in_layout(conf.channel_layout.as_ref());
+        }
+
+        Ok(stream)
+    }
+
+    fn build_output_stream_inner<D, E>(
+        &self,
+        conf: &StreamConfig,
+        sample_format: SampleFormat,
+        layout: DataLayout,
+        data_callback: D,
+        error_callback: E,
+    ) -> Result<Stream, BuildStreamError>
+    where
+        D: FnMut(&mut Data, &OutputCallbackInfo) + Send + 'static,
+        E: FnMut(StreamError) + Send + 'static,
+    {
+        if let DeviceType::InputDevice = &self.device_type {
+            // Trying to create an output stream from an input device
+            return Err(BuildStreamError::StreamConfigNotSupported);
+        }
+        self.check_config(conf, sample_format)
+            .map_err(BuildStreamError::ParameterNotSupported)?;
+
+        // The settings should be fine, create a Client
+        let client_options = super::get_client_options(self.start_server_automatically);
+        let client;
+        match super::get_client(&self.name, client_options) {
+            Ok(c) => client = c,
+            Err(e) => {
+                return Err(BuildStreamError::BackendSpecific {
+                    err: BackendSpecificError { description: e },
+                })
+            }
+        };
+        let mut stream =
+            Stream::new_output(client, conf.channels, layout, data_callback, error_callback);
+        if conf.strict {
+            if let Some(rejected) = stream.negotiated_config().and_then(|c| c.mismatch(conf)) {
+                return Err(BuildStreamError::ParameterNotSupported(rejected));
+            }
+        }
+
+        if self.connect_ports_automatically {
+            stream.connect_to_system_outputs_in_layout(conf.channel_layout.as_ref());
+        }
+
+        Ok(stream)
+    }
 }
 
 impl DeviceTrait for Device {
@@ -171,6 +378,30 @@ impl DeviceTrait for Device {
         self.default_config()
     }
 
+    fn check_input_config(
+        &self,
+        conf: &StreamConfig,
+        sample_format: SampleFormat,
+    ) -> Result<(), CheckStreamConfigError> {
+        if !self.is_input() {
+            return Err(CheckStreamConfigError::StreamTypeNotSupported);
+        }
+        self.check_config(conf, sample_format)
+            .map_err(CheckStreamConfigError::NotSupported)
+    }
+
+    fn check_output_config(
+        &self,
+        conf: &StreamConfig,
+        sample_format: SampleFormat,
+    ) -> Result<(), CheckStreamConfigError> {
+        if !self.is_output() {
+            return Err(CheckStreamConfigError::StreamTypeNotSupported);
+        }
+        self.check_config(conf, sample_format)
+            .map_err(CheckStreamConfigError::NotSupported)
+    }
+
     fn build_input_stream_raw<D, E>(
         &self,
         conf: &StreamConfig,
@@ -182,31 +413,33 @@ impl DeviceTrait for Device {
         D: FnMut(&Data, &InputCallbackInfo) + Send + 'static,
         E: FnMut(StreamError) + Send + 'static,
     {
-        if let DeviceType::OutputDevice = &self.device_type {
-            // Trying to create an input stream from an output device
-            return Err(BuildStreamError::StreamConfigNotSupported);
-        }
-        if conf.sample_rate != self.sample_rate || sample_format != JACK_SAMPLE_FORMAT {
-            return Err(BuildStreamError::StreamConfigNotSupported);
-        }
-        // The settings should be fine, create a Client
-        let client_options = super::get_client_options(self.start_server_automatically);
-        let client;
-        match super::get_client(&self.name, client_options) {
-            Ok(c) => client = c,
-            Err(e) => {
-                return Err(BuildStreamError::BackendSpecific {
-                    err: BackendSpecificError { description: e },
-                })
-            }
-        };
-        let mut stream = Stream::new_input(client, conf.channels, data_callback, error_callback);
-
-        if self.connect_ports_automatically {
-            stream.connect_to_system_inputs();
-        }
+        self.build_input_stream_inner(
+            conf,
+            sample_format,
+            DataLayout::Interleaved,
+            data_callback,
+            error_callback,
+        )
+    }
 
-        Ok(stream)
+    fn build_input_stream_planar_raw<D, E>(
+        &self,
+        conf: &StreamConfig,
+        sample_format: SampleFormat,
+        data_callback: D,
+        error_callback: E,
+    ) -> Result<Self::Stream, BuildStreamError>
+    where
+        D: FnMut(&Data, &InputCallbackInfo) + Send + 'static,
+        E: FnMut(StreamError) + Send + 'static,
+    {
+        self.build_input_stream_inner(
+            conf,
+            sample_format,
+            DataLayout::Planar,
+            data_callback,
+            error_callback,
+        )
     }
 
     fn build_output_stream_raw<D, E>(
@@ -220,28 +453,88 @@ impl DeviceTrait for Device {
         D: FnMut(&mut Data, &OutputCallbackInfo) + Send + 'static,
         E: FnMut(StreamError) + Send + 'static,
     {
-        if let DeviceType::InputDevice = &self.device_type {
-            // Trying to create an output stream from an input device
-            return Err(BuildStreamError::StreamConfigNotSupported);
-        }
-        if conf.sample_rate != self.sample_rate || sample_format != JACK_SAMPLE_FORMAT {
-            return Err(BuildStreamError::StreamConfigNotSupported);
+        self.build_output_stream_inner(
+            conf,
+            sample_format,
+            DataLayout::Interleaved,
+            data_callback,
+            error_callback,
+        )
+    }
+
+    fn build_output_stream_planar_raw<D, E>(
+        &self,
+        conf: &StreamConfig,
+        sample_format: SampleFormat,
+        data_callback: D,
+        error_callback: E,
+    ) -> Result<Self::Stream, BuildStreamError>
+    where
+        D: FnMut(&mut Data, &OutputCallbackInfo) + Send + 'static,
+        E: FnMut(StreamError) + Send + 'static,
+    {
+        self.build_output_stream_inner(
+            conf,
+            sample_format,
+            DataLayout::Planar,
+            data_callback,
+            error_callback,
+        )
+    }
+
+    fn build_duplex_stream_raw<D, E>(
+        &self,
+        conf: &DuplexStreamConfig,
+        sample_format: SampleFormat,
+        data_callback: D,
+        error_callback: E,
+    ) -> Result<Self::Stream, BuildStreamError>
+    where
+        D: FnMut(&Data, &mut Data, &DuplexCallbackInfo) + Send + 'static,
+        E: FnMut(StreamError) + Send + 'static,
+    {
+        // Without ports in both directions the process callback would never run the callback.
+        for &channels in [conf.input_channels, conf.output_channels].iter() {
+            if channels == 0 {
+                return Err(BuildStreamError::ParameterNotSupported(
+                    RejectedParameter::Channels {
+                        requested: channels,
+                        min: 1,
+                        max: ChannelCount::MAX,
+                    },
+                ));
+            }
         }
+        let output_conf = conf.output_config();
+        self.check_config(&output_conf, sample_format)
+            .map_err(BuildStreamError::ParameterNotSupported)?;
 
         // The settings should be fine, create a Client
         let client_options = super::get_client_options(self.start_server_automatically);
-        let client;
-        match super::get_client(&self.name, client_options) {
-            Ok(c) => client = c,
-            Err(e) => {
-                return Err(BuildStreamError::BackendSpecific {
-                    err: BackendSpecificError { description: e },
-                })
+        let client = super::get_client(&self.name, client_options).map_err(|e| {
+            BuildStreamError::BackendSpecific {
+                err: BackendSpecificError { description: e },
             }
-        };
-        let mut stream = Stream::new_output(client, conf.channels, data_callback, error_callback);
+        })?;
+        let mut stream = Stream::new_duplex(
+            client,
+            conf.input_channels,
+            conf.output_channels,
+            data_callback,
+            error_callback,
+        );
+        // Both directions share the client's sample rate and buffer size.
+        if conf.strict {
+            if let Some(rejected) = stream
+                .negotiated_config()
+                .and_then(|c| c.mismatch(&output_conf))
+            {
+                return Err(BuildStreamError::ParameterNotSupported(rejected));
+            }
+        }
 
         if self.connect_ports_automatically {
+            stream.connect_to_system_inputs();
             stream.connect_to_system_outputs();
         }
 
@@ -1,6 +1,8 @@
 extern crate jack;
 
-use crate::{DevicesError, SampleFormat, SupportedStreamConfigRange};
+use crate::{
+    ChannelLayout, ChannelPosition, DevicesError, SampleFormat, SupportedStreamConfigRange,
+};
 use traits::HostTrait;
 
 mod device;
@@ -10,6 +12,9 @@ mod stream;
 
 const JACK_SAMPLE_FORMAT: SampleFormat = SampleFormat::F32;
 
+const SYSTEM_PLAYBACK_PORTS: &str = "system:playback_.*";
+const SYSTEM_CAPTURE_PORTS: &str = "system:capture_.*";
+
 pub type SupportedInputConfigs = std::vec::IntoIter<SupportedStreamConfigRange>;
 pub type SupportedOutputConfigs = std::vec::IntoIter<SupportedStreamConfigRange>;
 pub type Devices = std::vec::IntoIter<Device>;
@@ -178,3 +183,43 @@ fn get_client(name: &str, client_options: jack::ClientOptions) -> Result<jack::C
         }
     }
 }
+
+// The speaker position of a port, taken from the suffix of its name or one of its aliases such as
+// `FL` in `system:playback_FL`. Ports that are only numbered, as with the ALSA backend of jackd,
+// have an `Unknown` position.
+fn port_position(client: &jack::Client, port_name: &str) -> ChannelPosition {
+    let aliases = client
+        .port_by_name(port_name)
+        .and_then(|port| port.aliases().ok())
+        .unwrap_or_default();
+    std::iter::once(port_name)
+        .chain(aliases.iter().map(String::as_str))
+        .filter_map(|name| name.rsplit(['_', ':']).next())
+        .find_map(|suffix| suffix.parse().ok())
+        .unwrap_or(ChannelPosition::Unknown)
+}
+
+// The system ports matching `pattern` that each channel of a stream connects to.
+//
+// Without a layout, channels connect to the ports in order. With one, each channel connects to the
+// port with its position, if there is such a port.
+fn system_ports(
+    client: &jack::Client,
+    pattern: &str,
+    layout: Option<&ChannelLayout>,
+) -> Vec<Option<String>> {
+    let ports = client.ports(Some(pattern), None, jack::PortFlags::empty());
+    match layout {
+        None => ports.into_iter().map(Some).collect(),
+        Some(layout) => layout
+            .positions()
+            .iter()
+            .map(|&position| {
+                ports
+                    .iter()
+                    .find(|port| port_position(client, port) == position)
+                    .cloned()
+            })
+            .collect(),
+    }
+}
@@ -4,13 +4,23 @@ use std::sync::{Arc, Mutex};
 use traits::StreamTrait;
 
 use crate::{
-    BackendSpecificError, Data, InputCallbackInfo, OutputCallbackInfo, PauseStreamError,
-    PlayStreamError, SampleRate, StreamError,
+    BackendSpecificError, ChannelLayout, Data, DataLayout, DuplexCallbackInfo, InputCallbackInfo,
+    NegotiatedStreamConfig, OutputCallbackInfo, PauseStreamError, PlayStreamError, SampleRate,
+    StreamError,
 };
 
 use super::JACK_SAMPLE_FORMAT;
 
 type ErrorCallbackPtr = Arc<Mutex<dyn FnMut(StreamError) + Send + 'static>>;
+type InputDataCallback = Box<dyn FnMut(&Data, &InputCallbackInfo) + Send + 'static>;
+type OutputDataCallback = Box<dyn FnMut(&mut Data, &OutputCallbackInfo) + Send + 'static>;
+type DuplexDataCallback = Box<dyn FnMut(&Data, &mut Data, &DuplexCallbackInfo) + Send + 'static>;
+
+// Pointers to the port buffers of the current cycle, handed to planar callbacks.
+struct PortBuffers(Vec<*mut ()>);
+
+// The pointers are only written and read on the process thread, within a single cycle.
+unsafe impl Send for PortBuffers {}
 
 pub struct Stream {
     // TODO: It might be faster to send a message when playing/pausing than to check this every iteration
@@ -26,6 +36,7 @@ impl Stream {
     pub fn new_input<D, E>(
         client: jack::Client,
         channels: ChannelCount,
+        layout: DataLayout,
         data_callback: D,
         mut error_callback: E,
     ) -> Stream
@@ -33,43 +44,24 @@ impl Stream {
         D: FnMut(&Data, &InputCallbackInfo) + Send + 'static,
         E: FnMut(StreamError) + Send + 'static,
     {
-        let mut ports = vec![];
-        let mut port_names: Vec<String> = vec![];
-        // Create ports
-        for i in 0..channels {
-            let port_try = client.register_port(&format!("in_{}", i), jack::AudioIn::default());
-            match port_try {
-                Ok(port) => {
-                    // Get the port name in order to later connect it automatically
-                    if let Ok(port_name) = port.name() {
-                        port_names.push(port_name);
-                    }
-                    // Store the port into a Vec to move to the ProcessHandler
-                    ports.push(port);
-                }
-                Err(e) => {
-                    // If port creation failed, send the error back via the error_callback
-                    error_callback(
-                        BackendSpecificError {
-                            description: e.to_string(),
-                        }
-                        .into(),
-                    );
-                }
-            }
-        }
+        let (ports, port_names) =
+            register_ports::<jack::AudioIn, _>(&client, "in", channels, &mut error_callback);
 
         let playing = Arc::new(AtomicBool::new(true));
 
         let error_callback_ptr = Arc::new(Mutex::new(error_callback)) as ErrorCallbackPtr;
 
         let input_process_handler = LocalProcessHandler::new(
-            vec![],
-            ports,
+            ProcessPorts {
+                out_ports: vec![],
+                in_ports: ports,
+                buffer_size: client.buffer_size() as usize,
+                layout,
+            },
             SampleRate(client.sample_rate() as u32),
-            client.buffer_size() as usize,
             Some(Box::new(data_callback)),
             None,
+            None,
             playing.clone(),
             Arc::clone(&error_callback_ptr),
         );
@@ -91,6 +83,7 @@ impl Stream {
     pub fn new_output<D, E>(
         client: jack::Client,
         channels: ChannelCount,
+        layout: DataLayout,
         data_callback: D,
         mut error_callback: E,
     ) -> Stream
@@ -98,43 +91,24 @@ impl Stream {
         D: FnMut(&mut Data, &OutputCallbackInfo) + Send + 'static,
         E: FnMut(StreamError) + Send + 'static,
     {
-        let mut ports = vec![];
-        let mut port_names: Vec<String> = vec![];
-        // Create ports
-        for i in 0..channels {
-            let port_try = client.register_port(&format!("out_{}", i), jack::AudioOut::default());
-            match port_try {
-                Ok(port) => {
-                    // Get the port name in order to later connect it automatically
-                    if let Ok(port_name) = port.name() {
-                        port_names.push(port_name);
-                    }
-                    // Store the port into a Vec to move to the ProcessHandler
-                    ports.push(port);
-                }
-                Err(e) => {
-                    // If port creation failed, send the error back via the error_callback
-                    error_callback(
-                        BackendSpecificError {
-                            description: e.to_string(),
-                        }
-                        .into(),
-                    );
-                }
-            }
-        }
+        let (ports, port_names) =
+            register_ports::<jack::AudioOut, _>(&client, "out", channels, &mut error_callback);
 
         let playing = Arc::new(AtomicBool::new(true));
 
         let error_callback_ptr = Arc::new(Mutex::new(error_callback)) as ErrorCallbackPtr;
 
         let output_process_handler = LocalProcessHandler::new(
-            ports,
-            vec![],
+            ProcessPorts {
+                out_ports: ports,
+                in_ports: vec![],
+                buffer_size: client.buffer_size() as usize,
+                layout,
+            },
             SampleRate(client.sample_rate() as u32),
-            client.buffer_size() as usize,
             None,
             Some(Box::new(data_callback)),
+            None,
             playing.clone(),
             Arc::clone(&error_callback_ptr),
         );
@@ -153,28 +127,81 @@ impl Stream {
         }
     }
 
+    // A single client with both input and output ports, whose process callback hands the input
+    // and output of each cycle to `data_callback` together.
+    pub fn new_duplex<D, E>(
+        client: jack::Client,
+        input_channels: ChannelCount,
+        output_channels: ChannelCount,
+        data_callback: D,
+        mut error_callback: E,
+    ) -> Stream
+    where
+        D: FnMut(&Data, &mut Data, &DuplexCallbackInfo) + Send + 'static,
+        E: FnMut(StreamError) + Send + 'static,
+    {
+        let (in_ports, input_port_names) =
+            register_ports::<jack::AudioIn, _>(&client, "in", input_channels, &mut error_callback);
+        let (out_ports, output_port_names) = register_ports::<jack::AudioOut, _>(
+            &client,
+            "out",
+            output_channels,
+            &mut error_callback,
+        );
+
+        let playing = Arc::new(AtomicBool::new(true));
+
+        let error_callback_ptr = Arc::new(Mutex::new(error_callback)) as ErrorCallbackPtr;
+
+        let duplex_process_handler = LocalProcessHandler::new(
+            ProcessPorts {
+                out_ports,
+                in_ports,
+                buffer_size: client.buffer_size() as usize,
+                layout: DataLayout::Interleaved,
+            },
+            SampleRate(client.sample_rate() as u32),
+            None,
+            None,
+            Some(Box::new(data_callback)),
+            playing.clone(),
+            Arc::clone(&error_callback_ptr),
+        );
+
+        let notification_handler = JackNotificationHandler::new(error_callback_ptr);
+
+        let async_client = client
+            .activate_async(notification_handler, duplex_process_handler)
+            .unwrap();
+
+        Stream {
+            playing,
+            async_client,
+            input_port_names,
+            output_port_names,
+        }
+    }
+
     /// Connect to the standard system outputs in jack, system:playback_1 and system:playback_2
     /// This has to be done after the client is activated, doing it just after creating the ports doesn't work.
     pub fn connect_to_system_outputs(&mut self) {
+        self.connect_to_system_outputs_in_layout(None);
+    }
+
+    /// Like `connect_to_system_outputs`, but with a `layout` each port is connected to the system
+    /// port with the matching position instead.
+    pub(crate) fn connect_to_system_outputs_in_layout(&mut self, layout: Option<&ChannelLayout>) {
+        let client = self.async_client.as_client();
         // Get the system ports
-        let system_ports = self.async_client.as_client().ports(
-            Some("system:playback_.*"),
-            None,
-            jack::PortFlags::empty(),
-        );
+        let system_ports = super::system_ports(client, super::SYSTEM_PLAYBACK_PORTS, layout);
 
         // Connect outputs from this client to the system playback inputs
-        for i in 0..self.output_port_names.len() {
-            if i >= system_ports.len() {
-                break;
-            }
-            match self
-                .async_client
-                .as_client()
-                .connect_ports_by_name(&self.output_port_names[i], &system_ports[i])
-            {
-                Ok(_) => (),
-                Err(e) => println!("Unable to connect to port with error {}", e),
+        for (port_name, system_port) in self.output_port_names.iter().zip(&system_ports) {
+            if let Some(system_port) = system_port {
+                match client.connect_ports_by_name(port_name, system_port) {
+                    Ok(_) => (),
+                    Err(e) => println!("Unable to connect to port with error {}", e),
+                }
             }
         }
     }
@@ -182,28 +209,84 @@ impl Stream {
     /// Connect to the standard system outputs in jack, system:capture_1 and system:capture_2
     /// This has to be done after the client is activated, doing it just after creating the ports doesn't work.
     pub fn connect_to_system_inputs(&mut self) {
+        self.connect_to_system_inputs_in_layout(None);
+    }
+
+    /// Like `connect_to_system_inputs`, but with a `layout` each port is connected to the system
+    /// port with the matching position instead.
+    pub(crate) fn connect_to_system_inputs_in_layout(&mut self, layout: Option<&ChannelLayout>) {
+        let client = self.async_client.as_client();
         // Get the system ports
-        let system_ports = self.async_client.as_client().ports(
-            Some("system:capture_.*"),
-            None,
-            jack::PortFlags::empty(),
-        );
+        let system_ports = super::system_ports(client, super::SYSTEM_CAPTURE_PORTS, layout);
 
         // Connect outputs from this client to the system playback inputs
-        for i in 0..self.input_port_names.len() {
-            if i >= system_ports.len() {
-                break;
+        for (port_name, system_port) in self.input_port_names.iter().zip(&system_ports) {
+            if let Some(system_port) = system_port {
+                match client.connect_ports_by_name(system_port, port_name) {
+                    Ok(_) => (),
+                    Err(e) => println!("Unable to connect to port with error {}", e),
+                }
+            }
+        }
+    }
+
+    // The greatest latency that the graph reports for any of the given ports, or `None` if the
+    // stream has no ports in that direction.
+    fn port_latency(
+        &self,
+        port_names: &[String],
+        latency_type: jack::LatencyType,
+    ) -> Option<std::time::Duration> {
+        let client = self.async_client.as_client();
+        let frames = port_names
+            .iter()
+            .filter_map(|name| client.port_by_name(name))
+            .map(|port| port.get_latency_range(latency_type).1)
+            .max()?;
+        Some(frames_to_duration(
+            frames as usize,
+            SampleRate(client.sample_rate() as u32),
+        ))
+    }
+}
+
+// Registers `channels` ports named `<prefix>_<channel>`, along with their full names for
+// connecting them later. Ports that cannot be registered are reported to `error_callback`.
+fn register_ports<PS, E>(
+    client: &jack::Client,
+    prefix: &str,
+    channels: ChannelCount,
+    error_callback: &mut E,
+) -> (Vec<jack::Port<PS>>, Vec<String>)
+where
+    PS: jack::PortSpec + Default,
+    E: FnMut(StreamError),
+{
+    let mut ports = vec![];
+    let mut port_names: Vec<String> = vec![];
+    for i in 0..channels {
+        let port_try = client.register_port(&format!("{}_{}", prefix, i), PS::default());
+        match port_try {
+            Ok(port) => {
+                // Get the port name in order to later connect it automatically
+                if let Ok(port_name) = port.name() {
+                    port_names.push(port_name);
+                }
+                // Store the port into a Vec to move to the ProcessHandler
+                ports.push(port);
             }
-            match self
-                .async_client
-                .as_client()
-                .connect_ports_by_name(&system_ports[i], &self.input_port_names[i])
-            {
-                Ok(_) => (),
-                Err(e) => println!("Unable to connect to port with error {}", e),
+            Err(e) => {
+                // If port creation failed, send the error back via the error_callback
+                error_callback(
+                    BackendSpecificError {
+                        description: e.to_string(),
+                    }
+                    .into(),
+                );
             }
         }
     }
+    (ports, port_names)
 }
 
 impl StreamTrait for Stream {
@@ -216,6 +299,48 @@ impl StreamTrait for Stream {
         self.playing.store(false, Ordering::SeqCst);
         Ok(())
     }
+
+    fn buffer_latency(&self) -> Option<std::time::Duration> {
+        // The server's buffer size, whatever the stream requested.
+        let client = self.async_client.as_client();
+        Some(frames_to_duration(
+            client.buffer_size() as usize,
+            SampleRate(client.sample_rate() as u32),
+        ))
+    }
+
+    fn negotiated_config(&self) -> Option<NegotiatedStreamConfig> {
+        let client = self.async_client.as_client();
+        Some(NegotiatedStreamConfig {
+            // Duplex streams report their output ports.
+            channels: if self.output_port_names.is_empty() {
+                self.input_port_names.len()
+            } else {
+                self.output_port_names.len()
+            } as ChannelCount,
+            sample_rate: SampleRate(client.sample_rate() as u32),
+            sample_format: JACK_SAMPLE_FORMAT,
+            // Each cycle processes the whole buffer.
+            period_size: Some(client.buffer_size()),
+            buffer_size: Some(client.buffer_size()),
+        })
+    }
+
+    fn input_latency(&self) -> Option<std::time::Duration> {
+        self.port_latency(&self.input_port_names, jack::LatencyType::Capture)
+    }
+
+    fn output_latency(&self) -> Option<std::time::Duration> {
+        self.port_latency(&self.output_port_names, jack::LatencyType::Playback)
+    }
+}
+
+// The ports a `LocalProcessHandler` processes and how their buffers are handed to the callback.
+struct ProcessPorts {
+    out_ports: Vec<jack::Port<jack::AudioOut>>,
+    in_ports: Vec<jack::Port<jack::AudioIn>>,
+    buffer_size: usize,
+    layout: DataLayout,
 }
 
 struct LocalProcessHandler {
@@ -225,12 +350,17 @@ struct LocalProcessHandler {
 
     sample_rate: SampleRate,
     buffer_size: usize,
-    input_data_callback: Option<Box<dyn FnMut(&Data, &InputCallbackInfo) + Send + 'static>>,
-    output_data_callback: Option<Box<dyn FnMut(&mut Data, &OutputCallbackInfo) + Send + 'static>>,
+    /// Planar callbacks are handed the port buffers directly rather than the temporary buffers.
+    layout: DataLayout,
+    input_data_callback: Option<InputDataCallback>,
+    output_data_callback: Option<OutputDataCallback>,
+    duplex_data_callback: Option<DuplexDataCallback>,
 
     // JACK audio samples are 32-bit float (unless you do some custom dark magic)
     temp_input_buffer: Vec<f32>,
     temp_output_buffer: Vec<f32>,
+    input_port_buffers: PortBuffers,
+    output_port_buffers: PortBuffers,
     playing: Arc<AtomicBool>,
     creation_timestamp: std::time::Instant,
     /// This should not be called on `process`, only on `buffer_size` because it can block.
@@ -239,42 +369,140 @@ struct LocalProcessHandler {
 
 impl LocalProcessHandler {
     fn new(
-        out_ports: Vec<jack::Port<jack::AudioOut>>,
-        in_ports: Vec<jack::Port<jack::AudioIn>>,
+        ports: ProcessPorts,
         sample_rate: SampleRate,
-        buffer_size: usize,
-        input_data_callback: Option<Box<dyn FnMut(&Data, &InputCallbackInfo) + Send + 'static>>,
-        output_data_callback: Option<
-            Box<dyn FnMut(&mut Data, &OutputCallbackInfo) + Send + 'static>,
-        >,
+        input_data_callback: Option<InputDataCallback>,
+        output_data_callback: Option<OutputDataCallback>,
+        duplex_data_callback: Option<DuplexDataCallback>,
         playing: Arc<AtomicBool>,
         error_callback_ptr: ErrorCallbackPtr,
     ) -> Self {
+        let ProcessPorts {
+            out_ports,
+            in_ports,
+            buffer_size,
+            layout,
+        } = ports;
         // These may be reallocated in the `buffer_size` callback.
         let temp_input_buffer = vec![0.0; in_ports.len() * buffer_size];
         let temp_output_buffer = vec![0.0; out_ports.len() * buffer_size];
+        let input_port_buffers = PortBuffers(vec![std::ptr::null_mut(); in_ports.len()]);
+        let output_port_buffers = PortBuffers(vec![std::ptr::null_mut(); out_ports.len()]);
 
         LocalProcessHandler {
             out_ports,
             in_ports,
             sample_rate,
             buffer_size,
+            layout,
             input_data_callback,
             output_data_callback,
+            duplex_data_callback,
             temp_input_buffer,
             temp_output_buffer,
+            input_port_buffers,
+            output_port_buffers,
             playing,
             creation_timestamp: std::time::Instant::now(),
             error_callback_ptr,
         }
     }
+
+    // The data of the input ports for this cycle, or `None` if the stream has no input ports.
+    fn input_data(
+        &mut self,
+        process_scope: &jack::ProcessScope,
+        current_frame_count: usize,
+    ) -> Option<Data> {
+        let num_in_channels = self.in_ports.len();
+        if num_in_channels == 0 {
+            return None;
+        }
+        let data = match self.layout {
+            DataLayout::Interleaved => {
+                // Read the data from the input ports into the temporary buffer
+                // Go through every channel and store its data in the temporary input buffer
+                for ch_ix in 0..num_in_channels {
+                    let input_channel = &self.in_ports[ch_ix].as_slice(process_scope);
+                    for i in 0..current_frame_count {
+                        self.temp_input_buffer[ch_ix + i * num_in_channels] = input_channel[i];
+                    }
+                }
+                // Create a slice of exactly current_frame_count frames
+                temp_buffer_to_data(
+                    &mut self.temp_input_buffer,
+                    current_frame_count * num_in_channels,
+                    num_in_channels,
+                )
+            }
+            DataLayout::Planar => {
+                // Hand over the port buffers themselves
+                for (buffer, port) in self.input_port_buffers.0.iter_mut().zip(&self.in_ports) {
+                    *buffer = port.as_slice(process_scope).as_ptr() as *mut ();
+                }
+                unsafe {
+                    Data::from_planar_parts(
+                        self.input_port_buffers.0.as_ptr(),
+                        num_in_channels,
+                        current_frame_count,
+                        JACK_SAMPLE_FORMAT,
+                    )
+                }
+            }
+        };
+        Some(data)
+    }
+
+    // The buffer to fill with this cycle's output, or `None` if the stream has no output ports.
+    fn output_data(
+        &mut self,
+        process_scope: &jack::ProcessScope,
+        current_frame_count: usize,
+    ) -> Option<Data> {
+        let num_out_channels = self.out_ports.len();
+        if num_out_channels == 0 {
+            return None;
+        }
+        let data = match self.layout {
+            // Create a slice of exactly current_frame_count frames
+            DataLayout::Interleaved => temp_buffer_to_data(
+                &mut self.temp_output_buffer,
+                current_frame_count * num_out_channels,
+                num_out_channels,
+            ),
+            DataLayout::Planar => {
+                // Hand over the port buffers themselves
+                for (buffer, port) in self
+                    .output_port_buffers
+                    .0
+                    .iter_mut()
+                    .zip(&mut self.out_ports)
+                {
+                    *buffer = port.as_mut_slice(process_scope).as_mut_ptr() as *mut ();
+                }
+                unsafe {
+                    Data::from_planar_parts(
+                        self.output_port_buffers.0.as_ptr(),
+                        num_out_channels,
+                        current_frame_count,
+                        JACK_SAMPLE_FORMAT,
+                    )
+                }
+            }
+        };
+        Some(data)
+    }
 }
 
-fn temp_buffer_to_data(temp_input_buffer: &mut Vec<f32>, total_buffer_size: usize) -> Data {
+fn temp_buffer_to_data(
+    temp_input_buffer: &mut Vec<f32>,
+    total_buffer_size: usize,
+    channels: usize,
+) -> Data {
     let slice = &temp_input_buffer[0..total_buffer_size];
     let data = slice.as_ptr() as *mut ();
     let len = total_buffer_size;
-    let data = unsafe { Data::from_parts(data, len, JACK_SAMPLE_FORMAT) };
+    let data = unsafe { Data::from_parts(data, len, channels, JACK_SAMPLE_FORMAT) };
     data
 }
 
@@ -308,61 +536,50 @@ impl jack::ProcessHandler for LocalProcessHandler {
             ))
             .expect("`playback` occurs beyond representation supported by `StreamInstant`");
 
-        if let Some(input_callback) = &mut self.input_data_callback {
-            // Let's get the data from the input ports and run the callback
+        // Create timestamps
+        let frames_since_cycle_start = process_scope.frames_since_cycle_start() as usize;
+        let duration_since_cycle_start =
+            frames_to_duration(frames_since_cycle_start, self.sample_rate);
+        let callback = start_callback_instant
+            .add(duration_since_cycle_start)
+            .expect("`playback` occurs beyond representation supported by `StreamInstant`");
+        let capture = start_callback_instant;
+        let buffer_duration = frames_to_duration(current_frame_count, self.sample_rate);
+        let playback = start_cycle_instant
+            .add(buffer_duration)
+            .expect("`playback` occurs beyond representation supported by `StreamInstant`");
 
-            let num_in_channels = self.in_ports.len();
+        let input = self.input_data(process_scope, current_frame_count);
+        let mut output = self.output_data(process_scope, current_frame_count);
 
-            // Read the data from the input ports into the temporary buffer
-            // Go through every channel and store its data in the temporary input buffer
-            for ch_ix in 0..num_in_channels {
-                let input_channel = &self.in_ports[ch_ix].as_slice(process_scope);
-                for i in 0..current_frame_count {
-                    self.temp_input_buffer[ch_ix + i * num_in_channels] = input_channel[i];
-                }
-            }
-            // Create a slice of exactly current_frame_count frames
-            let data = temp_buffer_to_data(
-                &mut self.temp_input_buffer,
-                current_frame_count * num_in_channels,
-            );
-            // Create timestamp
-            let frames_since_cycle_start = process_scope.frames_since_cycle_start() as usize;
-            let duration_since_cycle_start =
-                frames_to_duration(frames_since_cycle_start, self.sample_rate);
-            let callback = start_callback_instant
-                .add(duration_since_cycle_start)
-                .expect("`playback` occurs beyond representation supported by `StreamInstant`");
-            let capture = start_callback_instant;
+        if let (Some(input_callback), Some(input)) = (&mut self.input_data_callback, &input) {
             let timestamp = crate::InputStreamTimestamp { callback, capture };
             let info = crate::InputCallbackInfo { timestamp };
-            input_callback(&data, &info);
+            input_callback(input, &info);
         }
 
-        if let Some(output_callback) = &mut self.output_data_callback {
-            let num_out_channels = self.out_ports.len();
-
-            // Create a slice of exactly current_frame_count frames
-            let mut data = temp_buffer_to_data(
-                &mut self.temp_output_buffer,
-                current_frame_count * num_out_channels,
-            );
-            // Create timestamp
-            let frames_since_cycle_start = process_scope.frames_since_cycle_start() as usize;
-            let duration_since_cycle_start =
-                frames_to_duration(frames_since_cycle_start, self.sample_rate);
-            let callback = start_callback_instant
-                .add(duration_since_cycle_start)
-                .expect("`playback` occurs beyond representation supported by `StreamInstant`");
-            let buffer_duration = frames_to_duration(current_frame_count, self.sample_rate);
-            let playback = start_cycle_instant
-                .add(buffer_duration)
-                .expect("`playback` occurs beyond representation supported by `StreamInstant`");
+        if let (Some(output_callback), Some(output)) = (&mut self.output_data_callback, &mut output)
+        {
             let timestamp = crate::OutputStreamTimestamp { callback, playback };
             let info = crate::OutputCallbackInfo { timestamp };
-            output_callback(&mut data, &info);
+            output_callback(output, &info);
+        }
 
-            // Deinterlace
+        if let (Some(duplex_callback), Some(input), Some(output)) =
+            (&mut self.duplex_data_callback, &input, &mut output)
+        {
+            let timestamp = crate::DuplexStreamTimestamp {
+                callback,
+                capture,
+                playback,
+            };
+            let info = crate::DuplexCallbackInfo { timestamp };
+            duplex_callback(input, output, &info);
+        }
+
+        // Deinterlace, unless the callback wrote to the port buffers directly
+        if output.is_some() && self.layout == DataLayout::Interleaved {
+            let num_out_channels = self.out_ports.len();
             for ch_ix in 0..num_out_channels {
                 let output_channel = &mut self.out_ports[ch_ix].as_mut_slice(process_scope);
                 for i in 0..current_frame_count {
@@ -7,11 +7,11 @@ extern crate oboe;
 
 use crate::traits::{DeviceTrait, HostTrait, StreamTrait};
 use crate::{
-    BackendSpecificError, BufferSize, BuildStreamError, Data, DefaultStreamConfigError,
-    DeviceNameError, DevicesError, InputCallbackInfo, OutputCallbackInfo, PauseStreamError,
-    PlayStreamError, Sample, SampleFormat, SampleRate, StreamConfig, StreamError,
-    SupportedBufferSize, SupportedStreamConfig, SupportedStreamConfigRange,
-    SupportedStreamConfigsError,
+    duration_to_frames, BackendSpecificError, BufferSize, BuildStreamError, Data,
+    DefaultStreamConfigError, DeviceNameError, DevicesError, Endianness, InputCallbackInfo,
+    OutputCallbackInfo, PauseStreamError, PlayStreamError, Sample, SampleFormat, SampleRate,
+    StreamConfig, StreamError, SupportedBufferSize, SupportedSampleRates, SupportedStreamConfig,
+    SupportedStreamConfigRange, SupportedStreamConfigsError,
 };
 
 mod android_media;
@@ -104,6 +104,7 @@ fn buffer_size_range_for_params(
         SupportedBufferSize::Range {
             min: min_buffer_size as u32,
             max: i32::MAX as u32,
+            periods: None,
         }
     } else {
         SupportedBufferSize::Unknown
@@ -124,7 +125,7 @@ fn default_supported_configs(is_output: bool) -> VecIntoIter<SupportedStreamConf
         for (mask_idx, channel_mask) in CHANNEL_MASKS.iter().enumerate() {
             let channel_count = mask_idx + 1;
             for sample_rate in &SAMPLE_RATES {
-                if let SupportedBufferSize::Range { min, max } = buffer_size_range_for_params(
+                if let SupportedBufferSize::Range { min, max, .. } = buffer_size_range_for_params(
                     is_output,
                     *sample_rate,
                     *channel_mask,
@@ -134,8 +135,15 @@ fn default_supported_configs(is_output: bool) -> VecIntoIter<SupportedStreamConf
                         channels: channel_count as u16,
                         min_sample_rate: SampleRate(*sample_rate as u32),
                         max_sample_rate: SampleRate(*sample_rate as u32),
-                        buffer_size: SupportedBufferSize::Range { min, max },
+                        sample_rates: SupportedSampleRates::Continuous,
+                        buffer_size: SupportedBufferSize::Range {
+                            min,
+                            max,
+                            periods: None,
+                        },
                         sample_format: *sample_format,
+                        endianness: Endianness::NATIVE,
+                        channel_layout: None,
                     });
                 }
             }
@@ -198,8 +206,11 @@ fn device_supported_configs(
                     channels: cmp::min(*channel_count as u16, 2u16),
                     min_sample_rate: SampleRate(*sample_rate as u32),
                     max_sample_rate: SampleRate(*sample_rate as u32),
+                    sample_rates: SupportedSampleRates::Continuous,
                     buffer_size,
                     sample_format,
+                    endianness: Endianness::NATIVE,
+                    channel_layout: None,
                 });
             }
         }
@@ -220,8 +231,11 @@ fn configure_for_device<D, C, I>(
     };
     builder = builder.set_sample_rate(config.sample_rate.0.try_into().unwrap());
     match &config.buffer_size {
-        BufferSize::Default => builder,
+        // Periods are rejected before the stream is built.
+        BufferSize::Default | BufferSize::Periods { .. } => builder,
         BufferSize::Fixed(size) => builder.set_buffer_capacity_in_frames(*size as i32),
+        BufferSize::Latency(latency) => builder
+            .set_buffer_capacity_in_frames(duration_to_frames(*latency, config.sample_rate) as i32),
     }
 }
 
@@ -338,6 +352,9 @@ impl DeviceTrait for Device {
         D: FnMut(&Data, &InputCallbackInfo) + Send + 'static,
         E: FnMut(StreamError) + Send + 'static,
     {
+        if let BufferSize::Periods { .. } = config.buffer_size {
+            return Err(BuildStreamError::StreamConfigNotSupported);
+        }
         match sample_format {
             SampleFormat::I16 => {
                 let builder = oboe::AudioStreamBuilder::default()
@@ -393,8 +410,8 @@ impl DeviceTrait for Device {
                     .into())
                 }
             }
-            SampleFormat::U16 => Err(BackendSpecificError {
-                description: "U16 format is not supported on Android.".to_owned(),
+            sample_format => Err(BackendSpecificError {
+                description: format!("{:?} format is not supported on Android.", sample_format),
             }
             .into()),
         }
@@ -411,6 +428,9 @@ impl DeviceTrait for Device {
         D: FnMut(&mut Data, &OutputCallbackInfo) + Send + 'static,
         E: FnMut(StreamError) + Send + 'static,
     {
+        if let BufferSize::Periods { .. } = config.buffer_size {
+            return Err(BuildStreamError::StreamConfigNotSupported);
+        }
         match sample_format {
             SampleFormat::I16 => {
                 let builder = oboe::AudioStreamBuilder::default()
@@ -466,8 +486,8 @@ impl DeviceTrait for Device {
                     .into())
                 }
             }
-            SampleFormat::U16 => Err(BackendSpecificError {
-                description: "U16 format is not supported on Android.".to_owned(),
+            sample_format => Err(BackendSpecificError {
+                description: format!("{:?} format is not supported on Android.", sample_format),
             }
             .into()),
         }
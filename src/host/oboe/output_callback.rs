@@ -80,6 +80,7 @@ where
                 Data::from_parts(
                     audio_data.as_mut_ptr() as *mut _,
                     audio_data.len() * channel_count,
+                    channel_count,
                     T::FORMAT,
                 )
             },
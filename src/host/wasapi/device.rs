@@ -1,8 +1,8 @@
 use crate::{
     BackendSpecificError, BufferSize, Data, DefaultStreamConfigError, DeviceNameError,
-    DevicesError, InputCallbackInfo, OutputCallbackInfo, SampleFormat, SampleRate, StreamConfig,
-    SupportedBufferSize, SupportedStreamConfig, SupportedStreamConfigRange,
-    SupportedStreamConfigsError, COMMON_SAMPLE_RATES,
+    DevicesError, Endianness, InputCallbackInfo, OutputCallbackInfo, SampleFormat, SampleRate,
+    StreamConfig, SupportedBufferSize, SupportedSampleRates, SupportedStreamConfig,
+    SupportedStreamConfigRange, SupportedStreamConfigsError, COMMON_SAMPLE_RATES,
 };
 use std;
 use std::ffi::OsString;
@@ -305,13 +305,18 @@ unsafe fn format_from_waveformatex_ptr(
     ) {
         (16, mmreg::WAVE_FORMAT_PCM) => SampleFormat::I16,
         (32, mmreg::WAVE_FORMAT_IEEE_FLOAT) => SampleFormat::F32,
+        (64, mmreg::WAVE_FORMAT_IEEE_FLOAT) => SampleFormat::F64,
         (n_bits, mmreg::WAVE_FORMAT_EXTENSIBLE) => {
             let waveformatextensible_ptr = waveformatex_ptr as *const mmreg::WAVEFORMATEXTENSIBLE;
             let sub = (*waveformatextensible_ptr).SubFormat;
             if n_bits == 16 && cmp_guid(&sub, &ksmedia::KSDATAFORMAT_SUBTYPE_PCM) {
                 SampleFormat::I16
+            } else if n_bits == 32 && cmp_guid(&sub, &ksmedia::KSDATAFORMAT_SUBTYPE_PCM) {
+                SampleFormat::I32
             } else if n_bits == 32 && cmp_guid(&sub, &ksmedia::KSDATAFORMAT_SUBTYPE_IEEE_FLOAT) {
                 SampleFormat::F32
+            } else if n_bits == 64 && cmp_guid(&sub, &ksmedia::KSDATAFORMAT_SUBTYPE_IEEE_FLOAT) {
+                SampleFormat::F64
             } else {
                 return None;
             }
@@ -325,6 +330,8 @@ unsafe fn format_from_waveformatex_ptr(
         sample_rate: SampleRate((*waveformatex_ptr).nSamplesPerSec),
         buffer_size: SupportedBufferSize::Unknown,
         sample_format,
+        endianness: Endianness::NATIVE,
+        channel_layout: None,
     };
     Some(format)
 }
@@ -532,8 +539,11 @@ impl Device {
                     channels: format.channels.clone(),
                     min_sample_rate: SampleRate(rate as _),
                     max_sample_rate: SampleRate(rate as _),
+                    sample_rates: SupportedSampleRates::Continuous,
                     buffer_size: format.buffer_size.clone(),
                     sample_format: format.sample_format.clone(),
+                    endianness: Endianness::NATIVE,
+                    channel_layout: None,
                 })
             }
             Ok(supported_formats.into_iter())
@@ -647,14 +657,16 @@ impl Device {
                 }
             };
 
-            match config.buffer_size {
-                BufferSize::Fixed(_) => {
+            // The requested buffer duration in 100-nanosecond units, where 0 lets WASAPI decide.
+            let buffer_duration = match config.buffer_size {
+                BufferSize::Fixed(_) | BufferSize::Periods { .. } => {
                     // TO DO: We need IAudioClient3 to get buffersize ranges first
                     // Otherwise the supported ranges are unknown. In the meantime
                     // the smallest buffersize is selected and used.
                     return Err(BuildStreamError::StreamConfigNotSupported);
                 }
-                BufferSize::Default => (),
+                BufferSize::Latency(latency) => (latency.as_nanos() / 100) as _,
+                BufferSize::Default => 0,
             };
 
             let mut stream_flags: DWORD = AUDCLNT_STREAMFLAGS_EVENTCALLBACK;
@@ -680,7 +692,7 @@ impl Device {
                 let hresult = (*audio_client).Initialize(
                     share_mode,
                     stream_flags,
-                    0,
+                    buffer_duration,
                     0,
                     &format_attempt.Format,
                     ptr::null(),
@@ -815,14 +827,16 @@ impl Device {
                 }
             };
 
-            match config.buffer_size {
-                BufferSize::Fixed(_) => {
+            // The requested buffer duration in 100-nanosecond units, where 0 lets WASAPI decide.
+            let buffer_duration = match config.buffer_size {
+                BufferSize::Fixed(_) | BufferSize::Periods { .. } => {
                     // TO DO: We need IAudioClient3 to get buffersize ranges first
                     // Otherwise the supported ranges are unknown. In the meantime
                     // the smallest buffersize is selected and used.
                     return Err(BuildStreamError::StreamConfigNotSupported);
                 }
-                BufferSize::Default => (),
+                BufferSize::Latency(latency) => (latency.as_nanos() / 100) as _,
+                BufferSize::Default => 0,
             };
 
             // Computing the format and initializing the device.
@@ -842,7 +856,7 @@ impl Device {
                 let hresult = (*audio_client).Initialize(
                     share_mode,
                     AUDCLNT_STREAMFLAGS_EVENTCALLBACK,
-                    0,
+                    buffer_duration,
                     0,
                     &format_attempt.Format,
                     ptr::null(),
@@ -1227,8 +1241,8 @@ fn config_to_waveformatextensible(
 ) -> Option<mmreg::WAVEFORMATEXTENSIBLE> {
     let format_tag = match sample_format {
         SampleFormat::I16 => mmreg::WAVE_FORMAT_PCM,
-        SampleFormat::F32 => mmreg::WAVE_FORMAT_EXTENSIBLE,
-        SampleFormat::U16 => return None,
+        SampleFormat::I32 | SampleFormat::F32 | SampleFormat::F64 => mmreg::WAVE_FORMAT_EXTENSIBLE,
+        _ => return None,
     };
     let channels = config.channels as WORD;
     let sample_rate = config.sample_rate.0 as DWORD;
@@ -1238,12 +1252,12 @@ fn config_to_waveformatextensible(
     let bits_per_sample = 8 * sample_bytes;
     let cb_size = match sample_format {
         SampleFormat::I16 => 0,
-        SampleFormat::F32 => {
+        SampleFormat::I32 | SampleFormat::F32 | SampleFormat::F64 => {
             let extensible_size = mem::size_of::<mmreg::WAVEFORMATEXTENSIBLE>();
             let ex_size = mem::size_of::<mmreg::WAVEFORMATEX>();
             (extensible_size - ex_size) as WORD
         }
-        SampleFormat::U16 => return None,
+        _ => return None,
     };
     let waveformatex = mmreg::WAVEFORMATEX {
         wFormatTag: format_tag,
@@ -1261,9 +1275,9 @@ fn config_to_waveformatextensible(
     let channel_mask = KSAUDIO_SPEAKER_DIRECTOUT;
 
     let sub_format = match sample_format {
-        SampleFormat::I16 => ksmedia::KSDATAFORMAT_SUBTYPE_PCM,
-        SampleFormat::F32 => ksmedia::KSDATAFORMAT_SUBTYPE_IEEE_FLOAT,
-        SampleFormat::U16 => return None,
+        SampleFormat::I16 | SampleFormat::I32 => ksmedia::KSDATAFORMAT_SUBTYPE_PCM,
+        SampleFormat::F32 | SampleFormat::F64 => ksmedia::KSDATAFORMAT_SUBTYPE_IEEE_FLOAT,
+        _ => return None,
     };
     let waveformatextensible = mmreg::WAVEFORMATEXTENSIBLE {
         Format: waveformatex,
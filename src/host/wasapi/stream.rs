@@ -418,7 +418,12 @@ fn process_input(
             let data = buffer as *mut ();
             let len = frames_available as usize * stream.bytes_per_frame as usize
                 / stream.sample_format.sample_size();
-            let data = Data::from_parts(data, len, stream.sample_format);
+            let data = Data::from_parts(
+                data,
+                len,
+                stream.config.channels as usize,
+                stream.sample_format,
+            );
 
             // The `qpc_position` is in 100 nanosecond units. Convert it to nanoseconds.
             let timestamp = match input_timestamp(stream, qpc_position) {
@@ -472,7 +477,12 @@ fn process_output(
         let data = buffer as *mut ();
         let len = frames_available as usize * stream.bytes_per_frame as usize
             / stream.sample_format.sample_size();
-        let mut data = Data::from_parts(data, len, stream.sample_format);
+        let mut data = Data::from_parts(
+            data,
+            len,
+            stream.config.channels as usize,
+            stream.sample_format,
+        );
         let sample_rate = stream.config.sample_rate;
         let timestamp = match output_timestamp(stream, frames_available, sample_rate) {
             Ok(ts) => ts,
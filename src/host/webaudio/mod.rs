@@ -7,10 +7,11 @@ use self::wasm_bindgen::prelude::*;
 use self::wasm_bindgen::JsCast;
 use self::web_sys::{AudioContext, AudioContextOptions};
 use crate::{
-    BackendSpecificError, BufferSize, BuildStreamError, Data, DefaultStreamConfigError,
-    DeviceNameError, DevicesError, InputCallbackInfo, OutputCallbackInfo, PauseStreamError,
-    PlayStreamError, SampleFormat, SampleRate, StreamConfig, StreamError, SupportedBufferSize,
-    SupportedStreamConfig, SupportedStreamConfigRange, SupportedStreamConfigsError,
+    duration_to_frames, BackendSpecificError, BufferSize, BuildStreamError, Data,
+    DefaultStreamConfigError, DeviceNameError, DevicesError, Endianness, InputCallbackInfo,
+    OutputCallbackInfo, PauseStreamError, PlayStreamError, SampleFormat, SampleRate, StreamConfig,
+    StreamError, SupportedBufferSize, SupportedSampleRates, SupportedStreamConfig,
+    SupportedStreamConfigRange, SupportedStreamConfigsError,
 };
 use std::ops::DerefMut;
 use std::sync::{Arc, Mutex, RwLock};
@@ -99,14 +100,18 @@ impl Device {
         let buffer_size = SupportedBufferSize::Range {
             min: MIN_BUFFER_SIZE,
             max: MAX_BUFFER_SIZE,
+            periods: None,
         };
         let configs: Vec<_> = (MIN_CHANNELS..=MAX_CHANNELS)
             .map(|channels| SupportedStreamConfigRange {
                 channels,
                 min_sample_rate: MIN_SAMPLE_RATE,
                 max_sample_rate: MAX_SAMPLE_RATE,
+                sample_rates: SupportedSampleRates::Continuous,
                 buffer_size: buffer_size.clone(),
                 sample_format: SUPPORTED_SAMPLE_FORMAT,
+                endianness: Endianness::NATIVE,
+                channel_layout: None,
             })
             .collect();
         Ok(configs.into_iter())
@@ -207,7 +212,12 @@ impl DeviceTrait for Device {
                     v as usize
                 }
             }
+            BufferSize::Latency(latency) => match duration_to_frames(latency, config.sample_rate) {
+                0 => return Err(BuildStreamError::StreamConfigNotSupported),
+                v => v as usize,
+            },
             BufferSize::Default => DEFAULT_BUFFER_SIZE,
+            BufferSize::Periods { .. } => return Err(BuildStreamError::StreamConfigNotSupported),
         };
         let buffer_size_samples = buffer_size_frames * n_channels;
         let buffer_time_step_secs = buffer_time_step_secs(buffer_size_frames, config.sample_rate);
@@ -287,7 +297,8 @@ impl DeviceTrait for Device {
                     {
                         let len = temporary_buffer.len();
                         let data = temporary_buffer.as_mut_ptr() as *mut ();
-                        let mut data = unsafe { Data::from_parts(data, len, sample_format) };
+                        let mut data =
+                            unsafe { Data::from_parts(data, len, n_channels, sample_format) };
                         let mut data_callback = data_callback_handle.lock().unwrap();
                         let callback = crate::StreamInstant::from_secs_f64(now);
                         let playback = crate::StreamInstant::from_secs_f64(time_at_start_of_buffer);
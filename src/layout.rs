@@ -0,0 +1,142 @@
+use crate::{ChannelCount, Data, SampleFormat};
+use std::ptr;
+
+/// The arrangement of the samples of each channel within a `Data` buffer.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum DataLayout {
+    /// The samples of all channels alternate frame by frame within a single buffer.
+    Interleaved,
+    /// Each channel has a separate buffer of its own, also known as non-interleaved data.
+    Planar,
+}
+
+// Storage for presenting interleaved host buffers as planar `Data` and back.
+//
+// Hosts that cannot hand over per-channel buffers natively use this to implement the planar
+// stream constructors. The storage only grows when the host delivers a larger buffer than before,
+// so it does not allocate in the steady state.
+pub(crate) struct PlanarBuffer {
+    channels: usize,
+    // `u64` elements keep every plane aligned for any `SampleFormat`.
+    storage: Vec<u64>,
+    planes: Vec<*mut ()>,
+}
+
+// The plane pointers only ever point into `storage`, which is owned by the same value.
+unsafe impl Send for PlanarBuffer {}
+
+impl PlanarBuffer {
+    pub(crate) fn new(channels: ChannelCount) -> Self {
+        PlanarBuffer {
+            channels: channels as usize,
+            storage: Vec::new(),
+            planes: vec![ptr::null_mut(); channels as usize],
+        }
+    }
+
+    // A planar view onto the storage, sized for `len` interleaved samples of `sample_format`.
+    //
+    // The contents are left over from the previous use.
+    pub(crate) fn data(&mut self, len: usize, sample_format: SampleFormat) -> Data {
+        let frames = len / self.channels;
+        let plane_bytes = frames * sample_format.sample_size();
+        let total_bytes = plane_bytes * self.channels;
+        let words = total_bytes.div_ceil(8);
+        if self.storage.len() < words {
+            self.storage.resize(words, 0);
+        }
+        let base = self.storage.as_mut_ptr() as *mut u8;
+        for (channel, plane) in self.planes.iter_mut().enumerate() {
+            *plane = unsafe { base.add(channel * plane_bytes) } as *mut ();
+        }
+        unsafe {
+            Data::from_planar_parts(self.planes.as_ptr(), self.channels, frames, sample_format)
+        }
+    }
+
+    // Copies interleaved `source` into the storage, returning a planar view of it.
+    pub(crate) fn deinterleave(&mut self, source: &Data) -> Data {
+        let mut planar = self.data(source.len(), source.sample_format());
+        let sample_size = source.sample_format().sample_size();
+        let channels = self.channels;
+        let bytes = source.bytes();
+        for channel in 0..channels {
+            let plane = planar.plane_bytes_mut(channel).expect("planar data");
+            for (dst, src) in plane
+                .chunks_exact_mut(sample_size)
+                .zip(bytes.chunks_exact(sample_size * channels))
+            {
+                let offset = channel * sample_size;
+                dst.copy_from_slice(&src[offset..offset + sample_size]);
+            }
+        }
+        planar
+    }
+
+    // Interleaves the planar view last returned by `data` into `destination`.
+    pub(crate) fn interleave(&mut self, destination: &mut Data) {
+        let planar = self.data(destination.len(), destination.sample_format());
+        let sample_size = destination.sample_format().sample_size();
+        let channels = self.channels;
+        let bytes = destination.bytes_mut();
+        for channel in 0..channels {
+            let plane = planar.plane_bytes(channel).expect("planar data");
+            for (src, dst) in plane
+                .chunks_exact(sample_size)
+                .zip(bytes.chunks_exact_mut(sample_size * channels))
+            {
+                let offset = channel * sample_size;
+                dst[offset..offset + sample_size].copy_from_slice(src);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{DataLayout, PlanarBuffer};
+    use crate::{Data, SampleFormat, I24};
+
+    #[test]
+    fn deinterleave() {
+        let mut interleaved = [1i16, -1, 2, -2, 3, -3];
+        let data = unsafe {
+            Data::from_parts(
+                interleaved.as_mut_ptr() as *mut (),
+                interleaved.len(),
+                2,
+                SampleFormat::I16,
+            )
+        };
+        let mut planar = PlanarBuffer::new(2);
+        let data = planar.deinterleave(&data);
+        assert_eq!(data.layout(), DataLayout::Planar);
+        assert_eq!(data.plane::<i16>(0), Some(&[1, 2, 3][..]));
+        assert_eq!(data.plane::<i16>(1), Some(&[-1, -2, -3][..]));
+    }
+
+    #[test]
+    fn interleave() {
+        let mut interleaved = [I24::default(); 6];
+        let mut data = unsafe {
+            Data::from_parts(
+                interleaved.as_mut_ptr() as *mut (),
+                interleaved.len(),
+                3,
+                SampleFormat::I24,
+            )
+        };
+        let mut planar = PlanarBuffer::new(3);
+        {
+            let mut planes = planar.data(data.len(), data.sample_format());
+            for (channel, plane) in planes.planes_mut::<I24>().unwrap().enumerate() {
+                for (frame, sample) in plane.iter_mut().enumerate() {
+                    *sample = I24::new((frame * 10 + channel) as i32).unwrap();
+                }
+            }
+        }
+        planar.interleave(&mut data);
+        let values: Vec<i32> = interleaved.iter().map(I24::value).collect();
+        assert_eq!(values, [0, 1, 2, 10, 11, 12]);
+    }
+}
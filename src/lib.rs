@@ -88,7 +88,7 @@
 //! In this example, we simply fill the given output buffer with silence.
 //!
 //! ```no_run
-//! use cpal::{Data, Sample, SampleFormat};
+//! use cpal::{Data, Sample, SampleFormat, I24, I24In32};
 //! use cpal::traits::{DeviceTrait, HostTrait, StreamTrait};
 //! # let host = cpal::default_host();
 //! # let device = host.default_output_device().unwrap();
@@ -98,8 +98,17 @@
 //! let config = supported_config.into();
 //! let stream = match sample_format {
 //!     SampleFormat::F32 => device.build_output_stream(&config, write_silence::<f32>, err_fn),
+//!     SampleFormat::F64 => device.build_output_stream(&config, write_silence::<f64>, err_fn),
 //!     SampleFormat::I16 => device.build_output_stream(&config, write_silence::<i16>, err_fn),
 //!     SampleFormat::U16 => device.build_output_stream(&config, write_silence::<u16>, err_fn),
+//!     SampleFormat::I32 => device.build_output_stream(&config, write_silence::<i32>, err_fn),
+//!     SampleFormat::U32 => device.build_output_stream(&config, write_silence::<u32>, err_fn),
+//!     SampleFormat::I24 => device.build_output_stream(&config, write_silence::<I24>, err_fn),
+//!     SampleFormat::I24In32 => {
+//!         device.build_output_stream(&config, write_silence::<I24In32>, err_fn)
+//!     }
+//!     SampleFormat::I8 => device.build_output_stream(&config, write_silence::<i8>, err_fn),
+//!     SampleFormat::U8 => device.build_output_stream(&config, write_silence::<u8>, err_fn),
 //! }.unwrap();
 //!
 //! fn write_silence<T: Sample>(data: &mut [T], _: &cpal::OutputCallbackInfo) {
@@ -150,20 +159,41 @@ extern crate lazy_static;
 #[cfg(target_os = "emscripten")]
 #[macro_use]
 extern crate stdweb;
+#[cfg(feature = "futures")]
+extern crate futures_core;
+#[cfg(feature = "futures")]
+extern crate futures_sink;
+#[cfg(feature = "serde")]
+extern crate serde;
 extern crate thiserror;
 
+pub use channel_layout::{ChannelLayout, ChannelPosition};
+pub use config_query::{ConfigConstraints, SampleRateConstraint, ScoringPolicy};
+pub use conversions::{convert_data, convert_samples};
+pub use dither::{Dither, DitherKind};
 pub use error::*;
+pub use flow::CallbackFlow;
+pub use layout::DataLayout;
 pub use platform::{
     available_hosts, default_host, host_from_id, Device, Devices, Host, HostId, Stream,
     SupportedInputConfigs, SupportedOutputConfigs, ALL_HOSTS,
 };
-pub use samples_formats::{Sample, SampleFormat};
+pub use samples_formats::{Endianness, I24In32, Sample, SampleFormat, I24};
 use std::convert::TryInto;
 use std::ops::{Div, Mul};
 use std::time::Duration;
 
+pub mod blocking;
+mod channel_layout;
+mod config_query;
+mod conversions;
+mod dither;
 mod error;
+mod flow;
+#[cfg(feature = "futures")]
+pub mod futures;
 mod host;
+mod layout;
 pub mod platform;
 mod samples_formats;
 pub mod traits;
@@ -179,6 +209,7 @@ pub type ChannelCount = u16;
 
 /// The number of samples processed per second for a single channel of audio.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct SampleRate(pub u32);
 
 impl<T> Mul<T> for SampleRate
@@ -204,6 +235,11 @@ where
 /// The desired number of frames for the hardware buffer.
 pub type FrameCount = u32;
 
+// The number of frames spanning `duration` at `sample_rate`, rounded to the nearest frame.
+pub(crate) fn duration_to_frames(duration: Duration, sample_rate: SampleRate) -> FrameCount {
+    (duration.as_secs_f64() * sample_rate.0 as f64).round() as FrameCount
+}
+
 /// The buffer size used by the device.
 ///
 /// Default is used when no specific buffer size is set and uses the default
@@ -212,67 +248,176 @@ pub type FrameCount = u32;
 /// should be used in accordance with the SupportedBufferSize range produced by
 /// the SupportedStreamConfig API.  
 #[derive(Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum BufferSize {
     Default,
     Fixed(FrameCount),
+    /// A buffer made up of `periods` periods of `period_size` frames each.
+    ///
+    /// The host wakes up once per period, so the period size determines how often the data
+    /// callback runs while the number of periods determines the total latency. ALSA honours this
+    /// within the `SupportedPeriods` it reports, and JACK accepts it if `period_size` matches the
+    /// server's buffer size, as the number of periods is set when the server is started. Other
+    /// hosts return `BuildStreamError::StreamConfigNotSupported`.
+    Periods {
+        period_size: FrameCount,
+        periods: u32,
+    },
+    /// A buffer holding approximately the given duration of audio.
+    ///
+    /// Each host translates the duration into its own buffer or period settings, which may not
+    /// be able to match it exactly. `StreamTrait::buffer_latency` reports the latency that was
+    /// actually achieved. JACK cannot change the server's buffer size, so its streams always use
+    /// that.
+    Latency(Duration),
 }
 
 /// The set of parameters used to describe how to open a stream.
 ///
 /// The sample format is omitted in favour of using a sample type.
 #[derive(Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct StreamConfig {
     pub channels: ChannelCount,
     pub sample_rate: SampleRate,
     pub buffer_size: BufferSize,
+    /// The speaker position of each channel.
+    ///
+    /// `None` leaves the arrangement up to the host. Only hosts that report a layout through
+    /// `SupportedStreamConfigRange::channel_layout` can honour a request, others ignore it.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub channel_layout: Option<ChannelLayout>,
+    /// Whether building the stream fails if the host can only provide a sample rate or buffer
+    /// size close to the requested one, rather than settling for it.
+    ///
+    /// Only hosts that report `StreamTrait::negotiated_config` can honour this, others ignore it.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub strict: bool,
+}
+
+/// The set of parameters used to describe how to open a full-duplex stream.
+///
+/// Input and output run at the same sample rate with the same buffer size, but may have different
+/// channel counts.
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct DuplexStreamConfig {
+    pub input_channels: ChannelCount,
+    pub output_channels: ChannelCount,
+    pub sample_rate: SampleRate,
+    pub buffer_size: BufferSize,
+    /// Whether building the stream fails if the host settles for a sample rate or buffer size
+    /// close to the requested one in either direction, as with `StreamConfig::strict`.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub strict: bool,
 }
 
 /// Describes the minimum and maximum supported buffer size for the device
 #[derive(Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum SupportedBufferSize {
     Range {
         min: FrameCount,
         max: FrameCount,
+        /// The period sizes and counts that can be requested through `BufferSize::Periods`, for
+        /// hosts that report them.
+        periods: Option<SupportedPeriods>,
     },
     /// In the case that the platform provides no way of getting the default
     /// buffersize before starting a stream.
     Unknown,
 }
 
+/// The period sizes and numbers of periods supported by a device that divides its buffer into
+/// periods.
+///
+/// Not every combination within these bounds is valid, as the total buffer size must also lie
+/// within the bounds of the `SupportedBufferSize::Range`.
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SupportedPeriods {
+    pub min_period_size: FrameCount,
+    pub max_period_size: FrameCount,
+    pub min_periods: u32,
+    pub max_periods: u32,
+}
+
+/// The sample rates supported between the minimum and maximum of a `SupportedStreamConfigRange`.
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum SupportedSampleRates {
+    /// Every rate from the minimum to the maximum is supported.
+    Continuous,
+    /// Only these rates are supported, in ascending order.
+    Discrete(Vec<SampleRate>),
+}
+
 /// Describes a range of supported stream configurations, retrieved via the
 /// `Device::supported_input/output_configs` method.
 #[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct SupportedStreamConfigRange {
     pub(crate) channels: ChannelCount,
     /// Minimum value for the samples rate of the supported formats.
     pub(crate) min_sample_rate: SampleRate,
     /// Maximum value for the samples rate of the supported formats.
     pub(crate) max_sample_rate: SampleRate,
+    /// Whether all rates between the minimum and maximum are supported.
+    pub(crate) sample_rates: SupportedSampleRates,
     /// Buffersize ranges supported by the device
     pub(crate) buffer_size: SupportedBufferSize,
     /// Type of data expected by the device.
     pub(crate) sample_format: SampleFormat,
+    /// Byte order in which the device lays out samples.
+    pub(crate) endianness: Endianness,
+    /// Speaker positions of the channels, if the device reports them.
+    pub(crate) channel_layout: Option<ChannelLayout>,
 }
 
 /// Describes a single supported stream configuration, retrieved via either a
 /// `SupportedStreamConfigRange` instance or one of the `Device::default_input/output_config` methods.
 #[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct SupportedStreamConfig {
     channels: ChannelCount,
     sample_rate: SampleRate,
     buffer_size: SupportedBufferSize,
     sample_format: SampleFormat,
+    endianness: Endianness,
+    channel_layout: Option<ChannelLayout>,
+}
+
+/// The configuration that a stream actually runs with, retrieved via
+/// `StreamTrait::negotiated_config`.
+///
+/// Hosts may settle for values close to those of the requested `StreamConfig`.
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct NegotiatedStreamConfig {
+    channels: ChannelCount,
+    sample_rate: SampleRate,
+    sample_format: SampleFormat,
+    period_size: Option<FrameCount>,
+    buffer_size: Option<FrameCount>,
 }
 
 /// A buffer of dynamically typed audio data, passed to raw stream callbacks.
 ///
 /// Raw input stream callbacks receive `&Data`, while raw output stream callbacks expect `&mut
 /// Data`.
+///
+/// Data is interleaved unless the stream was created with one of the planar constructors, such as
+/// `DeviceTrait::build_input_stream_planar_raw`. Interleaved data is accessed as a whole through
+/// `as_slice`, while planar data is accessed one channel at a time through `plane`.
 #[derive(Debug)]
 pub struct Data {
+    // Interleaved data points to the first sample. Planar data points to an array of `planes`
+    // pointers, each to the first sample of a channel.
     data: *mut (),
     len: usize,
     sample_format: SampleFormat,
+    layout: DataLayout,
+    channels: usize,
 }
 
 /// A monotonic time instance associated with a stream, retrieved from either:
@@ -321,6 +466,17 @@ pub struct OutputStreamTimestamp {
     pub playback: StreamInstant,
 }
 
+/// A timestamp associated with a call to a duplex stream's data callback.
+#[derive(Copy, Clone, Debug, Eq, Hash, PartialEq)]
+pub struct DuplexStreamTimestamp {
+    /// The instant the stream's data callback was invoked.
+    pub callback: StreamInstant,
+    /// The instant that the input data was captured from the device.
+    pub capture: StreamInstant,
+    /// The predicted instant that the output data will be delivered to the device for playback.
+    pub playback: StreamInstant,
+}
+
 /// Information relevant to a single call to the user's input stream data callback.
 #[derive(Debug, Clone, PartialEq)]
 pub struct InputCallbackInfo {
@@ -333,6 +489,12 @@ pub struct OutputCallbackInfo {
     timestamp: OutputStreamTimestamp,
 }
 
+/// Information relevant to a single call to the user's duplex stream data callback.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DuplexCallbackInfo {
+    timestamp: DuplexStreamTimestamp,
+}
+
 impl SupportedStreamConfig {
     pub fn channels(&self) -> ChannelCount {
         self.channels
@@ -350,12 +512,129 @@ impl SupportedStreamConfig {
         self.sample_format
     }
 
+    /// The byte order in which the device lays out samples.
+    ///
+    /// Stream callbacks always see samples in native byte order regardless of this value.
+    pub fn endianness(&self) -> Endianness {
+        self.endianness
+    }
+
+    /// The speaker position of each channel, if the host reports it.
+    pub fn channel_layout(&self) -> Option<&ChannelLayout> {
+        self.channel_layout.as_ref()
+    }
+
     pub fn config(&self) -> StreamConfig {
         StreamConfig {
             channels: self.channels,
             sample_rate: self.sample_rate,
             buffer_size: BufferSize::Default,
+            channel_layout: self.channel_layout.clone(),
+            strict: false,
+        }
+    }
+}
+
+impl DuplexStreamConfig {
+    /// The config of the input half of the stream.
+    pub fn input_config(&self) -> StreamConfig {
+        self.config(self.input_channels)
+    }
+
+    /// The config of the output half of the stream.
+    pub fn output_config(&self) -> StreamConfig {
+        self.config(self.output_channels)
+    }
+
+    fn config(&self, channels: ChannelCount) -> StreamConfig {
+        StreamConfig {
+            channels,
+            sample_rate: self.sample_rate,
+            buffer_size: self.buffer_size.clone(),
+            channel_layout: None,
+            strict: self.strict,
+        }
+    }
+}
+
+impl NegotiatedStreamConfig {
+    pub fn channels(&self) -> ChannelCount {
+        self.channels
+    }
+
+    pub fn sample_rate(&self) -> SampleRate {
+        self.sample_rate
+    }
+
+    pub fn sample_format(&self) -> SampleFormat {
+        self.sample_format
+    }
+
+    /// The number of frames the host processes at a time, if it reports it.
+    pub fn period_size(&self) -> Option<FrameCount> {
+        self.period_size
+    }
+
+    /// The number of frames held by the host's buffer, if it reports it.
+    pub fn buffer_size(&self) -> Option<FrameCount> {
+        self.buffer_size
+    }
+
+    // The first parameter that differs from what `config` asked for, as ruled out by
+    // `StreamConfig::strict`. The negotiated value is reported as the only supported one.
+    pub(crate) fn mismatch(&self, config: &StreamConfig) -> Option<RejectedParameter> {
+        if self.channels != config.channels {
+            return Some(RejectedParameter::Channels {
+                requested: config.channels,
+                min: self.channels,
+                max: self.channels,
+            });
+        }
+        if self.sample_rate != config.sample_rate {
+            return Some(RejectedParameter::SampleRate {
+                requested: config.sample_rate,
+                min: self.sample_rate,
+                max: self.sample_rate,
+                rates: SupportedSampleRates::Continuous,
+            });
+        }
+        let buffer_size_matches = match config.buffer_size {
+            BufferSize::Default => true,
+            BufferSize::Fixed(frames) => self.buffer_size == Some(frames),
+            BufferSize::Periods {
+                period_size,
+                periods,
+            } => {
+                self.period_size == Some(period_size)
+                    && self.buffer_size.is_some()
+                    && self.buffer_size == period_size.checked_mul(periods)
+            }
+            // Buffers hold a whole number of periods, so a latency is met to within one period.
+            BufferSize::Latency(latency) => {
+                let frames = duration_to_frames(latency, config.sample_rate);
+                match (self.buffer_size, self.period_size) {
+                    (Some(buffer_size), Some(period_size)) => {
+                        buffer_size.abs_diff(frames) <= period_size
+                    }
+                    (buffer_size, _) => buffer_size == Some(frames),
+                }
+            }
+        };
+        if buffer_size_matches {
+            return None;
         }
+        let supported = match self.buffer_size {
+            Some(frames) => SupportedBufferSize::Range {
+                min: frames,
+                max: frames,
+                periods: None,
+            },
+            None => SupportedBufferSize::Unknown,
+        };
+        Some(RejectedParameter::BufferSize {
+            requested: config.buffer_size.clone(),
+            supported,
+        })
     }
 }
 
@@ -403,7 +682,7 @@ impl StreamInstant {
     fn from_nanos(nanos: i64) -> Self {
         let secs = nanos / 1_000_000_000;
         let subsec_nanos = nanos - secs * 1_000_000_000;
-        Self::new(secs as i64, subsec_nanos as u32)
+        Self::new(secs, subsec_nanos as u32)
     }
 
     #[allow(dead_code)]
@@ -435,6 +714,17 @@ impl InputCallbackInfo {
     pub fn timestamp(&self) -> InputStreamTimestamp {
         self.timestamp
     }
+
+    // The info for the part of the buffer captured `offset` after its start.
+    pub(crate) fn offset(&self, offset: Duration) -> Self {
+        let mut info = self.clone();
+        info.timestamp.capture = self
+            .timestamp
+            .capture
+            .add(offset)
+            .unwrap_or(self.timestamp.capture);
+        info
+    }
 }
 
 impl OutputCallbackInfo {
@@ -442,6 +732,24 @@ impl OutputCallbackInfo {
     pub fn timestamp(&self) -> OutputStreamTimestamp {
         self.timestamp
     }
+
+    // The info for the part of the buffer played `offset` after its start.
+    pub(crate) fn offset(&self, offset: Duration) -> Self {
+        let mut info = self.clone();
+        info.timestamp.playback = self
+            .timestamp
+            .playback
+            .add(offset)
+            .unwrap_or(self.timestamp.playback);
+        info
+    }
+}
+
+impl DuplexCallbackInfo {
+    /// The timestamp associated with the call to a duplex stream's data callback.
+    pub fn timestamp(&self) -> DuplexStreamTimestamp {
+        self.timestamp
+    }
 }
 
 #[allow(clippy::len_without_is_empty)]
@@ -453,17 +761,42 @@ impl Data {
     // - The `data` pointer must point to the first sample in the slice containing all samples.
     // - The `len` must describe the length of the buffer as a number of samples in the expected
     //   format specified via the `sample_format` argument.
+    // - The `channels` must describe the number of interleaved channels, by which `len` is
+    //   divisible.
     // - The `sample_format` must correctly represent the underlying sample data delivered/expected
     //   by the stream.
     pub(crate) unsafe fn from_parts(
         data: *mut (),
         len: usize,
+        channels: usize,
         sample_format: SampleFormat,
     ) -> Self {
         Data {
             data,
             len,
             sample_format,
+            layout: DataLayout::Interleaved,
+            channels,
+        }
+    }
+
+    // Internal constructor for planar data.
+    //
+    // The same requirements as for `from_parts` apply to each of the `channels` pointers in the
+    // `planes` array, each of which must point to `frames` samples. The array itself must remain
+    // valid for as long as the `Data` is used.
+    pub(crate) unsafe fn from_planar_parts(
+        planes: *const *mut (),
+        channels: usize,
+        frames: usize,
+        sample_format: SampleFormat,
+    ) -> Self {
+        Data {
+            data: planes as *mut (),
+            len: channels * frames,
+            sample_format,
+            layout: DataLayout::Planar,
+            channels,
         }
     }
 
@@ -472,10 +805,27 @@ impl Data {
         self.sample_format
     }
 
-    /// The full length of the buffer in samples.
+    /// The arrangement of the channels within the buffer.
+    pub fn layout(&self) -> DataLayout {
+        self.layout
+    }
+
+    /// The number of channels in the buffer.
+    pub fn channels(&self) -> ChannelCount {
+        self.channels as ChannelCount
+    }
+
+    /// The length of the buffer in frames, i.e. the number of samples in each channel.
     ///
-    /// The returned length is the same length as the slice of type `T` that would be returned via
-    /// `as_slice` given a sample type that matches the inner sample format.
+    /// Data without any channels has no frames.
+    pub fn frame_count(&self) -> usize {
+        self.len.checked_div(self.channels).unwrap_or(0)
+    }
+
+    /// The full length of the buffer in samples, across all channels.
+    ///
+    /// For interleaved data, the returned length is the same length as the slice of type `T` that
+    /// would be returned via `as_slice` given a sample type that matches the inner sample format.
     pub fn len(&self) -> usize {
         self.len
     }
@@ -483,7 +833,14 @@ impl Data {
     /// The raw slice of memory representing the underlying audio data as a slice of bytes.
     ///
     /// It is up to the user to interpret the slice of memory based on `Data::sample_format`.
+    ///
+    /// **panic!**s if the data is planar. Use `plane_bytes` instead.
     pub fn bytes(&self) -> &[u8] {
+        assert_eq!(
+            self.layout,
+            DataLayout::Interleaved,
+            "planar data has no single buffer"
+        );
         let len = self.len * self.sample_format.sample_size();
         // The safety of this block relies on correct construction of the `Data` instance. See
         // the unsafe `from_parts` constructor for these requirements.
@@ -493,7 +850,14 @@ impl Data {
     /// The raw slice of memory representing the underlying audio data as a slice of bytes.
     ///
     /// It is up to the user to interpret the slice of memory based on `Data::sample_format`.
+    ///
+    /// **panic!**s if the data is planar. Use `plane_bytes_mut` instead.
     pub fn bytes_mut(&mut self) -> &mut [u8] {
+        assert_eq!(
+            self.layout,
+            DataLayout::Interleaved,
+            "planar data has no single buffer"
+        );
         let len = self.len * self.sample_format.sample_size();
         // The safety of this block relies on correct construction of the `Data` instance. See
         // the unsafe `from_parts` constructor for these requirements.
@@ -502,12 +866,13 @@ impl Data {
 
     /// Access the data as a slice of sample type `T`.
     ///
-    /// Returns `None` if the sample type does not match the expected sample format.
+    /// Returns `None` if the sample type does not match the expected sample format, or if the data
+    /// is planar.
     pub fn as_slice<T>(&self) -> Option<&[T]>
     where
         T: Sample,
     {
-        if T::FORMAT == self.sample_format {
+        if T::FORMAT == self.sample_format && self.layout == DataLayout::Interleaved {
             // The safety of this block relies on correct construction of the `Data` instance. See
             // the unsafe `from_parts` constructor for these requirements.
             unsafe { Some(std::slice::from_raw_parts(self.data as *const T, self.len)) }
@@ -518,12 +883,13 @@ impl Data {
 
     /// Access the data as a slice of sample type `T`.
     ///
-    /// Returns `None` if the sample type does not match the expected sample format.
+    /// Returns `None` if the sample type does not match the expected sample format, or if the data
+    /// is planar.
     pub fn as_slice_mut<T>(&mut self) -> Option<&mut [T]>
     where
         T: Sample,
     {
-        if T::FORMAT == self.sample_format {
+        if T::FORMAT == self.sample_format && self.layout == DataLayout::Interleaved {
             // The safety of this block relies on correct construction of the `Data` instance. See
             // the unsafe `from_parts` constructor for these requirements.
             unsafe {
@@ -536,6 +902,173 @@ impl Data {
             None
         }
     }
+
+    /// Iterate over the frames of interleaved data, each a slice of one sample per channel.
+    ///
+    /// Returns `None` if the sample type does not match the expected sample format, or if the data
+    /// is planar.
+    pub fn frames<T>(&self) -> Option<std::slice::ChunksExact<'_, T>>
+    where
+        T: Sample,
+    {
+        // Data without any channels has no frames, rather than frames of no samples.
+        let channels = self.channels.max(1);
+        let len = self.frame_count() * channels;
+        self.as_slice()
+            .map(|slice| slice[..len].chunks_exact(channels))
+    }
+
+    /// Iterate over the frames of interleaved data, each a slice of one sample per channel.
+    ///
+    /// Returns `None` if the sample type does not match the expected sample format, or if the data
+    /// is planar.
+    pub fn frames_mut<T>(&mut self) -> Option<std::slice::ChunksExactMut<'_, T>>
+    where
+        T: Sample,
+    {
+        let channels = self.channels.max(1);
+        let len = self.frame_count() * channels;
+        self.as_slice_mut()
+            .map(|slice| slice[..len].chunks_exact_mut(channels))
+    }
+
+    /// Access interleaved data as a slice of frames of `N` samples of type `T`.
+    ///
+    /// Returns `None` if the sample type does not match the expected sample format, if the data is
+    /// planar or if the data does not have exactly `N` channels.
+    pub fn as_frames<T, const N: usize>(&self) -> Option<&[[T; N]]>
+    where
+        T: Sample,
+    {
+        if N != self.channels {
+            return None;
+        }
+        // `[T; N]` has the same layout as `N` consecutive `T`s.
+        let frames = self.frame_count();
+        self.as_slice().map(|slice: &[T]| unsafe {
+            std::slice::from_raw_parts(slice.as_ptr() as *const [T; N], frames)
+        })
+    }
+
+    /// Access interleaved data as a slice of frames of `N` samples of type `T`.
+    ///
+    /// Returns `None` if the sample type does not match the expected sample format, if the data is
+    /// planar or if the data does not have exactly `N` channels.
+    pub fn as_frames_mut<T, const N: usize>(&mut self) -> Option<&mut [[T; N]]>
+    where
+        T: Sample,
+    {
+        if N != self.channels {
+            return None;
+        }
+        // `[T; N]` has the same layout as `N` consecutive `T`s.
+        let frames = self.frame_count();
+        self.as_slice_mut().map(|slice: &mut [T]| unsafe {
+            std::slice::from_raw_parts_mut(slice.as_mut_ptr() as *mut [T; N], frames)
+        })
+    }
+
+    /// The number of separate channel buffers, or 0 if the data is interleaved.
+    pub fn plane_count(&self) -> usize {
+        match self.layout {
+            DataLayout::Interleaved => 0,
+            DataLayout::Planar => self.channels,
+        }
+    }
+
+    // A pointer to the first sample of the given channel of planar data.
+    fn plane_ptr(&self, channel: usize) -> Option<*mut ()> {
+        if self.layout == DataLayout::Planar && channel < self.channels {
+            // The safety of this block relies on correct construction of the `Data` instance. See
+            // the unsafe `from_planar_parts` constructor for these requirements.
+            unsafe { Some(*(self.data as *const *mut ()).add(channel)) }
+        } else {
+            None
+        }
+    }
+
+    /// The raw memory of a single channel of planar data as a slice of bytes.
+    ///
+    /// Returns `None` if the data is interleaved or `channel` is out of range.
+    pub fn plane_bytes(&self, channel: usize) -> Option<&[u8]> {
+        let len = self.frame_count() * self.sample_format.sample_size();
+        self.plane_ptr(channel)
+            .map(|ptr| unsafe { std::slice::from_raw_parts(ptr as *const u8, len) })
+    }
+
+    /// The raw memory of a single channel of planar data as a slice of bytes.
+    ///
+    /// Returns `None` if the data is interleaved or `channel` is out of range.
+    pub fn plane_bytes_mut(&mut self, channel: usize) -> Option<&mut [u8]> {
+        let len = self.frame_count() * self.sample_format.sample_size();
+        self.plane_ptr(channel)
+            .map(|ptr| unsafe { std::slice::from_raw_parts_mut(ptr as *mut u8, len) })
+    }
+
+    /// Access a single channel of planar data as a slice of sample type `T`.
+    ///
+    /// Returns `None` if the sample type does not match the expected sample format, if the data
+    /// is interleaved or if `channel` is out of range.
+    pub fn plane<T>(&self, channel: usize) -> Option<&[T]>
+    where
+        T: Sample,
+    {
+        if T::FORMAT != self.sample_format {
+            return None;
+        }
+        let len = self.frame_count();
+        self.plane_ptr(channel)
+            .map(|ptr| unsafe { std::slice::from_raw_parts(ptr as *const T, len) })
+    }
+
+    /// Access a single channel of planar data as a slice of sample type `T`.
+    ///
+    /// Returns `None` if the sample type does not match the expected sample format, if the data
+    /// is interleaved or if `channel` is out of range.
+    pub fn plane_mut<T>(&mut self, channel: usize) -> Option<&mut [T]>
+    where
+        T: Sample,
+    {
+        if T::FORMAT != self.sample_format {
+            return None;
+        }
+        let len = self.frame_count();
+        self.plane_ptr(channel)
+            .map(|ptr| unsafe { std::slice::from_raw_parts_mut(ptr as *mut T, len) })
+    }
+
+    /// Iterate over the channels of planar data as slices of sample type `T`.
+    ///
+    /// Returns `None` if the sample type does not match the expected sample format or if the data
+    /// is interleaved.
+    pub fn planes<'a, T>(&'a self) -> Option<impl Iterator<Item = &'a [T]> + 'a>
+    where
+        T: Sample + 'a,
+    {
+        if T::FORMAT != self.sample_format || self.layout != DataLayout::Planar {
+            return None;
+        }
+        Some((0..self.channels).map(move |channel| self.plane(channel).unwrap()))
+    }
+
+    /// Iterate over the channels of planar data as mutable slices of sample type `T`.
+    ///
+    /// Returns `None` if the sample type does not match the expected sample format or if the data
+    /// is interleaved.
+    pub fn planes_mut<'a, T>(&'a mut self) -> Option<impl Iterator<Item = &'a mut [T]> + 'a>
+    where
+        T: Sample + 'a,
+    {
+        if T::FORMAT != self.sample_format || self.layout != DataLayout::Planar {
+            return None;
+        }
+        let len = self.frame_count();
+        let planes = self.data as *const *mut T;
+        // Each channel has a buffer of its own, so the slices never alias.
+        Some((0..self.channels).map(move |channel| unsafe {
+            std::slice::from_raw_parts_mut(*planes.add(channel), len)
+        }))
+    }
 }
 
 impl SupportedStreamConfigRange {
@@ -559,17 +1092,73 @@ impl SupportedStreamConfigRange {
         self.sample_format
     }
 
+    /// The byte order in which the device lays out samples.
+    ///
+    /// Stream callbacks always see samples in native byte order regardless of this value.
+    pub fn endianness(&self) -> Endianness {
+        self.endianness
+    }
+
+    /// The speaker position of each channel, if the host reports it.
+    pub fn channel_layout(&self) -> Option<&ChannelLayout> {
+        self.channel_layout.as_ref()
+    }
+
+    /// The sample rates supported between `min_sample_rate` and `max_sample_rate`.
+    pub fn sample_rates(&self) -> &SupportedSampleRates {
+        &self.sample_rates
+    }
+
+    /// Whether the given sample rate is supported by this `SupportedStreamConfigRange`.
+    pub fn supports_sample_rate(&self, sample_rate: SampleRate) -> bool {
+        match self.sample_rates {
+            SupportedSampleRates::Continuous => {
+                self.min_sample_rate <= sample_rate && sample_rate <= self.max_sample_rate
+            }
+            SupportedSampleRates::Discrete(ref rates) => rates.contains(&sample_rate),
+        }
+    }
+
+    /// The supported sample rate closest to the given one, preferring the higher of two equally
+    /// close rates.
+    pub fn nearest_sample_rate(&self, sample_rate: SampleRate) -> SampleRate {
+        match self.sample_rates {
+            SupportedSampleRates::Continuous => sample_rate
+                .max(self.min_sample_rate)
+                .min(self.max_sample_rate),
+            SupportedSampleRates::Discrete(ref rates) => rates
+                .iter()
+                .cloned()
+                .min_by_key(|rate| (rate.0.abs_diff(sample_rate.0), std::cmp::Reverse(rate.0)))
+                .unwrap_or(self.max_sample_rate),
+        }
+    }
+
     /// Retrieve a `SupportedStreamConfig` with the given sample rate and buffer size.
     ///
-    /// **panic!**s if the given `sample_rate` is outside the range specified within this
+    /// **panic!**s if the given `sample_rate` is not supported by this
     /// `SupportedStreamConfigRange` instance.
     pub fn with_sample_rate(self, sample_rate: SampleRate) -> SupportedStreamConfig {
-        assert!(self.min_sample_rate <= sample_rate && sample_rate <= self.max_sample_rate);
+        assert!(self.supports_sample_rate(sample_rate));
         SupportedStreamConfig {
             channels: self.channels,
             sample_rate,
             sample_format: self.sample_format,
             buffer_size: self.buffer_size,
+            endianness: self.endianness,
+            channel_layout: self.channel_layout,
+        }
+    }
+
+    /// Retrieve a `SupportedStreamConfig` with the given sample rate and buffer size.
+    ///
+    /// Returns `None` if the given `sample_rate` is not supported by this
+    /// `SupportedStreamConfigRange` instance.
+    pub fn try_with_sample_rate(self, sample_rate: SampleRate) -> Option<SupportedStreamConfig> {
+        if self.supports_sample_rate(sample_rate) {
+            Some(self.with_sample_rate(sample_rate))
+        } else {
+            None
         }
     }
 
@@ -581,6 +1170,8 @@ impl SupportedStreamConfigRange {
             sample_rate: self.max_sample_rate,
             sample_format: self.sample_format,
             buffer_size: self.buffer_size,
+            endianness: self.endianness,
+            channel_layout: self.channel_layout,
         }
     }
 
@@ -599,10 +1190,22 @@ impl SupportedStreamConfigRange {
     /// - Mono
     /// - Max available channels
     ///
+    /// **Endianness**:
+    ///
+    /// - Native byte order
+    ///
     /// **Sample format**:
+    ///
     /// - f32
+    /// - f64
     /// - i16
     /// - u16
+    /// - i32
+    /// - u32
+    /// - i24 (in 32 bits)
+    /// - i24 (packed)
+    /// - i8
+    /// - u8
     ///
     /// **Sample rate**:
     ///
@@ -610,7 +1213,7 @@ impl SupportedStreamConfigRange {
     /// - Max sample rate
     pub fn cmp_default_heuristics(&self, other: &Self) -> std::cmp::Ordering {
         use std::cmp::Ordering::Equal;
-        use SampleFormat::{F32, I16, U16};
+        use SampleFormat::{I24In32, F32, F64, I16, I24, I32, I8, U16, U32, U8};
 
         let cmp_stereo = (self.channels == 2).cmp(&(other.channels == 2));
         if cmp_stereo != Equal {
@@ -627,11 +1230,24 @@ impl SupportedStreamConfigRange {
             return cmp_channels;
         }
 
+        let cmp_native = self
+            .endianness
+            .is_native()
+            .cmp(&other.endianness.is_native());
+        if cmp_native != Equal {
+            return cmp_native;
+        }
+
         let cmp_f32 = (self.sample_format == F32).cmp(&(other.sample_format == F32));
         if cmp_f32 != Equal {
             return cmp_f32;
         }
 
+        let cmp_f64 = (self.sample_format == F64).cmp(&(other.sample_format == F64));
+        if cmp_f64 != Equal {
+            return cmp_f64;
+        }
+
         let cmp_i16 = (self.sample_format == I16).cmp(&(other.sample_format == I16));
         if cmp_i16 != Equal {
             return cmp_i16;
@@ -642,10 +1258,39 @@ impl SupportedStreamConfigRange {
             return cmp_u16;
         }
 
+        let cmp_i32 = (self.sample_format == I32).cmp(&(other.sample_format == I32));
+        if cmp_i32 != Equal {
+            return cmp_i32;
+        }
+
+        let cmp_u32 = (self.sample_format == U32).cmp(&(other.sample_format == U32));
+        if cmp_u32 != Equal {
+            return cmp_u32;
+        }
+
+        let cmp_i24_in_32 = (self.sample_format == I24In32).cmp(&(other.sample_format == I24In32));
+        if cmp_i24_in_32 != Equal {
+            return cmp_i24_in_32;
+        }
+
+        let cmp_i24 = (self.sample_format == I24).cmp(&(other.sample_format == I24));
+        if cmp_i24 != Equal {
+            return cmp_i24;
+        }
+
+        let cmp_i8 = (self.sample_format == I8).cmp(&(other.sample_format == I8));
+        if cmp_i8 != Equal {
+            return cmp_i8;
+        }
+
+        let cmp_u8 = (self.sample_format == U8).cmp(&(other.sample_format == U8));
+        if cmp_u8 != Equal {
+            return cmp_u8;
+        }
+
         const HZ_44100: SampleRate = SampleRate(44_100);
-        let r44100_in_self = self.min_sample_rate <= HZ_44100 && HZ_44100 <= self.max_sample_rate;
-        let r44100_in_other =
-            other.min_sample_rate <= HZ_44100 && HZ_44100 <= other.max_sample_rate;
+        let r44100_in_self = self.supports_sample_rate(HZ_44100);
+        let r44100_in_other = other.supports_sample_rate(HZ_44100);
         let cmp_r44100 = r44100_in_self.cmp(&r44100_in_other);
         if cmp_r44100 != Equal {
             return cmp_r44100;
@@ -657,41 +1302,174 @@ impl SupportedStreamConfigRange {
 
 #[test]
 fn test_cmp_default_heuristics() {
-    let mut formats = vec![
+    let mut formats = [
         SupportedStreamConfigRange {
-            buffer_size: SupportedBufferSize::Range { min: 256, max: 512 },
+            buffer_size: SupportedBufferSize::Range {
+                min: 256,
+                max: 512,
+                periods: None,
+            },
             channels: 2,
             min_sample_rate: SampleRate(1),
             max_sample_rate: SampleRate(96000),
+            sample_rates: SupportedSampleRates::Continuous,
             sample_format: SampleFormat::F32,
+            endianness: Endianness::NATIVE,
+            channel_layout: None,
         },
         SupportedStreamConfigRange {
-            buffer_size: SupportedBufferSize::Range { min: 256, max: 512 },
+            buffer_size: SupportedBufferSize::Range {
+                min: 256,
+                max: 512,
+                periods: None,
+            },
             channels: 1,
             min_sample_rate: SampleRate(1),
             max_sample_rate: SampleRate(96000),
+            sample_rates: SupportedSampleRates::Continuous,
             sample_format: SampleFormat::F32,
+            endianness: Endianness::NATIVE,
+            channel_layout: None,
         },
         SupportedStreamConfigRange {
-            buffer_size: SupportedBufferSize::Range { min: 256, max: 512 },
+            buffer_size: SupportedBufferSize::Range {
+                min: 256,
+                max: 512,
+                periods: None,
+            },
             channels: 2,
             min_sample_rate: SampleRate(1),
             max_sample_rate: SampleRate(96000),
+            sample_rates: SupportedSampleRates::Continuous,
             sample_format: SampleFormat::I16,
+            endianness: Endianness::NATIVE,
+            channel_layout: None,
         },
         SupportedStreamConfigRange {
-            buffer_size: SupportedBufferSize::Range { min: 256, max: 512 },
+            buffer_size: SupportedBufferSize::Range {
+                min: 256,
+                max: 512,
+                periods: None,
+            },
             channels: 2,
             min_sample_rate: SampleRate(1),
             max_sample_rate: SampleRate(96000),
+            sample_rates: SupportedSampleRates::Continuous,
             sample_format: SampleFormat::U16,
+            endianness: Endianness::NATIVE,
+            channel_layout: None,
         },
         SupportedStreamConfigRange {
-            buffer_size: SupportedBufferSize::Range { min: 256, max: 512 },
+            buffer_size: SupportedBufferSize::Range {
+                min: 256,
+                max: 512,
+                periods: None,
+            },
+            channels: 2,
+            min_sample_rate: SampleRate(1),
+            max_sample_rate: SampleRate(96000),
+            sample_rates: SupportedSampleRates::Continuous,
+            sample_format: SampleFormat::I32,
+            endianness: Endianness::NATIVE,
+            channel_layout: None,
+        },
+        SupportedStreamConfigRange {
+            buffer_size: SupportedBufferSize::Range {
+                min: 256,
+                max: 512,
+                periods: None,
+            },
+            channels: 2,
+            min_sample_rate: SampleRate(1),
+            max_sample_rate: SampleRate(96000),
+            sample_rates: SupportedSampleRates::Continuous,
+            sample_format: SampleFormat::U32,
+            endianness: Endianness::NATIVE,
+            channel_layout: None,
+        },
+        SupportedStreamConfigRange {
+            buffer_size: SupportedBufferSize::Range {
+                min: 256,
+                max: 512,
+                periods: None,
+            },
+            channels: 2,
+            min_sample_rate: SampleRate(1),
+            max_sample_rate: SampleRate(96000),
+            sample_rates: SupportedSampleRates::Continuous,
+            sample_format: SampleFormat::I24,
+            endianness: Endianness::NATIVE,
+            channel_layout: None,
+        },
+        SupportedStreamConfigRange {
+            buffer_size: SupportedBufferSize::Range {
+                min: 256,
+                max: 512,
+                periods: None,
+            },
+            channels: 2,
+            min_sample_rate: SampleRate(1),
+            max_sample_rate: SampleRate(96000),
+            sample_rates: SupportedSampleRates::Continuous,
+            sample_format: SampleFormat::I24In32,
+            endianness: Endianness::NATIVE,
+            channel_layout: None,
+        },
+        SupportedStreamConfigRange {
+            buffer_size: SupportedBufferSize::Range {
+                min: 256,
+                max: 512,
+                periods: None,
+            },
+            channels: 2,
+            min_sample_rate: SampleRate(1),
+            max_sample_rate: SampleRate(96000),
+            sample_rates: SupportedSampleRates::Continuous,
+            sample_format: SampleFormat::F64,
+            endianness: Endianness::NATIVE,
+            channel_layout: None,
+        },
+        SupportedStreamConfigRange {
+            buffer_size: SupportedBufferSize::Range {
+                min: 256,
+                max: 512,
+                periods: None,
+            },
+            channels: 2,
+            min_sample_rate: SampleRate(1),
+            max_sample_rate: SampleRate(96000),
+            sample_rates: SupportedSampleRates::Continuous,
+            sample_format: SampleFormat::I8,
+            endianness: Endianness::NATIVE,
+            channel_layout: None,
+        },
+        SupportedStreamConfigRange {
+            buffer_size: SupportedBufferSize::Range {
+                min: 256,
+                max: 512,
+                periods: None,
+            },
+            channels: 2,
+            min_sample_rate: SampleRate(1),
+            max_sample_rate: SampleRate(96000),
+            sample_rates: SupportedSampleRates::Continuous,
+            sample_format: SampleFormat::U8,
+            endianness: Endianness::NATIVE,
+            channel_layout: None,
+        },
+        SupportedStreamConfigRange {
+            buffer_size: SupportedBufferSize::Range {
+                min: 256,
+                max: 512,
+                periods: None,
+            },
             channels: 2,
             min_sample_rate: SampleRate(1),
             max_sample_rate: SampleRate(22050),
+            sample_rates: SupportedSampleRates::Continuous,
             sample_format: SampleFormat::F32,
+            endianness: Endianness::NATIVE,
+            channel_layout: None,
         },
     ];
 
@@ -703,25 +1481,93 @@ fn test_cmp_default_heuristics() {
     assert_eq!(formats[0].max_sample_rate(), SampleRate(96000));
     assert_eq!(formats[0].channels(), 1);
 
-    assert_eq!(formats[1].sample_format(), SampleFormat::U16);
+    assert_eq!(formats[1].sample_format(), SampleFormat::U8);
     assert_eq!(formats[1].min_sample_rate(), SampleRate(1));
     assert_eq!(formats[1].max_sample_rate(), SampleRate(96000));
     assert_eq!(formats[1].channels(), 2);
 
-    assert_eq!(formats[2].sample_format(), SampleFormat::I16);
+    assert_eq!(formats[2].sample_format(), SampleFormat::I8);
     assert_eq!(formats[2].min_sample_rate(), SampleRate(1));
     assert_eq!(formats[2].max_sample_rate(), SampleRate(96000));
     assert_eq!(formats[2].channels(), 2);
 
-    assert_eq!(formats[3].sample_format(), SampleFormat::F32);
+    assert_eq!(formats[3].sample_format(), SampleFormat::I24);
     assert_eq!(formats[3].min_sample_rate(), SampleRate(1));
-    assert_eq!(formats[3].max_sample_rate(), SampleRate(22050));
+    assert_eq!(formats[3].max_sample_rate(), SampleRate(96000));
     assert_eq!(formats[3].channels(), 2);
 
-    assert_eq!(formats[4].sample_format(), SampleFormat::F32);
+    assert_eq!(formats[4].sample_format(), SampleFormat::I24In32);
     assert_eq!(formats[4].min_sample_rate(), SampleRate(1));
     assert_eq!(formats[4].max_sample_rate(), SampleRate(96000));
     assert_eq!(formats[4].channels(), 2);
+
+    assert_eq!(formats[5].sample_format(), SampleFormat::U32);
+    assert_eq!(formats[5].min_sample_rate(), SampleRate(1));
+    assert_eq!(formats[5].max_sample_rate(), SampleRate(96000));
+    assert_eq!(formats[5].channels(), 2);
+
+    assert_eq!(formats[6].sample_format(), SampleFormat::I32);
+    assert_eq!(formats[6].min_sample_rate(), SampleRate(1));
+    assert_eq!(formats[6].max_sample_rate(), SampleRate(96000));
+    assert_eq!(formats[6].channels(), 2);
+
+    assert_eq!(formats[7].sample_format(), SampleFormat::U16);
+    assert_eq!(formats[7].min_sample_rate(), SampleRate(1));
+    assert_eq!(formats[7].max_sample_rate(), SampleRate(96000));
+    assert_eq!(formats[7].channels(), 2);
+
+    assert_eq!(formats[8].sample_format(), SampleFormat::I16);
+    assert_eq!(formats[8].min_sample_rate(), SampleRate(1));
+    assert_eq!(formats[8].max_sample_rate(), SampleRate(96000));
+    assert_eq!(formats[8].channels(), 2);
+
+    assert_eq!(formats[9].sample_format(), SampleFormat::F64);
+    assert_eq!(formats[9].min_sample_rate(), SampleRate(1));
+    assert_eq!(formats[9].max_sample_rate(), SampleRate(96000));
+    assert_eq!(formats[9].channels(), 2);
+
+    assert_eq!(formats[10].sample_format(), SampleFormat::F32);
+    assert_eq!(formats[10].min_sample_rate(), SampleRate(1));
+    assert_eq!(formats[10].max_sample_rate(), SampleRate(22050));
+    assert_eq!(formats[10].channels(), 2);
+
+    assert_eq!(formats[11].sample_format(), SampleFormat::F32);
+    assert_eq!(formats[11].min_sample_rate(), SampleRate(1));
+    assert_eq!(formats[11].max_sample_rate(), SampleRate(96000));
+    assert_eq!(formats[11].channels(), 2);
+}
+
+#[test]
+fn test_cmp_default_heuristics_prefers_native_endianness() {
+    let native = SupportedStreamConfigRange {
+        buffer_size: SupportedBufferSize::Range {
+            min: 256,
+            max: 512,
+            periods: None,
+        },
+        channels: 2,
+        min_sample_rate: SampleRate(1),
+        max_sample_rate: SampleRate(96000),
+        sample_rates: SupportedSampleRates::Continuous,
+        sample_format: SampleFormat::U8,
+        endianness: Endianness::NATIVE,
+        channel_layout: None,
+    };
+    let swapped = SupportedStreamConfigRange {
+        sample_format: SampleFormat::F32,
+        endianness: Endianness::NATIVE.swapped(),
+        channel_layout: None,
+        ..native.clone()
+    };
+
+    assert_eq!(
+        native.cmp_default_heuristics(&swapped),
+        std::cmp::Ordering::Greater
+    );
+    assert_eq!(
+        swapped.with_max_sample_rate().endianness(),
+        Endianness::NATIVE.swapped()
+    );
 }
 
 impl From<SupportedStreamConfig> for StreamConfig {
@@ -751,6 +1597,222 @@ const COMMON_SAMPLE_RATES: &'static [SampleRate] = &[
     SampleRate(192000),
 ];
 
+#[test]
+fn test_discrete_sample_rates() {
+    let range = SupportedStreamConfigRange {
+        channels: 2,
+        min_sample_rate: SampleRate(44100),
+        max_sample_rate: SampleRate(96000),
+        sample_rates: SupportedSampleRates::Discrete(vec![
+            SampleRate(44100),
+            SampleRate(48000),
+            SampleRate(96000),
+        ]),
+        buffer_size: SupportedBufferSize::Unknown,
+        sample_format: SampleFormat::F32,
+        endianness: Endianness::NATIVE,
+        channel_layout: None,
+    };
+
+    assert!(range.supports_sample_rate(SampleRate(48000)));
+    assert!(!range.supports_sample_rate(SampleRate(46000)));
+    assert_eq!(
+        range.nearest_sample_rate(SampleRate(46000)),
+        SampleRate(44100)
+    );
+    assert_eq!(
+        range.nearest_sample_rate(SampleRate(72000)),
+        SampleRate(96000)
+    );
+    assert_eq!(
+        range.nearest_sample_rate(SampleRate(8000)),
+        SampleRate(44100)
+    );
+    assert!(range
+        .clone()
+        .try_with_sample_rate(SampleRate(46000))
+        .is_none());
+    assert_eq!(
+        range.with_sample_rate(SampleRate(48000)).sample_rate(),
+        SampleRate(48000)
+    );
+}
+
+#[test]
+#[should_panic]
+fn test_with_sample_rate_between_discrete_rates() {
+    let range = SupportedStreamConfigRange {
+        channels: 2,
+        min_sample_rate: SampleRate(44100),
+        max_sample_rate: SampleRate(48000),
+        sample_rates: SupportedSampleRates::Discrete(vec![SampleRate(44100), SampleRate(48000)]),
+        buffer_size: SupportedBufferSize::Unknown,
+        sample_format: SampleFormat::F32,
+        endianness: Endianness::NATIVE,
+        channel_layout: None,
+    };
+    range.with_sample_rate(SampleRate(46000));
+}
+
+#[test]
+fn test_negotiated_config_mismatch() {
+    let negotiated = NegotiatedStreamConfig {
+        channels: 2,
+        sample_rate: SampleRate(48000),
+        sample_format: SampleFormat::F32,
+        period_size: Some(240),
+        buffer_size: Some(960),
+    };
+    let config = |buffer_size| StreamConfig {
+        channels: 2,
+        sample_rate: SampleRate(48000),
+        buffer_size,
+        channel_layout: None,
+        strict: true,
+    };
+
+    assert_eq!(negotiated.mismatch(&config(BufferSize::Default)), None);
+    assert_eq!(negotiated.mismatch(&config(BufferSize::Fixed(960))), None);
+    assert_eq!(
+        negotiated.mismatch(&config(BufferSize::Fixed(1024))),
+        Some(RejectedParameter::BufferSize {
+            requested: BufferSize::Fixed(1024),
+            supported: SupportedBufferSize::Range {
+                min: 960,
+                max: 960,
+                periods: None,
+            },
+        })
+    );
+    assert_eq!(
+        negotiated.mismatch(&config(BufferSize::Periods {
+            period_size: 240,
+            periods: 4,
+        })),
+        None
+    );
+    assert!(negotiated
+        .mismatch(&config(BufferSize::Periods {
+            period_size: 480,
+            periods: 2,
+        }))
+        .is_some());
+    assert_eq!(
+        negotiated.mismatch(&config(BufferSize::Latency(Duration::from_millis(20)))),
+        None
+    );
+    // 960 frames are within a period of the 1000 that 20.833ms take.
+    assert_eq!(
+        negotiated.mismatch(&config(BufferSize::Latency(Duration::from_micros(20_833)))),
+        None
+    );
+    assert!(negotiated
+        .mismatch(&config(BufferSize::Latency(Duration::from_millis(30))))
+        .is_some());
+    assert!(negotiated
+        .mismatch(&config(BufferSize::Periods {
+            period_size: u32::MAX,
+            periods: 2,
+        }))
+        .is_some());
+    let rejected = negotiated
+        .mismatch(&StreamConfig {
+            sample_rate: SampleRate(44100),
+            ..config(BufferSize::Default)
+        })
+        .unwrap();
+    assert_eq!(
+        rejected.to_string(),
+        "sample rate of 44100 Hz (supported: 48000 to 48000 Hz)"
+    );
+    let rejected = RejectedParameter::SampleRate {
+        requested: SampleRate(44000),
+        min: SampleRate(44100),
+        max: SampleRate(48000),
+        rates: SupportedSampleRates::Discrete(vec![SampleRate(44100), SampleRate(48000)]),
+    };
+    assert_eq!(
+        rejected.to_string(),
+        "sample rate of 44000 Hz (supported: 44100, 48000 Hz)"
+    );
+}
+
+#[test]
+fn test_data_planar() {
+    let mut left = [0.5f32, 0.25];
+    let mut right = [-0.5f32, -0.25];
+    let planes = [left.as_mut_ptr() as *mut (), right.as_mut_ptr() as *mut ()];
+    let mut data = unsafe { Data::from_planar_parts(planes.as_ptr(), 2, 2, SampleFormat::F32) };
+    assert_eq!(data.layout(), DataLayout::Planar);
+    assert_eq!(data.len(), 4);
+    assert_eq!(data.plane_count(), 2);
+    assert_eq!(data.as_slice::<f32>(), None);
+    assert_eq!(data.plane::<f32>(1), Some(&[-0.5, -0.25][..]));
+    assert_eq!(data.plane::<f32>(2), None);
+    assert_eq!(data.plane::<i16>(0), None);
+    assert_eq!(data.plane_bytes(0).map(|b| b.len()), Some(8));
+    for plane in data.planes_mut::<f32>().unwrap() {
+        plane[0] = 0.0;
+    }
+    let firsts: Vec<f32> = data.planes::<f32>().unwrap().map(|p| p[0]).collect();
+    assert_eq!(firsts, [0.0, 0.0]);
+    assert_eq!(left, [0.0, 0.25]);
+}
+
+#[test]
+fn test_data_as_slice_i32() {
+    let mut buffer = [i32::MIN, -1, 0, 1, i32::MAX];
+    let data = unsafe {
+        Data::from_parts(
+            buffer.as_mut_ptr() as *mut (),
+            buffer.len(),
+            1,
+            SampleFormat::I32,
+        )
+    };
+    assert_eq!(data.as_slice::<i32>(), Some(&buffer[..]));
+    assert_eq!(data.as_slice::<u32>(), None);
+    assert_eq!(data.as_slice::<i16>(), None);
+    assert_eq!(data.bytes().len(), buffer.len() * 4);
+}
+
+#[test]
+fn test_data_frames() {
+    let mut buffer = [1i16, -1, 2, -2, 3, -3];
+    let mut data = unsafe {
+        Data::from_parts(
+            buffer.as_mut_ptr() as *mut (),
+            buffer.len(),
+            2,
+            SampleFormat::I16,
+        )
+    };
+    assert_eq!(data.channels(), 2);
+    assert_eq!(data.frame_count(), 3);
+    let frames: Vec<&[i16]> = data.frames().unwrap().collect();
+    assert_eq!(frames, [&[1, -1][..], &[2, -2], &[3, -3]]);
+    assert!(data.frames::<f32>().is_none());
+    assert_eq!(
+        data.as_frames::<i16, 2>(),
+        Some(&[[1, -1], [2, -2], [3, -3]][..])
+    );
+    assert_eq!(data.as_frames::<i16, 3>(), None);
+    for frame in data.frames_mut::<i16>().unwrap() {
+        frame.swap(0, 1);
+    }
+    data.as_frames_mut::<i16, 2>().unwrap()[0] = [0, 0];
+    assert_eq!(buffer, [0, 0, -2, 2, -3, 3]);
+
+    let data = unsafe { Data::from_parts(buffer.as_mut_ptr() as *mut (), 0, 0, SampleFormat::I16) };
+    assert_eq!(data.frame_count(), 0);
+    assert_eq!(data.frames::<i16>().unwrap().count(), 0);
+    assert_eq!(data.as_frames::<i16, 0>(), Some(&[][..]));
+    let mut planes = [];
+    let data = unsafe { Data::from_planar_parts(planes.as_mut_ptr(), 0, 0, SampleFormat::I16) };
+    assert_eq!(data.frame_count(), 0);
+    assert_eq!(data.plane_bytes(0), None);
+}
+
 #[test]
 fn test_stream_instant() {
     let a = StreamInstant::new(2, 0);
@@ -784,3 +1846,58 @@ fn test_stream_instant() {
     );
     assert_eq!(max.add(Duration::from_secs(1)), None);
 }
+
+#[test]
+fn test_host_id_display_from_str() {
+    for host_id in ALL_HOSTS {
+        assert_eq!(host_id.to_string().parse(), Ok(*host_id));
+        assert_eq!(host_id.name().to_uppercase().parse(), Ok(*host_id));
+    }
+    assert!("not a host".parse::<HostId>().is_err());
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn test_serde_round_trip() {
+    extern crate serde_json;
+
+    let config = StreamConfig {
+        channels: 2,
+        sample_rate: SampleRate(48_000),
+        buffer_size: BufferSize::Fixed(256),
+        channel_layout: Some(ChannelLayout::surround_5_1()),
+        strict: true,
+    };
+    let json = serde_json::to_string(&config).unwrap();
+    assert_eq!(serde_json::from_str::<StreamConfig>(&json).unwrap(), config);
+
+    let range = SupportedStreamConfigRange {
+        channels: 2,
+        min_sample_rate: SampleRate(8_000),
+        max_sample_rate: SampleRate(96_000),
+        sample_rates: SupportedSampleRates::Continuous,
+        buffer_size: SupportedBufferSize::Range {
+            min: 64,
+            max: 4096,
+            periods: None,
+        },
+        sample_format: SampleFormat::I24,
+        endianness: Endianness::Big,
+        channel_layout: Some(ChannelLayout::stereo()),
+    };
+    let json = serde_json::to_string(&range).unwrap();
+    let parsed: SupportedStreamConfigRange = serde_json::from_str(&json).unwrap();
+    assert_eq!(parsed, range);
+
+    let supported = range.with_max_sample_rate();
+    let json = serde_json::to_string(&supported).unwrap();
+    assert_eq!(
+        serde_json::from_str::<SupportedStreamConfig>(&json).unwrap(),
+        supported
+    );
+
+    for host_id in ALL_HOSTS {
+        let json = serde_json::to_string(host_id).unwrap();
+        assert_eq!(serde_json::from_str::<HostId>(&json).unwrap(), *host_id);
+    }
+}
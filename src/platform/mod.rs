@@ -77,6 +77,7 @@ macro_rules! impl_platform_host {
 
         /// Unique identifier for available hosts on the platform.
         #[derive(Copy, Clone, Debug, Eq, Hash, PartialEq)]
+        #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
         pub enum HostId {
             $(
                 $HostVariant,
@@ -129,6 +130,27 @@ macro_rules! impl_platform_host {
             }
         }
 
+        impl std::fmt::Display for HostId {
+            fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+                f.write_str(self.name())
+            }
+        }
+
+        /// Parses the name of a host on this platform, as returned by `HostId::name`, ignoring
+        /// ASCII case.
+        impl std::str::FromStr for HostId {
+            type Err = crate::ParseHostIdError;
+
+            fn from_str(s: &str) -> Result<Self, Self::Err> {
+                $(
+                    if s.eq_ignore_ascii_case($host_name) {
+                        return Ok(HostId::$HostVariant);
+                    }
+                )*
+                Err(crate::ParseHostIdError { input: s.to_string() })
+            }
+        }
+
         impl Host {
             /// The unique identifier associated with this host.
             pub fn id(&self) -> HostId {
@@ -255,6 +277,30 @@ macro_rules! impl_platform_host {
                 }
             }
 
+            fn check_input_config(
+                &self,
+                config: &crate::StreamConfig,
+                sample_format: crate::SampleFormat,
+            ) -> Result<(), crate::CheckStreamConfigError> {
+                match self.0 {
+                    $(
+                        DeviceInner::$HostVariant(ref d) => d.check_input_config(config, sample_format),
+                    )*
+                }
+            }
+
+            fn check_output_config(
+                &self,
+                config: &crate::StreamConfig,
+                sample_format: crate::SampleFormat,
+            ) -> Result<(), crate::CheckStreamConfigError> {
+                match self.0 {
+                    $(
+                        DeviceInner::$HostVariant(ref d) => d.check_output_config(config, sample_format),
+                    )*
+                }
+            }
+
             fn build_input_stream_raw<D, E>(
                 &self,
                 config: &crate::StreamConfig,
@@ -306,6 +352,148 @@ macro_rules! impl_platform_host {
                     )*
                 }
             }
+
+            fn build_duplex_stream_raw<D, E>(
+                &self,
+                config: &crate::DuplexStreamConfig,
+                sample_format: crate::SampleFormat,
+                data_callback: D,
+                error_callback: E,
+            ) -> Result<Self::Stream, crate::BuildStreamError>
+            where
+                D: FnMut(&crate::Data, &mut crate::Data, &crate::DuplexCallbackInfo)
+                    + Send
+                    + 'static,
+                E: FnMut(crate::StreamError) + Send + 'static,
+            {
+                match self.0 {
+                    $(
+                        DeviceInner::$HostVariant(ref d) => d
+                            .build_duplex_stream_raw(
+                                config,
+                                sample_format,
+                                data_callback,
+                                error_callback,
+                            )
+                            .map(StreamInner::$HostVariant)
+                            .map(Stream::from),
+                    )*
+                }
+            }
+
+            fn build_input_stream_flow_raw<D, E, C>(
+                &self,
+                config: &crate::StreamConfig,
+                sample_format: crate::SampleFormat,
+                data_callback: D,
+                error_callback: E,
+                completion_callback: C,
+            ) -> Result<Self::Stream, crate::BuildStreamError>
+            where
+                D: FnMut(&crate::Data, &crate::InputCallbackInfo) -> crate::CallbackFlow
+                    + Send
+                    + 'static,
+                E: FnMut(crate::StreamError) + Send + 'static,
+                C: FnOnce() + Send + 'static,
+            {
+                match self.0 {
+                    $(
+                        DeviceInner::$HostVariant(ref d) => d
+                            .build_input_stream_flow_raw(
+                                config,
+                                sample_format,
+                                data_callback,
+                                error_callback,
+                                completion_callback,
+                            )
+                            .map(StreamInner::$HostVariant)
+                            .map(Stream::from),
+                    )*
+                }
+            }
+
+            fn build_output_stream_flow_raw<D, E, C>(
+                &self,
+                config: &crate::StreamConfig,
+                sample_format: crate::SampleFormat,
+                data_callback: D,
+                error_callback: E,
+                completion_callback: C,
+            ) -> Result<Self::Stream, crate::BuildStreamError>
+            where
+                D: FnMut(&mut crate::Data, &crate::OutputCallbackInfo) -> crate::CallbackFlow
+                    + Send
+                    + 'static,
+                E: FnMut(crate::StreamError) + Send + 'static,
+                C: FnOnce() + Send + 'static,
+            {
+                match self.0 {
+                    $(
+                        DeviceInner::$HostVariant(ref d) => d
+                            .build_output_stream_flow_raw(
+                                config,
+                                sample_format,
+                                data_callback,
+                                error_callback,
+                                completion_callback,
+                            )
+                            .map(StreamInner::$HostVariant)
+                            .map(Stream::from),
+                    )*
+                }
+            }
+
+            fn build_input_stream_planar_raw<D, E>(
+                &self,
+                config: &crate::StreamConfig,
+                sample_format: crate::SampleFormat,
+                data_callback: D,
+                error_callback: E,
+            ) -> Result<Self::Stream, crate::BuildStreamError>
+            where
+                D: FnMut(&crate::Data, &crate::InputCallbackInfo) + Send + 'static,
+                E: FnMut(crate::StreamError) + Send + 'static,
+            {
+                match self.0 {
+                    $(
+                        DeviceInner::$HostVariant(ref d) => d
+                            .build_input_stream_planar_raw(
+                                config,
+                                sample_format,
+                                data_callback,
+                                error_callback,
+                            )
+                            .map(StreamInner::$HostVariant)
+                            .map(Stream::from),
+                    )*
+                }
+            }
+
+            fn build_output_stream_planar_raw<D, E>(
+                &self,
+                config: &crate::StreamConfig,
+                sample_format: crate::SampleFormat,
+                data_callback: D,
+                error_callback: E,
+            ) -> Result<Self::Stream, crate::BuildStreamError>
+            where
+                D: FnMut(&mut crate::Data, &crate::OutputCallbackInfo) + Send + 'static,
+                E: FnMut(crate::StreamError) + Send + 'static,
+            {
+                match self.0 {
+                    $(
+                        DeviceInner::$HostVariant(ref d) => d
+                            .build_output_stream_planar_raw(
+                                config,
+                                sample_format,
+                                data_callback,
+                                error_callback,
+                            )
+                            .map(StreamInner::$HostVariant)
+                            .map(Stream::from),
+                    )*
+                }
+            }
         }
 
         impl crate::traits::HostTrait for Host {
@@ -367,6 +555,46 @@ macro_rules! impl_platform_host {
                     )*
                 }
             }
+
+            fn buffer_latency(&self) -> Option<std::time::Duration> {
+                match self.0 {
+                    $(
+                        StreamInner::$HostVariant(ref s) => {
+                            s.buffer_latency()
+                        }
+                    )*
+                }
+            }
+
+            fn negotiated_config(&self) -> Option<crate::NegotiatedStreamConfig> {
+                match self.0 {
+                    $(
+                        StreamInner::$HostVariant(ref s) => {
+                            s.negotiated_config()
+                        }
+                    )*
+                }
+            }
+
+            fn input_latency(&self) -> Option<std::time::Duration> {
+                match self.0 {
+                    $(
+                        StreamInner::$HostVariant(ref s) => {
+                            s.input_latency()
+                        }
+                    )*
+                }
+            }
+
+            fn output_latency(&self) -> Option<std::time::Duration> {
+                match self.0 {
+                    $(
+                        StreamInner::$HostVariant(ref s) => {
+                            s.output_latency()
+                        }
+                    )*
+                }
+            }
         }
 
         impl From<DeviceInner> for Device {
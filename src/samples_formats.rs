@@ -1,14 +1,32 @@
-use std::mem;
+use std::{fmt, mem, str};
+
+use ParseSampleFormatError;
 
 /// Format that each sample has.
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum SampleFormat {
+    /// The value 0 corresponds to 0.
+    I8,
+    /// The value 0 corresponds to 128.
+    U8,
     /// The value 0 corresponds to 0.
     I16,
     /// The value 0 corresponds to 32768.
     U16,
+    /// 24-bit samples packed into 3 bytes. The value 0 corresponds to 0.
+    I24,
+    /// 24-bit samples stored in the least significant bits of 4 bytes. The value 0 corresponds
+    /// to 0.
+    I24In32,
+    /// The value 0 corresponds to 0.
+    I32,
+    /// The value 0 corresponds to 2147483648.
+    U32,
     /// The boundaries are (-1.0, 1.0).
     F32,
+    /// The boundaries are (-1.0, 1.0).
+    F64,
 }
 
 impl SampleFormat {
@@ -16,46 +34,691 @@ impl SampleFormat {
     #[inline]
     pub fn sample_size(&self) -> usize {
         match *self {
+            SampleFormat::I8 => mem::size_of::<i8>(),
+            SampleFormat::U8 => mem::size_of::<u8>(),
             SampleFormat::I16 => mem::size_of::<i16>(),
             SampleFormat::U16 => mem::size_of::<u16>(),
+            SampleFormat::I24 => mem::size_of::<I24>(),
+            SampleFormat::I24In32 => mem::size_of::<I24In32>(),
+            SampleFormat::I32 => mem::size_of::<i32>(),
+            SampleFormat::U32 => mem::size_of::<u32>(),
             SampleFormat::F32 => mem::size_of::<f32>(),
+            SampleFormat::F64 => mem::size_of::<f64>(),
+        }
+    }
+}
+
+impl fmt::Display for SampleFormat {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match *self {
+            SampleFormat::I8 => "i8",
+            SampleFormat::U8 => "u8",
+            SampleFormat::I16 => "i16",
+            SampleFormat::U16 => "u16",
+            SampleFormat::I24 => "i24",
+            SampleFormat::I24In32 => "i24in32",
+            SampleFormat::I32 => "i32",
+            SampleFormat::U32 => "u32",
+            SampleFormat::F32 => "f32",
+            SampleFormat::F64 => "f64",
+        };
+        f.write_str(name)
+    }
+}
+
+/// Parses the names produced by the `Display` implementation, such as `"i16"` or `"f32"`,
+/// ignoring ASCII case.
+impl str::FromStr for SampleFormat {
+    type Err = ParseSampleFormatError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match &*s.to_ascii_lowercase() {
+            "i8" => Ok(SampleFormat::I8),
+            "u8" => Ok(SampleFormat::U8),
+            "i16" => Ok(SampleFormat::I16),
+            "u16" => Ok(SampleFormat::U16),
+            "i24" => Ok(SampleFormat::I24),
+            "i24in32" => Ok(SampleFormat::I24In32),
+            "i32" => Ok(SampleFormat::I32),
+            "u32" => Ok(SampleFormat::U32),
+            "f32" => Ok(SampleFormat::F32),
+            "f64" => Ok(SampleFormat::F64),
+            _ => Err(ParseSampleFormatError {
+                input: s.to_string(),
+            }),
+        }
+    }
+}
+
+/// Byte order in which a device lays out multi-byte samples.
+///
+/// Samples handed to and from stream callbacks are always in native byte order. When a device
+/// only supports the opposite byte order, hosts that support it swap the bytes of every sample
+/// transparently.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Endianness {
+    /// The least significant byte comes first.
+    Little,
+    /// The most significant byte comes first.
+    Big,
+}
+
+impl Endianness {
+    /// The byte order of the target platform.
+    #[cfg(target_endian = "little")]
+    pub const NATIVE: Endianness = Endianness::Little;
+    /// The byte order of the target platform.
+    #[cfg(target_endian = "big")]
+    pub const NATIVE: Endianness = Endianness::Big;
+
+    /// Returns `true` if this is the byte order of the target platform.
+    #[inline]
+    pub fn is_native(&self) -> bool {
+        *self == Endianness::NATIVE
+    }
+
+    /// Returns the opposite byte order.
+    #[inline]
+    pub fn swapped(&self) -> Endianness {
+        match *self {
+            Endianness::Little => Endianness::Big,
+            Endianness::Big => Endianness::Little,
+        }
+    }
+}
+
+/// A signed 24-bit sample packed into 3 bytes in native byte order.
+///
+/// This is the in-memory representation of `SampleFormat::I24`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+#[repr(C)]
+pub struct I24([u8; 3]);
+
+/// A signed 24-bit sample stored in the least significant bits of a native-endian 32-bit word.
+///
+/// This is the in-memory representation of `SampleFormat::I24In32`. The most significant byte is
+/// ignored when reading the sample, as devices are free to leave it unset.
+#[derive(Clone, Copy, Debug, Default)]
+#[repr(transparent)]
+pub struct I24In32(i32);
+
+impl I24 {
+    /// The minimum value of a 24-bit sample.
+    pub const MIN: i32 = -(1 << 23);
+    /// The maximum value of a 24-bit sample.
+    pub const MAX: i32 = (1 << 23) - 1;
+
+    /// Creates a sample from the given value.
+    ///
+    /// Returns `None` if `value` is outside of the range `I24::MIN..=I24::MAX`.
+    pub fn new(value: i32) -> Option<Self> {
+        if (I24::MIN..=I24::MAX).contains(&value) {
+            Some(Self::new_wrapping(value))
+        } else {
+            None
+        }
+    }
+
+    /// Creates a sample from the lowest 24 bits of the given value.
+    #[inline]
+    fn new_wrapping(value: i32) -> Self {
+        let [a, b, c, d] = value.to_ne_bytes();
+        if cfg!(target_endian = "big") {
+            I24([b, c, d])
+        } else {
+            I24([a, b, c])
+        }
+    }
+
+    /// The value of the sample within the range `I24::MIN..=I24::MAX`.
+    #[inline]
+    pub fn value(&self) -> i32 {
+        let [a, b, c] = self.0;
+        if cfg!(target_endian = "big") {
+            i32::from_ne_bytes([a, b, c, 0]) >> 8
+        } else {
+            i32::from_ne_bytes([0, a, b, c]) >> 8
+        }
+    }
+}
+
+impl I24In32 {
+    /// Creates a sample from the given value.
+    ///
+    /// Returns `None` if `value` is outside of the range `I24::MIN..=I24::MAX`.
+    pub fn new(value: i32) -> Option<Self> {
+        if (I24::MIN..=I24::MAX).contains(&value) {
+            Some(I24In32(value))
+        } else {
+            None
+        }
+    }
+
+    /// The value of the sample within the range `I24::MIN..=I24::MAX`.
+    #[inline]
+    pub fn value(&self) -> i32 {
+        (self.0 << 8) >> 8
+    }
+}
+
+impl PartialEq for I24In32 {
+    fn eq(&self, other: &Self) -> bool {
+        self.value() == other.value()
+    }
+}
+
+impl Eq for I24In32 {}
+
+/// Trait for containers that contain PCM data.
+///
+/// # Safety
+///
+/// `FORMAT` is used to reinterpret the raw buffers held by `Data` as slices of `Self`, so
+/// implementors must ensure that it describes the exact in-memory representation of the type.
+pub unsafe trait Sample: Copy + Clone {
+    /// The signed sample type able to represent the amplitude of this one relative to
+    /// `EQUILIBRIUM`. This is `Self` for signed and floating-point formats.
+    type Signed: Sample;
+    /// The floating-point type used to scale samples of this type without losing precision.
+    type Float: Sample;
+
+    /// The `SampleFormat` corresponding to this data type.
+    const FORMAT: SampleFormat;
+    /// The value of a silent sample, around which the signal oscillates.
+    const EQUILIBRIUM: Self;
+
+    /// Turns the sample into its equivalent as a floating-point.
+    fn to_f32(&self) -> f32;
+    /// Converts this sample into a standard i16 sample.
+    fn to_i16(&self) -> i16;
+    /// Converts this sample into a standard u16 sample.
+    fn to_u16(&self) -> u16;
+    /// Converts this sample into a standard i32 sample.
+    fn to_i32(&self) -> i32;
+    /// Turns the sample into its equivalent as a double precision floating-point.
+    fn to_f64(&self) -> f64;
+
+    /// Adds the signed amplitude `amp` to this sample, saturating at the bounds of integer
+    /// formats. Floating-point samples are not clipped.
+    fn saturating_add_amp(&self, amp: Self::Signed) -> Self;
+    /// Scales the amplitude of this sample relative to `EQUILIBRIUM` by `amp`, saturating at the
+    /// bounds of integer formats. Floating-point samples are not clipped.
+    fn saturating_mul_amp(&self, amp: Self::Float) -> Self;
+    /// Restricts this sample to the range `min..=max`.
+    fn clamp_between(&self, min: Self, max: Self) -> Self;
+    /// Restricts this sample to the full-scale range of its format.
+    ///
+    /// Floating-point samples are clamped to `-1.0..=1.0`, with NaN mapped to `EQUILIBRIUM`.
+    /// Integer samples always lie within full scale, apart from the unused most significant byte
+    /// of `I24In32` which is normalised.
+    fn clamp_to_full_scale(&self) -> Self;
+
+    /// Converts any sample type to this one by calling `to_i16`, `to_u16`, `to_i32`, `to_f32` or
+    /// `to_f64`.
+    fn from<S>(s: &S) -> Self
+    where
+        S: Sample;
+}
+
+unsafe impl Sample for u8 {
+    type Signed = i8;
+    type Float = f32;
+
+    const FORMAT: SampleFormat = SampleFormat::U8;
+    const EQUILIBRIUM: Self = 128;
+
+    #[inline]
+    fn to_f32(&self) -> f32 {
+        (*self as i8).wrapping_add(i8::MIN).to_f32()
+    }
+
+    #[inline]
+    fn to_i16(&self) -> i16 {
+        ((*self as i8).wrapping_add(i8::MIN) as i16) << 8
+    }
+
+    #[inline]
+    fn to_u16(&self) -> u16 {
+        (*self as u16) << 8
+    }
+
+    #[inline]
+    fn to_i32(&self) -> i32 {
+        (self.to_i16() as i32) << 16
+    }
+
+    #[inline]
+    fn to_f64(&self) -> f64 {
+        (*self as i8).wrapping_add(i8::MIN).to_f64()
+    }
+
+    #[inline]
+    fn saturating_add_amp(&self, amp: i8) -> Self {
+        let signed = (*self as i8).wrapping_add(i8::MIN);
+        signed.saturating_add(amp).wrapping_add(i8::MIN) as u8
+    }
+
+    #[inline]
+    fn saturating_mul_amp(&self, amp: f32) -> Self {
+        let signed = (*self as i8).wrapping_add(i8::MIN);
+        ((signed as f32 * amp) as i8).wrapping_add(i8::MIN) as u8
+    }
+
+    #[inline]
+    fn clamp_between(&self, min: Self, max: Self) -> Self {
+        clamp_partial(*self, min, max)
+    }
+
+    #[inline]
+    fn clamp_to_full_scale(&self) -> Self {
+        *self
+    }
+
+    #[inline]
+    fn from<S>(sample: &S) -> Self
+    where
+        S: Sample,
+    {
+        (sample.to_u16() >> 8) as u8
+    }
+}
+
+unsafe impl Sample for i8 {
+    type Signed = i8;
+    type Float = f32;
+
+    const FORMAT: SampleFormat = SampleFormat::I8;
+    const EQUILIBRIUM: Self = 0;
+
+    #[inline]
+    fn to_f32(&self) -> f32 {
+        if *self < 0 {
+            *self as f32 / -(i8::MIN as f32)
+        } else {
+            *self as f32 / i8::MAX as f32
+        }
+    }
+
+    #[inline]
+    fn to_i16(&self) -> i16 {
+        (*self as i16) << 8
+    }
+
+    #[inline]
+    fn to_u16(&self) -> u16 {
+        self.to_i16().wrapping_add(i16::MIN) as u16
+    }
+
+    #[inline]
+    fn to_i32(&self) -> i32 {
+        (self.to_i16() as i32) << 16
+    }
+
+    #[inline]
+    fn to_f64(&self) -> f64 {
+        if *self < 0 {
+            *self as f64 / -(i8::MIN as f64)
+        } else {
+            *self as f64 / i8::MAX as f64
+        }
+    }
+
+    #[inline]
+    fn saturating_add_amp(&self, amp: i8) -> Self {
+        self.saturating_add(amp)
+    }
+
+    #[inline]
+    fn saturating_mul_amp(&self, amp: f32) -> Self {
+        (*self as f32 * amp) as i8
+    }
+
+    #[inline]
+    fn clamp_between(&self, min: Self, max: Self) -> Self {
+        clamp_partial(*self, min, max)
+    }
+
+    #[inline]
+    fn clamp_to_full_scale(&self) -> Self {
+        *self
+    }
+
+    #[inline]
+    fn from<S>(sample: &S) -> Self
+    where
+        S: Sample,
+    {
+        (sample.to_i16() >> 8) as i8
+    }
+}
+
+unsafe impl Sample for u16 {
+    type Signed = i16;
+    type Float = f32;
+
+    const FORMAT: SampleFormat = SampleFormat::U16;
+    const EQUILIBRIUM: Self = 32768;
+
+    #[inline]
+    fn to_f32(&self) -> f32 {
+        self.to_i16().to_f32()
+    }
+
+    #[inline]
+    fn to_i16(&self) -> i16 {
+        (*self as i16).wrapping_add(i16::MIN)
+    }
+
+    #[inline]
+    fn to_u16(&self) -> u16 {
+        *self
+    }
+
+    #[inline]
+    fn to_i32(&self) -> i32 {
+        (self.to_i16() as i32) << 16
+    }
+
+    #[inline]
+    fn to_f64(&self) -> f64 {
+        self.to_i16().to_f64()
+    }
+
+    #[inline]
+    fn saturating_add_amp(&self, amp: i16) -> Self {
+        self.to_i16().saturating_add(amp).wrapping_add(i16::MIN) as u16
+    }
+
+    #[inline]
+    fn saturating_mul_amp(&self, amp: f32) -> Self {
+        ((self.to_i16() as f32 * amp) as i16).wrapping_add(i16::MIN) as u16
+    }
+
+    #[inline]
+    fn clamp_between(&self, min: Self, max: Self) -> Self {
+        clamp_partial(*self, min, max)
+    }
+
+    #[inline]
+    fn clamp_to_full_scale(&self) -> Self {
+        *self
+    }
+
+    #[inline]
+    fn from<S>(sample: &S) -> Self
+    where
+        S: Sample,
+    {
+        sample.to_u16()
+    }
+}
+
+unsafe impl Sample for i16 {
+    type Signed = i16;
+    type Float = f32;
+
+    const FORMAT: SampleFormat = SampleFormat::I16;
+    const EQUILIBRIUM: Self = 0;
+
+    #[inline]
+    fn to_f32(&self) -> f32 {
+        if *self < 0 {
+            *self as f32 / -(i16::MIN as f32)
+        } else {
+            *self as f32 / i16::MAX as f32
+        }
+    }
+
+    #[inline]
+    fn to_i16(&self) -> i16 {
+        *self
+    }
+
+    #[inline]
+    fn to_u16(&self) -> u16 {
+        self.wrapping_add(i16::MIN) as u16
+    }
+
+    #[inline]
+    fn to_i32(&self) -> i32 {
+        (*self as i32) << 16
+    }
+
+    #[inline]
+    fn to_f64(&self) -> f64 {
+        if *self < 0 {
+            *self as f64 / -(i16::MIN as f64)
+        } else {
+            *self as f64 / i16::MAX as f64
         }
     }
+
+    #[inline]
+    fn saturating_add_amp(&self, amp: i16) -> Self {
+        self.saturating_add(amp)
+    }
+
+    #[inline]
+    fn saturating_mul_amp(&self, amp: f32) -> Self {
+        (*self as f32 * amp) as i16
+    }
+
+    #[inline]
+    fn clamp_between(&self, min: Self, max: Self) -> Self {
+        clamp_partial(*self, min, max)
+    }
+
+    #[inline]
+    fn clamp_to_full_scale(&self) -> Self {
+        *self
+    }
+
+    #[inline]
+    fn from<S>(sample: &S) -> Self
+    where
+        S: Sample,
+    {
+        sample.to_i16()
+    }
+}
+unsafe impl Sample for u32 {
+    type Signed = i32;
+    type Float = f64;
+
+    const FORMAT: SampleFormat = SampleFormat::U32;
+    const EQUILIBRIUM: Self = 1 << 31;
+
+    #[inline]
+    fn to_f32(&self) -> f32 {
+        self.to_i32().to_f32()
+    }
+
+    #[inline]
+    fn to_i16(&self) -> i16 {
+        (self.to_i32() >> 16) as i16
+    }
+
+    #[inline]
+    fn to_u16(&self) -> u16 {
+        (*self >> 16) as u16
+    }
+
+    #[inline]
+    fn to_i32(&self) -> i32 {
+        (*self as i32).wrapping_add(i32::MIN)
+    }
+
+    #[inline]
+    fn to_f64(&self) -> f64 {
+        self.to_i32().to_f64()
+    }
+
+    #[inline]
+    fn saturating_add_amp(&self, amp: i32) -> Self {
+        self.to_i32().saturating_add(amp).wrapping_add(i32::MIN) as u32
+    }
+
+    #[inline]
+    fn saturating_mul_amp(&self, amp: f64) -> Self {
+        ((self.to_i32() as f64 * amp) as i32).wrapping_add(i32::MIN) as u32
+    }
+
+    #[inline]
+    fn clamp_between(&self, min: Self, max: Self) -> Self {
+        clamp_partial(*self, min, max)
+    }
+
+    #[inline]
+    fn clamp_to_full_scale(&self) -> Self {
+        *self
+    }
+
+    #[inline]
+    fn from<S>(sample: &S) -> Self
+    where
+        S: Sample,
+    {
+        sample.to_i32().wrapping_add(i32::MIN) as u32
+    }
+}
+
+unsafe impl Sample for i32 {
+    type Signed = i32;
+    type Float = f64;
+
+    const FORMAT: SampleFormat = SampleFormat::I32;
+    const EQUILIBRIUM: Self = 0;
+
+    #[inline]
+    fn to_f32(&self) -> f32 {
+        if *self < 0 {
+            *self as f32 / -(i32::MIN as f32)
+        } else {
+            *self as f32 / i32::MAX as f32
+        }
+    }
+
+    #[inline]
+    fn to_i16(&self) -> i16 {
+        (*self >> 16) as i16
+    }
+
+    #[inline]
+    fn to_u16(&self) -> u16 {
+        self.to_i16().wrapping_add(i16::MIN) as u16
+    }
+
+    #[inline]
+    fn to_i32(&self) -> i32 {
+        *self
+    }
+
+    #[inline]
+    fn to_f64(&self) -> f64 {
+        if *self < 0 {
+            *self as f64 / -(i32::MIN as f64)
+        } else {
+            *self as f64 / i32::MAX as f64
+        }
+    }
+
+    #[inline]
+    fn saturating_add_amp(&self, amp: i32) -> Self {
+        self.saturating_add(amp)
+    }
+
+    #[inline]
+    fn saturating_mul_amp(&self, amp: f64) -> Self {
+        (*self as f64 * amp) as i32
+    }
+
+    #[inline]
+    fn clamp_between(&self, min: Self, max: Self) -> Self {
+        clamp_partial(*self, min, max)
+    }
+
+    #[inline]
+    fn clamp_to_full_scale(&self) -> Self {
+        *self
+    }
+
+    #[inline]
+    fn from<S>(sample: &S) -> Self
+    where
+        S: Sample,
+    {
+        sample.to_i32()
+    }
 }
 
-/// Trait for containers that contain PCM data.
-pub unsafe trait Sample: Copy + Clone {
-    /// The `SampleFormat` corresponding to this data type.
-    const FORMAT: SampleFormat;
-
-    /// Turns the sample into its equivalent as a floating-point.
-    fn to_f32(&self) -> f32;
-    /// Converts this sample into a standard i16 sample.
-    fn to_i16(&self) -> i16;
-    /// Converts this sample into a standard u16 sample.
-    fn to_u16(&self) -> u16;
+#[inline]
+fn i24_to_f32(value: i32) -> f32 {
+    if value < 0 {
+        value as f32 / -(I24::MIN as f32)
+    } else {
+        value as f32 / I24::MAX as f32
+    }
+}
 
-    /// Converts any sample type to this one by calling `to_i16`, `to_u16` or `to_f32`.
-    fn from<S>(s: &S) -> Self
-    where
-        S: Sample;
+#[inline]
+fn i24_to_f64(value: i32) -> f64 {
+    if value < 0 {
+        value as f64 / -(I24::MIN as f64)
+    } else {
+        value as f64 / I24::MAX as f64
+    }
 }
 
-unsafe impl Sample for u16 {
-    const FORMAT: SampleFormat = SampleFormat::U16;
+unsafe impl Sample for I24 {
+    type Signed = I24;
+    type Float = f32;
+
+    const FORMAT: SampleFormat = SampleFormat::I24;
+    const EQUILIBRIUM: Self = I24([0; 3]);
 
     #[inline]
     fn to_f32(&self) -> f32 {
-        self.to_i16().to_f32()
+        i24_to_f32(self.value())
     }
 
     #[inline]
     fn to_i16(&self) -> i16 {
-        (*self as i16).wrapping_add(i16::MIN)
+        (self.value() >> 8) as i16
     }
 
     #[inline]
     fn to_u16(&self) -> u16 {
+        self.to_i16().wrapping_add(i16::MIN) as u16
+    }
+
+    #[inline]
+    fn to_i32(&self) -> i32 {
+        self.value() << 8
+    }
+
+    #[inline]
+    fn to_f64(&self) -> f64 {
+        i24_to_f64(self.value())
+    }
+
+    #[inline]
+    fn saturating_add_amp(&self, amp: I24) -> Self {
+        I24::new_wrapping((self.value() + amp.value()).clamp(I24::MIN, I24::MAX))
+    }
+
+    #[inline]
+    fn saturating_mul_amp(&self, amp: f32) -> Self {
+        I24::new_wrapping(((self.value() as f32 * amp) as i32).clamp(I24::MIN, I24::MAX))
+    }
+
+    #[inline]
+    fn clamp_between(&self, min: Self, max: Self) -> Self {
+        I24::new_wrapping(clamp_partial(self.value(), min.value(), max.value()))
+    }
+
+    #[inline]
+    fn clamp_to_full_scale(&self) -> Self {
         *self
     }
 
@@ -64,30 +727,60 @@ unsafe impl Sample for u16 {
     where
         S: Sample,
     {
-        sample.to_u16()
+        I24::new_wrapping(sample.to_i32() >> 8)
     }
 }
 
-unsafe impl Sample for i16 {
-    const FORMAT: SampleFormat = SampleFormat::I16;
+unsafe impl Sample for I24In32 {
+    type Signed = I24In32;
+    type Float = f32;
+
+    const FORMAT: SampleFormat = SampleFormat::I24In32;
+    const EQUILIBRIUM: Self = I24In32(0);
 
     #[inline]
     fn to_f32(&self) -> f32 {
-        if *self < 0 {
-            *self as f32 / -(i16::MIN as f32)
-        } else {
-            *self as f32 / i16::MAX as f32
-        }
+        i24_to_f32(self.value())
     }
 
     #[inline]
     fn to_i16(&self) -> i16 {
-        *self
+        (self.value() >> 8) as i16
     }
 
     #[inline]
     fn to_u16(&self) -> u16 {
-        self.wrapping_add(i16::MIN) as u16
+        self.to_i16().wrapping_add(i16::MIN) as u16
+    }
+
+    #[inline]
+    fn to_i32(&self) -> i32 {
+        self.value() << 8
+    }
+
+    #[inline]
+    fn to_f64(&self) -> f64 {
+        i24_to_f64(self.value())
+    }
+
+    #[inline]
+    fn saturating_add_amp(&self, amp: I24In32) -> Self {
+        I24In32((self.value() + amp.value()).clamp(I24::MIN, I24::MAX))
+    }
+
+    #[inline]
+    fn saturating_mul_amp(&self, amp: f32) -> Self {
+        I24In32(((self.value() as f32 * amp) as i32).clamp(I24::MIN, I24::MAX))
+    }
+
+    #[inline]
+    fn clamp_between(&self, min: Self, max: Self) -> Self {
+        I24In32(clamp_partial(self.value(), min.value(), max.value()))
+    }
+
+    #[inline]
+    fn clamp_to_full_scale(&self) -> Self {
+        I24In32(self.value())
     }
 
     #[inline]
@@ -95,12 +788,17 @@ unsafe impl Sample for i16 {
     where
         S: Sample,
     {
-        sample.to_i16()
+        I24In32(sample.to_i32() >> 8)
     }
 }
+
 const F32_TO_16BIT_INT_MULTIPLIER: f32 = u16::MAX as f32 * 0.5;
 unsafe impl Sample for f32 {
+    type Signed = f32;
+    type Float = f32;
+
     const FORMAT: SampleFormat = SampleFormat::F32;
+    const EQUILIBRIUM: Self = 0.0;
 
     #[inline]
     fn to_f32(&self) -> f32 {
@@ -122,6 +820,44 @@ unsafe impl Sample for f32 {
             .round() as u16
     }
 
+    #[inline]
+    fn to_i32(&self) -> i32 {
+        if *self >= 0.0 {
+            (*self as f64 * i32::MAX as f64) as i32
+        } else {
+            (-*self as f64 * i32::MIN as f64) as i32
+        }
+    }
+
+    #[inline]
+    fn to_f64(&self) -> f64 {
+        *self as f64
+    }
+
+    #[inline]
+    fn saturating_add_amp(&self, amp: f32) -> Self {
+        *self + amp
+    }
+
+    #[inline]
+    fn saturating_mul_amp(&self, amp: f32) -> Self {
+        *self * amp
+    }
+
+    #[inline]
+    fn clamp_between(&self, min: Self, max: Self) -> Self {
+        clamp_partial(*self, min, max)
+    }
+
+    #[inline]
+    fn clamp_to_full_scale(&self) -> Self {
+        if self.is_nan() {
+            Self::EQUILIBRIUM
+        } else {
+            clamp_partial(*self, -1.0, 1.0)
+        }
+    }
+
     #[inline]
     fn from<S>(sample: &S) -> Self
     where
@@ -131,9 +867,165 @@ unsafe impl Sample for f32 {
     }
 }
 
+unsafe impl Sample for f64 {
+    type Signed = f64;
+    type Float = f64;
+
+    const FORMAT: SampleFormat = SampleFormat::F64;
+    const EQUILIBRIUM: Self = 0.0;
+
+    #[inline]
+    fn to_f32(&self) -> f32 {
+        *self as f32
+    }
+
+    #[inline]
+    fn to_i16(&self) -> i16 {
+        if *self >= 0.0 {
+            (*self * i16::MAX as f64) as i16
+        } else {
+            (-*self * i16::MIN as f64) as i16
+        }
+    }
+
+    #[inline]
+    fn to_u16(&self) -> u16 {
+        self.mul_add(
+            F32_TO_16BIT_INT_MULTIPLIER as f64,
+            F32_TO_16BIT_INT_MULTIPLIER as f64,
+        )
+        .round() as u16
+    }
+
+    #[inline]
+    fn to_i32(&self) -> i32 {
+        if *self >= 0.0 {
+            (*self * i32::MAX as f64) as i32
+        } else {
+            (-*self * i32::MIN as f64) as i32
+        }
+    }
+
+    #[inline]
+    fn to_f64(&self) -> f64 {
+        *self
+    }
+
+    #[inline]
+    fn saturating_add_amp(&self, amp: f64) -> Self {
+        *self + amp
+    }
+
+    #[inline]
+    fn saturating_mul_amp(&self, amp: f64) -> Self {
+        *self * amp
+    }
+
+    #[inline]
+    fn clamp_between(&self, min: Self, max: Self) -> Self {
+        clamp_partial(*self, min, max)
+    }
+
+    #[inline]
+    fn clamp_to_full_scale(&self) -> Self {
+        if self.is_nan() {
+            Self::EQUILIBRIUM
+        } else {
+            clamp_partial(*self, -1.0, 1.0)
+        }
+    }
+
+    #[inline]
+    fn from<S>(sample: &S) -> Self
+    where
+        S: Sample,
+    {
+        sample.to_f64()
+    }
+}
+
+// Restricts `value` to `min..=max` without the panics of `Ord::clamp` and `f32::clamp`.
+#[inline]
+fn clamp_partial<T>(value: T, min: T, max: T) -> T
+where
+    T: PartialOrd,
+{
+    if value < min {
+        min
+    } else if value > max {
+        max
+    } else {
+        value
+    }
+}
+
 #[cfg(test)]
 mod test {
-    use super::Sample;
+    use super::{I24In32, Sample, SampleFormat, I24};
+
+    #[test]
+    fn i8_to_i16() {
+        assert_eq!(0i8.to_i16(), 0);
+        assert_eq!((-64i8).to_i16(), -16384);
+        assert_eq!(127i8.to_i16(), 32512);
+        assert_eq!((-128i8).to_i16(), -32768);
+    }
+
+    #[test]
+    fn i8_to_u16() {
+        assert_eq!(0i8.to_u16(), 32768);
+        assert_eq!((-64i8).to_u16(), 16384);
+        assert_eq!(127i8.to_u16(), 65280);
+        assert_eq!((-128i8).to_u16(), 0);
+    }
+
+    #[test]
+    fn i8_to_f32() {
+        assert_eq!(0i8.to_f32(), 0.0);
+        assert_eq!((-64i8).to_f32(), -0.5);
+        assert_eq!(127i8.to_f32(), 1.0);
+        assert_eq!((-128i8).to_f32(), -1.0);
+    }
+
+    #[test]
+    fn u8_to_i16() {
+        assert_eq!(128u8.to_i16(), 0);
+        assert_eq!(64u8.to_i16(), -16384);
+        assert_eq!(255u8.to_i16(), 32512);
+        assert_eq!(0u8.to_i16(), -32768);
+    }
+
+    #[test]
+    fn u8_to_u16() {
+        assert_eq!(0u8.to_u16(), 0);
+        assert_eq!(128u8.to_u16(), 32768);
+        assert_eq!(255u8.to_u16(), 65280);
+    }
+
+    #[test]
+    fn u8_to_f32() {
+        assert_eq!(0u8.to_f32(), -1.0);
+        assert_eq!(128u8.to_f32(), 0.0);
+        assert_eq!(255u8.to_f32(), 1.0);
+    }
+
+    #[test]
+    fn i8_from() {
+        assert_eq!(<i8 as Sample>::from(&0.0f32), 0);
+        assert_eq!(<i8 as Sample>::from(&1.0f32), 127);
+        assert_eq!(<i8 as Sample>::from(&-1.0f32), -128);
+        assert_eq!(<i8 as Sample>::from(&-16384i16), -64);
+        assert_eq!(<i8 as Sample>::from(&255u8), 127);
+    }
+
+    #[test]
+    fn u8_from() {
+        assert_eq!(<u8 as Sample>::from(&0.0f32), 128);
+        assert_eq!(<u8 as Sample>::from(&1.0f32), 255);
+        assert_eq!(<u8 as Sample>::from(&-1.0f32), 0);
+        assert_eq!(<u8 as Sample>::from(&16384u16), 64);
+        assert_eq!(<u8 as Sample>::from(&-128i8), 0);
+    }
 
     #[test]
     fn i16_to_i16() {
@@ -197,10 +1089,304 @@ mod test {
         assert_eq!(1.0f32.to_u16(), 65535);
     }
 
+    #[test]
+    fn i16_to_i32() {
+        assert_eq!(0i16.to_i32(), 0);
+        assert_eq!((-16384i16).to_i32(), -1073741824);
+        assert_eq!(32767i16.to_i32(), 2147418112);
+        assert_eq!((-32768i16).to_i32(), i32::MIN);
+    }
+
+    #[test]
+    fn i32_to_i16() {
+        assert_eq!(0i32.to_i16(), 0);
+        assert_eq!((-1073741824i32).to_i16(), -16384);
+        assert_eq!(i32::MAX.to_i16(), 32767);
+        assert_eq!(i32::MIN.to_i16(), -32768);
+    }
+
+    #[test]
+    fn i32_to_u16() {
+        assert_eq!(0i32.to_u16(), 32768);
+        assert_eq!(i32::MAX.to_u16(), 65535);
+        assert_eq!(i32::MIN.to_u16(), 0);
+    }
+
+    #[test]
+    fn i32_to_f32() {
+        assert_eq!(0i32.to_f32(), 0.0);
+        assert_eq!((-1073741824i32).to_f32(), -0.5);
+        assert_eq!(i32::MAX.to_f32(), 1.0);
+        assert_eq!(i32::MIN.to_f32(), -1.0);
+    }
+
+    #[test]
+    fn u32_to_i32() {
+        assert_eq!(2147483648u32.to_i32(), 0);
+        assert_eq!(1073741824u32.to_i32(), -1073741824);
+        assert_eq!(u32::MAX.to_i32(), i32::MAX);
+        assert_eq!(0u32.to_i32(), i32::MIN);
+    }
+
+    #[test]
+    fn u32_to_u16() {
+        assert_eq!(0u32.to_u16(), 0);
+        assert_eq!(2147483648u32.to_u16(), 32768);
+        assert_eq!(u32::MAX.to_u16(), 65535);
+    }
+
+    #[test]
+    fn u32_to_f32() {
+        assert_eq!(0u32.to_f32(), -1.0);
+        assert_eq!(2147483648u32.to_f32(), 0.0);
+        assert_eq!(u32::MAX.to_f32(), 1.0);
+    }
+
+    #[test]
+    fn f32_to_i32() {
+        assert_eq!(0.0f32.to_i32(), 0);
+        assert_eq!((-0.5f32).to_i32(), i32::MIN / 2);
+        assert_eq!(1.0f32.to_i32(), i32::MAX);
+        assert_eq!((-1.0f32).to_i32(), i32::MIN);
+    }
+
+    #[test]
+    fn u32_from() {
+        assert_eq!(<u32 as Sample>::from(&0.0f32), 2147483648);
+        assert_eq!(<u32 as Sample>::from(&1.0f32), u32::MAX);
+        assert_eq!(<u32 as Sample>::from(&-1.0f32), 0);
+        assert_eq!(<u32 as Sample>::from(&0i32), 2147483648);
+        assert_eq!(<u32 as Sample>::from(&65535u16), 4294901760);
+    }
+
+    #[test]
+    fn i24_value() {
+        assert_eq!(I24::new(0).unwrap().value(), 0);
+        assert_eq!(I24::new(-1).unwrap().value(), -1);
+        assert_eq!(I24::new(I24::MAX).unwrap().value(), I24::MAX);
+        assert_eq!(I24::new(I24::MIN).unwrap().value(), I24::MIN);
+        assert_eq!(I24::new(I24::MAX + 1), None);
+        assert_eq!(I24::new(I24::MIN - 1), None);
+        assert_eq!(std::mem::size_of::<I24>(), 3);
+    }
+
+    #[test]
+    fn i24_in_32_value() {
+        // The most significant byte is padding and must not affect the value.
+        let padded: I24In32 = unsafe { std::mem::transmute(0x7f80_0000u32 as i32) };
+        assert_eq!(padded.value(), I24::MIN);
+        let padded: I24In32 = unsafe { std::mem::transmute(0x7f00_0001u32 as i32) };
+        assert_eq!(padded.value(), 1);
+        assert_eq!(I24In32::new(I24::MAX).unwrap().value(), I24::MAX);
+        assert_eq!(I24In32::new(I24::MAX + 1), None);
+    }
+
+    #[test]
+    fn i24_to_i32() {
+        assert_eq!(I24::new(0).unwrap().to_i32(), 0);
+        assert_eq!(I24::new(-4194304).unwrap().to_i32(), -1073741824);
+        assert_eq!(I24::new(I24::MAX).unwrap().to_i32(), 2147483392);
+        assert_eq!(I24::new(I24::MIN).unwrap().to_i32(), i32::MIN);
+    }
+
+    #[test]
+    fn i24_to_i16() {
+        assert_eq!(I24::new(0).unwrap().to_i16(), 0);
+        assert_eq!(I24::new(-4194304).unwrap().to_i16(), -16384);
+        assert_eq!(I24::new(I24::MAX).unwrap().to_i16(), 32767);
+        assert_eq!(I24::new(I24::MIN).unwrap().to_i16(), -32768);
+    }
+
+    #[test]
+    fn i24_to_f32() {
+        assert_eq!(I24::new(0).unwrap().to_f32(), 0.0);
+        assert_eq!(I24::new(-4194304).unwrap().to_f32(), -0.5);
+        assert_eq!(I24::new(I24::MAX).unwrap().to_f32(), 1.0);
+        assert_eq!(I24::new(I24::MIN).unwrap().to_f32(), -1.0);
+        assert_eq!(I24In32::new(I24::MAX).unwrap().to_f32(), 1.0);
+        assert_eq!(I24In32::new(I24::MIN).unwrap().to_f32(), -1.0);
+    }
+
+    #[test]
+    fn i24_from() {
+        assert_eq!(<I24 as Sample>::from(&0.0f32).value(), 0);
+        assert_eq!(<I24 as Sample>::from(&1.0f32).value(), I24::MAX);
+        assert_eq!(<I24 as Sample>::from(&-1.0f32).value(), I24::MIN);
+        assert_eq!(<I24 as Sample>::from(&-16384i16).value(), -4194304);
+        assert_eq!(<I24 as Sample>::from(&0x7fff_ffffi32).value(), I24::MAX);
+        assert_eq!(<I24In32 as Sample>::from(&1.0f32).value(), I24::MAX);
+        assert_eq!(<I24In32 as Sample>::from(&-1.0f32).value(), I24::MIN);
+        assert_eq!(<I24In32 as Sample>::from(&65535u16).value(), 8388352);
+    }
+
+    #[test]
+    fn i32_to_f64() {
+        assert_eq!(0i32.to_f64(), 0.0);
+        assert_eq!((-1073741824i32).to_f64(), -0.5);
+        assert_eq!(i32::MAX.to_f64(), 1.0);
+        assert_eq!(i32::MIN.to_f64(), -1.0);
+        assert_eq!(1i32.to_f64(), 1.0 / i32::MAX as f64);
+    }
+
+    #[test]
+    fn u16_to_f64() {
+        assert_eq!(0u16.to_f64(), -1.0);
+        assert_eq!(32768u16.to_f64(), 0.0);
+        assert_eq!(65535u16.to_f64(), 1.0);
+    }
+
+    #[test]
+    fn f64_to_i16() {
+        assert_eq!(0.0f64.to_i16(), 0);
+        assert_eq!((-0.5f64).to_i16(), i16::MIN / 2);
+        assert_eq!(1.0f64.to_i16(), i16::MAX);
+        assert_eq!((-1.0f64).to_i16(), i16::MIN);
+    }
+
+    #[test]
+    fn f64_to_u16() {
+        assert_eq!((-1.0f64).to_u16(), 0);
+        assert_eq!(0.0f64.to_u16(), 32768);
+        assert_eq!(1.0f64.to_u16(), 65535);
+    }
+
+    #[test]
+    fn f64_to_i32() {
+        assert_eq!(0.0f64.to_i32(), 0);
+        assert_eq!((-0.5f64).to_i32(), i32::MIN / 2);
+        assert_eq!(1.0f64.to_i32(), i32::MAX);
+        assert_eq!((-1.0f64).to_i32(), i32::MIN);
+    }
+
+    #[test]
+    fn f64_to_f32() {
+        assert_eq!(0.5f64.to_f32(), 0.5);
+        assert_eq!((-1.0f64).to_f32(), -1.0);
+    }
+
+    #[test]
+    fn f64_from() {
+        assert_eq!(<f64 as Sample>::from(&0.25f32), 0.25);
+        assert_eq!(<f64 as Sample>::from(&i32::MAX), 1.0);
+        assert_eq!(<f64 as Sample>::from(&I24::new(I24::MIN).unwrap()), -1.0);
+        assert_eq!(<f64 as Sample>::from(&128u8), 0.0);
+    }
+
     #[test]
     fn f32_to_f32() {
         assert_eq!(0.1f32.to_f32(), 0.1);
         assert_eq!((-0.7f32).to_f32(), -0.7);
         assert_eq!(1.0f32.to_f32(), 1.0);
     }
+
+    #[test]
+    fn equilibrium() {
+        assert_eq!(u8::EQUILIBRIUM.to_f32(), 0.0);
+        assert_eq!(i8::EQUILIBRIUM.to_f32(), 0.0);
+        assert_eq!(u16::EQUILIBRIUM.to_f32(), 0.0);
+        assert_eq!(i16::EQUILIBRIUM.to_f32(), 0.0);
+        assert_eq!(u32::EQUILIBRIUM.to_f32(), 0.0);
+        assert_eq!(i32::EQUILIBRIUM.to_f32(), 0.0);
+        assert_eq!(I24::EQUILIBRIUM.to_f32(), 0.0);
+        assert_eq!(I24In32::EQUILIBRIUM.to_f32(), 0.0);
+        assert_eq!(f32::EQUILIBRIUM, 0.0);
+        assert_eq!(f64::EQUILIBRIUM, 0.0);
+    }
+
+    #[test]
+    fn saturating_add_amp() {
+        assert_eq!(100i8.saturating_add_amp(100), i8::MAX);
+        assert_eq!(200u8.saturating_add_amp(-8), 192);
+        assert_eq!(200u8.saturating_add_amp(100), u8::MAX);
+        assert_eq!(10u8.saturating_add_amp(-100), 0);
+        assert_eq!((-30000i16).saturating_add_amp(-30000), i16::MIN);
+        assert_eq!(60000u16.saturating_add_amp(10000), u16::MAX);
+        assert_eq!(1000u16.saturating_add_amp(-1000), 0);
+        assert_eq!(i32::MAX.saturating_add_amp(1), i32::MAX);
+        assert_eq!(u32::EQUILIBRIUM.saturating_add_amp(i32::MIN), 0);
+        assert_eq!(
+            I24::new(I24::MAX - 1)
+                .unwrap()
+                .saturating_add_amp(I24::new(5).unwrap()),
+            I24::new(I24::MAX).unwrap()
+        );
+        assert_eq!(
+            I24In32::new(I24::MIN)
+                .unwrap()
+                .saturating_add_amp(I24In32::new(-1).unwrap())
+                .value(),
+            I24::MIN
+        );
+        assert_eq!(0.75f32.saturating_add_amp(0.5), 1.25);
+        assert_eq!((-0.5f64).saturating_add_amp(0.25), -0.25);
+    }
+
+    #[test]
+    fn saturating_mul_amp() {
+        assert_eq!(100i8.saturating_mul_amp(0.5), 50);
+        assert_eq!(192u8.saturating_mul_amp(0.5), 160);
+        assert_eq!(192u8.saturating_mul_amp(4.0), u8::MAX);
+        assert_eq!((-1000i16).saturating_mul_amp(-2.0), 2000);
+        assert_eq!(0u16.saturating_mul_amp(0.5), 16384);
+        assert_eq!(i16::MIN.saturating_mul_amp(2.0), i16::MIN);
+        assert_eq!(i32::MAX.saturating_mul_amp(1.5), i32::MAX);
+        assert_eq!(u32::MAX.saturating_mul_amp(0.0), u32::EQUILIBRIUM);
+        assert_eq!(
+            I24::new(1 << 22).unwrap().saturating_mul_amp(4.0).value(),
+            I24::MAX
+        );
+        assert_eq!(
+            I24In32::new(-1000).unwrap().saturating_mul_amp(0.5).value(),
+            -500
+        );
+        assert_eq!(0.5f32.saturating_mul_amp(3.0), 1.5);
+        assert_eq!(0.5f64.saturating_mul_amp(-1.0), -0.5);
+    }
+
+    #[test]
+    fn clamp_between() {
+        assert_eq!(100i16.clamp_between(-50, 50), 50);
+        assert_eq!(10u8.clamp_between(64, 192), 64);
+        assert_eq!(
+            I24::new(-100)
+                .unwrap()
+                .clamp_between(I24::new(-10).unwrap(), I24::new(10).unwrap()),
+            I24::new(-10).unwrap()
+        );
+        assert_eq!(0.9f32.clamp_between(-0.5, 0.5), 0.5);
+        assert_eq!((-0.25f64).clamp_between(-0.5, 0.5), -0.25);
+    }
+
+    #[test]
+    fn clamp_to_full_scale() {
+        assert_eq!(i16::MIN.clamp_to_full_scale(), i16::MIN);
+        assert_eq!(1.5f32.clamp_to_full_scale(), 1.0);
+        assert_eq!((-1.5f64).clamp_to_full_scale(), -1.0);
+        assert_eq!(f32::NAN.clamp_to_full_scale(), 0.0);
+        assert_eq!(f64::NAN.clamp_to_full_scale(), 0.0);
+        let padded = I24In32(0x7f80_0000u32 as i32);
+        assert_eq!(padded.clamp_to_full_scale().0, I24::MIN);
+    }
+
+    #[test]
+    fn sample_format_display_from_str() {
+        let formats = [
+            SampleFormat::I8,
+            SampleFormat::U8,
+            SampleFormat::I16,
+            SampleFormat::U16,
+            SampleFormat::I24,
+            SampleFormat::I24In32,
+            SampleFormat::I32,
+            SampleFormat::U32,
+            SampleFormat::F32,
+            SampleFormat::F64,
+        ];
+        for format in formats.iter() {
+            assert_eq!(format.to_string().parse(), Ok(*format));
+        }
+        assert_eq!("F32".parse(), Ok(SampleFormat::F32));
+        assert_eq!("I24In32".parse(), Ok(SampleFormat::I24In32));
+        assert!("f16".parse::<SampleFormat>().is_err());
+    }
 }
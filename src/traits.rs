@@ -1,10 +1,18 @@
 //! The suite of traits allowing CPAL to abstract over hosts, devices, event loops and stream IDs.
 
+use config_query;
+use conversions::ScratchBuffer;
+use flow::FlowEmulation;
+use layout::PlanarBuffer;
+use std::time::Duration;
 use {
-    BuildStreamError, Data, DefaultStreamConfigError, DeviceNameError, DevicesError,
-    InputCallbackInfo, InputDevices, OutputCallbackInfo, OutputDevices, PauseStreamError,
-    PlayStreamError, Sample, SampleFormat, StreamConfig, StreamError, SupportedStreamConfig,
-    SupportedStreamConfigRange, SupportedStreamConfigsError,
+    duration_to_frames, BufferSize, BuildStreamError, CallbackFlow, ChannelCount,
+    CheckStreamConfigError, ConfigConstraints, Data, DefaultStreamConfigError, DeviceNameError,
+    DevicesError, Dither, DuplexCallbackInfo, DuplexStreamConfig, InputCallbackInfo, InputDevices,
+    NegotiatedStreamConfig, OutputCallbackInfo, OutputDevices, PauseStreamError, PlayStreamError,
+    QueryStreamConfigError, RejectedParameter, Sample, SampleFormat, SampleRate, StreamConfig,
+    StreamError, SupportedBufferSize, SupportedStreamConfig, SupportedStreamConfigRange,
+    SupportedStreamConfigsError,
 };
 
 /// A **Host** provides access to the available audio devices on the system.
@@ -88,7 +96,8 @@ pub trait DeviceTrait {
     type SupportedInputConfigs: Iterator<Item = SupportedStreamConfigRange>;
     /// The iterator type yielding supported output stream formats.
     type SupportedOutputConfigs: Iterator<Item = SupportedStreamConfigRange>;
-    /// The stream type created by `build_input_stream_raw` and `build_output_stream_raw`.
+    /// The stream type created by `build_input_stream_raw`, `build_output_stream_raw` and
+    /// `build_duplex_stream_raw`, and their planar counterparts.
     type Stream: StreamTrait;
 
     /// The human-readable name of the device.
@@ -114,6 +123,54 @@ pub trait DeviceTrait {
     /// The default output stream format for the device.
     fn default_output_config(&self) -> Result<SupportedStreamConfig, DefaultStreamConfigError>;
 
+    /// The supported input stream format that best matches `constraints`.
+    ///
+    /// Returns `QueryStreamConfigError::ConstraintNotMet` naming the first constraint that none
+    /// of the supported configs satisfy.
+    fn query_input_config(
+        &self,
+        constraints: &ConfigConstraints,
+    ) -> Result<SupportedStreamConfig, QueryStreamConfigError> {
+        config_query::best_config(self.supported_input_configs()?, constraints)
+    }
+
+    /// The supported output stream format that best matches `constraints`.
+    ///
+    /// Returns `QueryStreamConfigError::ConstraintNotMet` naming the first constraint that none
+    /// of the supported configs satisfy.
+    fn query_output_config(
+        &self,
+        constraints: &ConfigConstraints,
+    ) -> Result<SupportedStreamConfig, QueryStreamConfigError> {
+        config_query::best_config(self.supported_output_configs()?, constraints)
+    }
+
+    /// Whether an input stream can be built with `config` and `sample_format`, checked without
+    /// opening one.
+    ///
+    /// Returns `CheckStreamConfigError::NotSupported` naming the first parameter that the device
+    /// does not support, as `BuildStreamError::ParameterNotSupported` would.
+    fn check_input_config(
+        &self,
+        config: &StreamConfig,
+        sample_format: SampleFormat,
+    ) -> Result<(), CheckStreamConfigError> {
+        config_query::check_config(self.supported_input_configs()?, config, sample_format)
+    }
+
+    /// Whether an output stream can be built with `config` and `sample_format`, checked without
+    /// opening one.
+    ///
+    /// Returns `CheckStreamConfigError::NotSupported` naming the first parameter that the device
+    /// does not support, as `BuildStreamError::ParameterNotSupported` would.
+    fn check_output_config(
+        &self,
+        config: &StreamConfig,
+        sample_format: SampleFormat,
+    ) -> Result<(), CheckStreamConfigError> {
+        config_query::check_config(self.supported_output_configs()?, config, sample_format)
+    }
+
     /// Create an input stream.
     fn build_input_stream<T, D, E>(
         &self,
@@ -166,6 +223,158 @@ pub trait DeviceTrait {
         )
     }
 
+    /// Create an input stream whose callback receives whole frames of `N` channels.
+    ///
+    /// Returns `BuildStreamError::ParameterNotSupported` if `config` does not have exactly `N`
+    /// channels.
+    fn build_input_stream_frames<T, const N: usize, D, E>(
+        &self,
+        config: &StreamConfig,
+        mut data_callback: D,
+        error_callback: E,
+    ) -> Result<Self::Stream, BuildStreamError>
+    where
+        T: Sample,
+        D: FnMut(&[[T; N]], &InputCallbackInfo) + Send + 'static,
+        E: FnMut(StreamError) + Send + 'static,
+    {
+        check_frame_channels(config, N)?;
+        self.build_input_stream_raw(
+            config,
+            T::FORMAT,
+            move |data, info| {
+                data_callback(
+                    data.as_frames()
+                        .expect("host supplied incorrect sample type"),
+                    info,
+                )
+            },
+            error_callback,
+        )
+    }
+
+    /// Create an output stream whose callback fills whole frames of `N` channels.
+    ///
+    /// Returns `BuildStreamError::ParameterNotSupported` if `config` does not have exactly `N`
+    /// channels.
+    fn build_output_stream_frames<T, const N: usize, D, E>(
+        &self,
+        config: &StreamConfig,
+        mut data_callback: D,
+        error_callback: E,
+    ) -> Result<Self::Stream, BuildStreamError>
+    where
+        T: Sample,
+        D: FnMut(&mut [[T; N]], &OutputCallbackInfo) + Send + 'static,
+        E: FnMut(StreamError) + Send + 'static,
+    {
+        check_frame_channels(config, N)?;
+        self.build_output_stream_raw(
+            config,
+            T::FORMAT,
+            move |data, info| {
+                data_callback(
+                    data.as_frames_mut()
+                        .expect("host supplied incorrect sample type"),
+                    info,
+                )
+            },
+            error_callback,
+        )
+    }
+
+    /// Create an input stream delivering samples of type `T`, whatever the device's native format.
+    ///
+    /// The stream opens in `T::FORMAT` if `check_input_config` accepts it for `config`, or a
+    /// supported config lists it. Otherwise it opens in the device's best native format according
+    /// to `SupportedStreamConfigRange::cmp_default_heuristics`, and samples are converted with
+    /// `convert_data` into a buffer owned by the stream. The buffer is allocated up front, sized for
+    /// the requested buffer size. If the host delivers more frames than fit, the callback is called
+    /// once per chunk, with timestamps advanced to the start of each chunk.
+    ///
+    /// If `dither` is given, conversions to a narrower integer format go through it instead.
+    ///
+    /// Returns `BuildStreamError::StreamConfigNotSupported` if `check_input_config` rejects
+    /// `config` and no supported config has its channel count and sample rate.
+    ///
+    /// **panic!**s if `dither` is for a different number of channels than `config`.
+    fn build_input_stream_adapted<T, D, E>(
+        &self,
+        config: &StreamConfig,
+        dither: Option<Dither>,
+        mut data_callback: D,
+        error_callback: E,
+    ) -> Result<Self::Stream, BuildStreamError>
+    where
+        T: Sample + Send + 'static,
+        D: FnMut(&[T], &InputCallbackInfo) + Send + 'static,
+        E: FnMut(StreamError) + Send + 'static,
+    {
+        check_dither(config, dither.as_ref());
+        if self.check_input_config(config, T::FORMAT).is_ok() {
+            return self.build_input_stream(config, data_callback, error_callback);
+        }
+        let native = native_config(self.supported_input_configs()?, config, T::FORMAT)?;
+        if native.sample_format() == T::FORMAT {
+            return self.build_input_stream(config, data_callback, error_callback);
+        }
+        let frames = scratch_frames(config, &native)?;
+        let mut scratch = ScratchBuffer::new(frames, config.channels, dither);
+        let sample_rate = config.sample_rate;
+        self.build_input_stream_raw(
+            config,
+            native.sample_format(),
+            move |data, info| {
+                scratch.convert_from(data, |samples, frames| {
+                    data_callback(samples, &info.offset(chunk_offset(frames, sample_rate)))
+                })
+            },
+            error_callback,
+        )
+    }
+
+    /// Create an output stream expecting samples of type `T`, whatever the device's native format.
+    ///
+    /// The format is chosen as for `build_input_stream_adapted`, and samples written by the
+    /// callback are converted with `convert_data` from a buffer owned by the stream, or with
+    /// `dither` when the native format is a narrower integer format.
+    ///
+    /// **panic!**s if `dither` is for a different number of channels than `config`.
+    fn build_output_stream_adapted<T, D, E>(
+        &self,
+        config: &StreamConfig,
+        dither: Option<Dither>,
+        mut data_callback: D,
+        error_callback: E,
+    ) -> Result<Self::Stream, BuildStreamError>
+    where
+        T: Sample + Send + 'static,
+        D: FnMut(&mut [T], &OutputCallbackInfo) + Send + 'static,
+        E: FnMut(StreamError) + Send + 'static,
+    {
+        check_dither(config, dither.as_ref());
+        if self.check_output_config(config, T::FORMAT).is_ok() {
+            return self.build_output_stream(config, data_callback, error_callback);
+        }
+        let native = native_config(self.supported_output_configs()?, config, T::FORMAT)?;
+        if native.sample_format() == T::FORMAT {
+            return self.build_output_stream(config, data_callback, error_callback);
+        }
+        let frames = scratch_frames(config, &native)?;
+        let mut scratch = ScratchBuffer::new(frames, config.channels, dither);
+        let sample_rate = config.sample_rate;
+        self.build_output_stream_raw(
+            config,
+            native.sample_format(),
+            move |data, info| {
+                scratch.convert_into(data, |samples, frames| {
+                    data_callback(samples, &info.offset(chunk_offset(frames, sample_rate)))
+                })
+            },
+            error_callback,
+        )
+    }
+
     /// Create a dynamically typed input stream.
     fn build_input_stream_raw<D, E>(
         &self,
@@ -189,6 +398,236 @@ pub trait DeviceTrait {
     where
         D: FnMut(&mut Data, &OutputCallbackInfo) + Send + 'static,
         E: FnMut(StreamError) + Send + 'static;
+
+    /// Create a full-duplex stream, whose callback reads the input and writes the output for the
+    /// same span of frames.
+    fn build_duplex_stream<T, D, E>(
+        &self,
+        config: &DuplexStreamConfig,
+        mut data_callback: D,
+        error_callback: E,
+    ) -> Result<Self::Stream, BuildStreamError>
+    where
+        T: Sample,
+        D: FnMut(&[T], &mut [T], &DuplexCallbackInfo) + Send + 'static,
+        E: FnMut(StreamError) + Send + 'static,
+    {
+        self.build_duplex_stream_raw(
+            config,
+            T::FORMAT,
+            move |input, output, info| {
+                data_callback(
+                    input
+                        .as_slice()
+                        .expect("host supplied incorrect sample type"),
+                    output
+                        .as_slice_mut()
+                        .expect("host supplied incorrect sample type"),
+                    info,
+                )
+            },
+            error_callback,
+        )
+    }
+
+    /// Create a dynamically typed full-duplex stream.
+    ///
+    /// Each call to the callback receives interleaved input and output `Data` holding the same
+    /// number of frames, with the input captured by the device in sync with the output played.
+    /// The stream reports the output half through `StreamTrait::negotiated_config`.
+    ///
+    /// Only hosts that can run input and output off the same clock support this, others return
+    /// `BuildStreamError::StreamConfigNotSupported`.
+    fn build_duplex_stream_raw<D, E>(
+        &self,
+        _config: &DuplexStreamConfig,
+        _sample_format: SampleFormat,
+        _data_callback: D,
+        _error_callback: E,
+    ) -> Result<Self::Stream, BuildStreamError>
+    where
+        D: FnMut(&Data, &mut Data, &DuplexCallbackInfo) + Send + 'static,
+        E: FnMut(StreamError) + Send + 'static,
+    {
+        Err(BuildStreamError::StreamConfigNotSupported)
+    }
+
+    /// Create an input stream whose callback returns a `CallbackFlow`, letting it stop the stream.
+    ///
+    /// `completion_callback` is called on the stream's thread once the stream has stopped this
+    /// way. It is not called if the stream is dropped first.
+    fn build_input_stream_flow<T, D, E, C>(
+        &self,
+        config: &StreamConfig,
+        mut data_callback: D,
+        error_callback: E,
+        completion_callback: C,
+    ) -> Result<Self::Stream, BuildStreamError>
+    where
+        T: Sample,
+        D: FnMut(&[T], &InputCallbackInfo) -> CallbackFlow + Send + 'static,
+        E: FnMut(StreamError) + Send + 'static,
+        C: FnOnce() + Send + 'static,
+    {
+        self.build_input_stream_flow_raw(
+            config,
+            T::FORMAT,
+            move |data, info| {
+                data_callback(
+                    data.as_slice()
+                        .expect("host supplied incorrect sample type"),
+                    info,
+                )
+            },
+            error_callback,
+            completion_callback,
+        )
+    }
+
+    /// Create an output stream whose callback returns a `CallbackFlow`, letting it stop the
+    /// stream, e.g. once a file has finished playing.
+    ///
+    /// `completion_callback` is called on the stream's thread once the stream has stopped this
+    /// way, after the buffered output has been played for `CallbackFlow::Drain`. It is not called
+    /// if the stream is dropped first.
+    fn build_output_stream_flow<T, D, E, C>(
+        &self,
+        config: &StreamConfig,
+        mut data_callback: D,
+        error_callback: E,
+        completion_callback: C,
+    ) -> Result<Self::Stream, BuildStreamError>
+    where
+        T: Sample,
+        D: FnMut(&mut [T], &OutputCallbackInfo) -> CallbackFlow + Send + 'static,
+        E: FnMut(StreamError) + Send + 'static,
+        C: FnOnce() + Send + 'static,
+    {
+        self.build_output_stream_flow_raw(
+            config,
+            T::FORMAT,
+            move |data, info| {
+                data_callback(
+                    data.as_slice_mut()
+                        .expect("host supplied incorrect sample type"),
+                    info,
+                )
+            },
+            error_callback,
+            completion_callback,
+        )
+    }
+
+    /// Create a dynamically typed input stream whose callback returns a `CallbackFlow`.
+    ///
+    /// Hosts that cannot stop a stream from its own callback leave it running once asked to stop,
+    /// without calling the data callback again. Drop the stream after the completion callback has
+    /// run to release the device.
+    fn build_input_stream_flow_raw<D, E, C>(
+        &self,
+        config: &StreamConfig,
+        sample_format: SampleFormat,
+        mut data_callback: D,
+        error_callback: E,
+        completion_callback: C,
+    ) -> Result<Self::Stream, BuildStreamError>
+    where
+        D: FnMut(&Data, &InputCallbackInfo) -> CallbackFlow + Send + 'static,
+        E: FnMut(StreamError) + Send + 'static,
+        C: FnOnce() + Send + 'static,
+    {
+        let mut flow = FlowEmulation::new(completion_callback);
+        self.build_input_stream_raw(
+            config,
+            sample_format,
+            move |data, info| flow.input(|| data_callback(data, info)),
+            error_callback,
+        )
+    }
+
+    /// Create a dynamically typed output stream whose callback returns a `CallbackFlow`.
+    ///
+    /// Hosts that cannot stop a stream from its own callback leave it running once asked to stop,
+    /// playing silence without calling the data callback again. They judge when the output has
+    /// drained from the playback timestamps rather than the device. Drop the stream after the
+    /// completion callback has run to release the device.
+    fn build_output_stream_flow_raw<D, E, C>(
+        &self,
+        config: &StreamConfig,
+        sample_format: SampleFormat,
+        mut data_callback: D,
+        error_callback: E,
+        completion_callback: C,
+    ) -> Result<Self::Stream, BuildStreamError>
+    where
+        D: FnMut(&mut Data, &OutputCallbackInfo) -> CallbackFlow + Send + 'static,
+        E: FnMut(StreamError) + Send + 'static,
+        C: FnOnce() + Send + 'static,
+    {
+        let mut flow = FlowEmulation::new(completion_callback);
+        let sample_rate = config.sample_rate;
+        self.build_output_stream_raw(
+            config,
+            sample_format,
+            move |data, info| {
+                flow.output(data, info, sample_rate, |data| data_callback(data, info))
+            },
+            error_callback,
+        )
+    }
+
+    /// Create a dynamically typed input stream delivering planar data.
+    ///
+    /// The callback receives `Data` with `DataLayout::Planar`, holding a separate buffer for each
+    /// channel. Hosts that can hand over per-channel buffers directly do so without copying,
+    /// others de-interleave into a buffer owned by the stream.
+    fn build_input_stream_planar_raw<D, E>(
+        &self,
+        config: &StreamConfig,
+        sample_format: SampleFormat,
+        mut data_callback: D,
+        error_callback: E,
+    ) -> Result<Self::Stream, BuildStreamError>
+    where
+        D: FnMut(&Data, &InputCallbackInfo) + Send + 'static,
+        E: FnMut(StreamError) + Send + 'static,
+    {
+        let mut planar = PlanarBuffer::new(config.channels);
+        self.build_input_stream_raw(
+            config,
+            sample_format,
+            move |data, info| data_callback(&planar.deinterleave(data), info),
+            error_callback,
+        )
+    }
+
+    /// Create a dynamically typed output stream expecting planar data.
+    ///
+    /// The callback receives `Data` with `DataLayout::Planar`, holding a separate buffer for each
+    /// channel. Hosts that can hand over per-channel buffers directly do so without copying,
+    /// others interleave from a buffer owned by the stream.
+    fn build_output_stream_planar_raw<D, E>(
+        &self,
+        config: &StreamConfig,
+        sample_format: SampleFormat,
+        mut data_callback: D,
+        error_callback: E,
+    ) -> Result<Self::Stream, BuildStreamError>
+    where
+        D: FnMut(&mut Data, &OutputCallbackInfo) + Send + 'static,
+        E: FnMut(StreamError) + Send + 'static,
+    {
+        let mut planar = PlanarBuffer::new(config.channels);
+        self.build_output_stream_raw(
+            config,
+            sample_format,
+            move |data, info| {
+                data_callback(&mut planar.data(data.len(), data.sample_format()), info);
+                planar.interleave(data);
+            },
+            error_callback,
+        )
+    }
 }
 
 /// A stream created from `Device`, with methods to control playback.
@@ -205,4 +644,119 @@ pub trait StreamTrait {
     /// Note: Not all devices support suspending the stream at the hardware level. This method may
     /// fail in these cases.
     fn pause(&self) -> Result<(), PauseStreamError>;
+
+    /// The duration of audio held by the stream's buffer, as negotiated with the host.
+    ///
+    /// This is the latency achieved for the requested `BufferSize`. Returns `None` if the host
+    /// does not report it.
+    fn buffer_latency(&self) -> Option<Duration> {
+        None
+    }
+
+    /// The configuration that the stream actually runs with.
+    ///
+    /// Hosts may settle for a sample rate or buffer size close to the requested one unless
+    /// `StreamConfig::strict` is set. Duplex streams report their output half. Returns `None` if
+    /// the host does not report it.
+    fn negotiated_config(&self) -> Option<NegotiatedStreamConfig> {
+        None
+    }
+
+    /// The time it currently takes audio captured by the device to reach the data callback.
+    ///
+    /// The value may change while the stream runs. Returns `None` for output streams, or if the
+    /// host does not report it.
+    fn input_latency(&self) -> Option<Duration> {
+        None
+    }
+
+    /// The time it currently takes audio written by the data callback to be played by the device.
+    ///
+    /// The value may change while the stream runs. Returns `None` for input streams, or if the
+    /// host does not report it.
+    fn output_latency(&self) -> Option<Duration> {
+        None
+    }
+}
+
+// The supported config in which to open an adapted stream, preferring the callback's own format.
+fn native_config<I>(
+    configs: I,
+    config: &StreamConfig,
+    preferred: SampleFormat,
+) -> Result<SupportedStreamConfigRange, BuildStreamError>
+where
+    I: Iterator<Item = SupportedStreamConfigRange>,
+{
+    let matching: Vec<_> = configs
+        .filter(|range| {
+            range.channels() == config.channels && range.supports_sample_rate(config.sample_rate)
+        })
+        .collect();
+    if let Some(range) = matching.iter().find(|r| r.sample_format() == preferred) {
+        return Ok(range.clone());
+    }
+    matching
+        .into_iter()
+        .max_by(|a, b| a.cmp_default_heuristics(b))
+        .ok_or(BuildStreamError::StreamConfigNotSupported)
+}
+
+// Frames to allocate for adapting a stream up front: the requested buffer size, or else the
+// largest buffer the device reports, within reason. Hosts that deliver more frames are adapted in
+// chunks of this size.
+fn scratch_frames(
+    config: &StreamConfig,
+    native: &SupportedStreamConfigRange,
+) -> Result<usize, BuildStreamError> {
+    const DEFAULT_FRAMES: u32 = 8192;
+    let frames = match (&config.buffer_size, native.buffer_size()) {
+        (BufferSize::Fixed(frames), _) => *frames,
+        (
+            BufferSize::Periods {
+                period_size,
+                periods,
+            },
+            _,
+        ) => period_size.checked_mul(*periods).ok_or_else(|| {
+            BuildStreamError::ParameterNotSupported(RejectedParameter::BufferSize {
+                requested: config.buffer_size.clone(),
+                supported: native.buffer_size().clone(),
+            })
+        })?,
+        (BufferSize::Latency(latency), _) => duration_to_frames(*latency, config.sample_rate),
+        (BufferSize::Default, SupportedBufferSize::Range { max, .. }) => DEFAULT_FRAMES.min(*max),
+        (BufferSize::Default, SupportedBufferSize::Unknown) => DEFAULT_FRAMES,
+    };
+    Ok(frames as usize)
+}
+
+// Rejects a config without exactly the `channels` that each frame of the callback holds.
+fn check_frame_channels(config: &StreamConfig, channels: usize) -> Result<(), BuildStreamError> {
+    if config.channels as usize == channels {
+        return Ok(());
+    }
+    let channels = channels as ChannelCount;
+    Err(BuildStreamError::ParameterNotSupported(
+        RejectedParameter::Channels {
+            requested: config.channels,
+            min: channels,
+            max: channels,
+        },
+    ))
+}
+
+fn check_dither(config: &StreamConfig, dither: Option<&Dither>) {
+    if let Some(dither) = dither {
+        assert_eq!(
+            dither.channels(),
+            config.channels,
+            "the dither must be for the channels of the stream"
+        );
+    }
+}
+
+// The time from the start of a host buffer to the chunk starting `frames` into it.
+fn chunk_offset(frames: usize, sample_rate: SampleRate) -> Duration {
+    Duration::from_secs_f64(frames as f64 / sample_rate.0 as f64)
 }